@@ -0,0 +1,30 @@
+//! Generate the C header for the bindings in `src/`, so it never drifts out
+//! of sync with the Rust side.
+//!
+//! The header is written to `OUT_DIR`, and its path is passed on to the crate
+//! as `SIMBELMYNE_HEADER`. Copying it over the checked-in
+//! `include/simbelmyne.h` is an explicit step (see `tests/header.rs`), so
+//! building the crate never touches the source tree.
+
+use std::path::PathBuf;
+
+fn main() {
+  println!("cargo:rerun-if-changed=src");
+  println!("cargo:rerun-if-changed=cbindgen.toml");
+
+  let crate_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+  let header = out_dir.join("simbelmyne.h");
+
+  let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml"))
+    .expect("Failed to read cbindgen.toml");
+
+  cbindgen::Builder::new()
+    .with_crate(&crate_dir)
+    .with_config(config)
+    .generate()
+    .expect("Failed to generate C header")
+    .write_to_file(&header);
+
+  println!("cargo:rustc-env=SIMBELMYNE_HEADER={}", header.display());
+}
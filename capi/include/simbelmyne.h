@@ -0,0 +1,182 @@
+#ifndef SIMBELMYNE_H
+#define SIMBELMYNE_H
+
+/* This file is generated by the build script. Do not edit it by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// An upper bound on the number of legal moves in any position, useful for
+// sizing the buffer passed to `simbelmyne_board_legal_moves`.
+#define SIMBELMYNE_MAX_MOVES 256
+
+// A chess position, along with the moves that led up to it
+//
+// Keeping track of the history lets the search detect repetitions when the
+// board is passed to `simbelmyne_engine_set_position`.
+typedef struct SimbelmyneBoard SimbelmyneBoard;
+
+// An engine that runs searches on a background thread
+typedef struct SimbelmyneEngine SimbelmyneEngine;
+
+// The outcome of a search
+typedef struct SimbelmyneSearchResult {
+  // The best move found
+  uint16_t best_move;
+  // The score of the position, in centipawns from the side to move's
+  // perspective. Scaled so that 100cp corresponds to a 50% chance of
+  // winning.
+  int32_t score;
+  // The number of moves until mate (negative when getting mated), or 0 if
+  // there's no mate in sight.
+  int32_t mate;
+  // The depth that was searched to
+  uint32_t depth;
+  // The number of nodes searched
+  uint64_t nodes;
+} SimbelmyneSearchResult;
+
+// Create a board with the standard starting position.
+struct SimbelmyneBoard *simbelmyne_board_new(void);
+
+// Create a board from a FEN string. Returns NULL if the FEN is invalid.
+//
+// # Safety
+// `fen` should be a NUL-terminated string.
+struct SimbelmyneBoard *simbelmyne_board_from_fen(const char *fen);
+
+// Release a board. Passing NULL is a no-op.
+//
+// # Safety
+// `board` should be NULL or a pointer returned by one of the board
+// constructors, that hasn't been freed yet.
+void simbelmyne_board_free(struct SimbelmyneBoard *board);
+
+// Write the board's FEN into `buf`, and return the length of the FEN.
+//
+// # Safety
+// `board` should be a valid board, and `buf` should be valid for `len` bytes.
+size_t simbelmyne_board_to_fen(const struct SimbelmyneBoard *board, char *buf, size_t len);
+
+// Return the side to move: 0 for White, 1 for Black.
+//
+// # Safety
+// `board` should be a valid board.
+int32_t simbelmyne_board_side_to_move(const struct SimbelmyneBoard *board);
+
+// Return whether the side to move is in check.
+//
+// # Safety
+// `board` should be a valid board.
+bool simbelmyne_board_in_check(const struct SimbelmyneBoard *board);
+
+// Write up to `capacity` legal moves into `moves`, and return the total
+// number of legal moves.
+//
+// # Safety
+// `board` should be a valid board, and `moves` should be valid for
+// `capacity` elements.
+size_t simbelmyne_board_legal_moves(const struct SimbelmyneBoard *board,
+                                    uint16_t *moves,
+                                    size_t capacity);
+
+// Play a move on the board. Returns 0 on success, and -1 if the move isn't
+// legal in the current position.
+//
+// # Safety
+// `board` should be a valid board.
+int32_t simbelmyne_board_play_move(struct SimbelmyneBoard *board, uint16_t mv);
+
+// Find the legal move corresponding to a move in UCI notation (e.g.,
+// "e2e4", or "e7e8q"). Returns 0 if there is no such legal move.
+//
+// # Safety
+// `board` should be a valid board, and `uci` a NUL-terminated string.
+uint16_t simbelmyne_board_parse_move(const struct SimbelmyneBoard *board, const char *uci);
+
+// Write a move in UCI notation into `buf`, and return the length of the
+// move string.
+//
+// # Safety
+// `buf` should be valid for `len` bytes.
+size_t simbelmyne_move_to_uci(uint16_t mv, char *buf, size_t len);
+
+// Count the number of leaf nodes at the given depth.
+//
+// # Safety
+// `board` should be a valid board.
+uint64_t simbelmyne_board_perft(const struct SimbelmyneBoard *board, uint32_t depth);
+
+// Create an engine, and spin up its search thread.
+struct SimbelmyneEngine *simbelmyne_engine_new(void);
+
+// Release an engine, stopping any search that's still running. Passing
+// NULL is a no-op.
+//
+// # Safety
+// `engine` should be NULL or a pointer returned by `simbelmyne_engine_new`
+// that hasn't been freed yet.
+void simbelmyne_engine_free(struct SimbelmyneEngine *engine);
+
+// Set an engine option, using the UCI option names (e.g., "Hash", or
+// "Threads"). Returns 0 on success, and -1 if the option or value is
+// invalid.
+//
+// # Safety
+// `engine` should be a valid engine, `name` and `value` NUL-terminated
+// strings.
+int32_t simbelmyne_engine_set_option(struct SimbelmyneEngine *engine,
+                                     const char *name,
+                                     const char *value);
+
+// Set the position to search. The board is copied, so it can be freed or
+// modified afterwards.
+//
+// # Safety
+// `engine` should be a valid engine, and `board` a valid board.
+void simbelmyne_engine_set_position(struct SimbelmyneEngine *engine,
+                                    const struct SimbelmyneBoard *board);
+
+// Clear the engine's transposition and history tables, in preparation of
+// a new game.
+//
+// # Safety
+// `engine` should be a valid engine.
+void simbelmyne_engine_new_game(struct SimbelmyneEngine *engine);
+
+// Start searching the current position in the background. The search is
+// limited to `depth` plies and `movetime_ms` milliseconds, where a limit
+// of 0 means no limit. Without any limits, the search runs until stopped.
+//
+// # Safety
+// `engine` should be a valid engine.
+int32_t simbelmyne_engine_go(struct SimbelmyneEngine *engine, uint32_t depth, uint64_t movetime_ms);
+
+// Stop the running search. The search still reports the best move it found
+// so far.
+//
+// # Safety
+// `engine` should be a valid engine.
+void simbelmyne_engine_stop(struct SimbelmyneEngine *engine);
+
+// Check whether the last search has finished. Returns 1 and fills in
+// `result` if it has, 0 if it's still running, and -1 if no search was
+// started.
+//
+// # Safety
+// `engine` should be a valid engine, and `result` a valid pointer.
+int32_t simbelmyne_engine_poll(const struct SimbelmyneEngine *engine,
+                               struct SimbelmyneSearchResult *result);
+
+// Block until the last search has finished, and fill in `result`. Returns
+// 0 on success, and -1 if no search was started.
+//
+// # Safety
+// `engine` should be a valid engine, and `result` a valid pointer.
+int32_t simbelmyne_engine_wait(const struct SimbelmyneEngine *engine,
+                               struct SimbelmyneSearchResult *result);
+
+#endif /* SIMBELMYNE_H */
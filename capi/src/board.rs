@@ -0,0 +1,207 @@
+//! Bindings for setting up positions, and generating and playing moves
+
+use crate::read_str;
+use crate::write_str;
+use chess::board::Board;
+use chess::movegen::legal_moves::All;
+use chess::movegen::moves::BareMove;
+use chess::movegen::moves::Move;
+use chess::movegen::sliders;
+use engine::position::Position;
+use std::ffi::c_char;
+
+/// An upper bound on the number of legal moves in any position, useful for
+/// sizing the buffer passed to `simbelmyne_board_legal_moves`.
+pub const SIMBELMYNE_MAX_MOVES: usize = 256;
+
+/// A chess position, along with the moves that led up to it
+///
+/// Keeping track of the history lets the search detect repetitions when the
+/// board is passed to `simbelmyne_engine_set_position`.
+pub struct SimbelmyneBoard {
+  pub(crate) position: Position,
+}
+
+/// Create a board with the standard starting position.
+#[no_mangle]
+pub extern "C" fn simbelmyne_board_new() -> *mut SimbelmyneBoard {
+  sliders::init();
+  Box::into_raw(Box::new(SimbelmyneBoard {
+    position: Position::new(Board::default()),
+  }))
+}
+
+/// Create a board from a FEN string. Returns NULL if the FEN is invalid.
+///
+/// # Safety
+/// `fen` should be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_board_from_fen(
+  fen: *const c_char,
+) -> *mut SimbelmyneBoard {
+  sliders::init();
+
+  let Some(board) = read_str(fen).and_then(|fen| fen.parse::<Board>().ok())
+  else {
+    return std::ptr::null_mut();
+  };
+
+  Box::into_raw(Box::new(SimbelmyneBoard {
+    position: Position::new(board),
+  }))
+}
+
+/// Release a board. Passing NULL is a no-op.
+///
+/// # Safety
+/// `board` should be NULL or a pointer returned by one of the board
+/// constructors, that hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_board_free(board: *mut SimbelmyneBoard) {
+  if !board.is_null() {
+    drop(Box::from_raw(board));
+  }
+}
+
+/// Write the board's FEN into `buf`, and return the length of the FEN.
+///
+/// # Safety
+/// `board` should be a valid board, and `buf` should be valid for `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_board_to_fen(
+  board: *const SimbelmyneBoard,
+  buf: *mut c_char,
+  len: usize,
+) -> usize {
+  let Some(board) = board.as_ref() else {
+    return 0;
+  };
+
+  write_str(&board.position.board.to_fen(), buf, len)
+}
+
+/// Return the side to move: 0 for White, 1 for Black.
+///
+/// # Safety
+/// `board` should be a valid board.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_board_side_to_move(
+  board: *const SimbelmyneBoard,
+) -> i32 {
+  match board.as_ref() {
+    Some(board) if board.position.board.current.is_white() => 0,
+    _ => 1,
+  }
+}
+
+/// Return whether the side to move is in check.
+///
+/// # Safety
+/// `board` should be a valid board.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_board_in_check(
+  board: *const SimbelmyneBoard,
+) -> bool {
+  board
+    .as_ref()
+    .is_some_and(|board| board.position.board.in_check())
+}
+
+/// Write up to `capacity` legal moves into `moves`, and return the total
+/// number of legal moves.
+///
+/// # Safety
+/// `board` should be a valid board, and `moves` should be valid for
+/// `capacity` elements.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_board_legal_moves(
+  board: *const SimbelmyneBoard,
+  moves: *mut u16,
+  capacity: usize,
+) -> usize {
+  let Some(board) = board.as_ref() else {
+    return 0;
+  };
+
+  let legal_moves = board.position.board.legal_moves::<All>();
+
+  if !moves.is_null() {
+    for (i, mv) in legal_moves.iter().take(capacity).enumerate() {
+      *moves.add(i) = mv.to_u16();
+    }
+  }
+
+  legal_moves.len()
+}
+
+/// Play a move on the board. Returns 0 on success, and -1 if the move isn't
+/// legal in the current position.
+///
+/// # Safety
+/// `board` should be a valid board.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_board_play_move(
+  board: *mut SimbelmyneBoard,
+  mv: u16,
+) -> i32 {
+  let Some(board) = board.as_mut() else {
+    return -1;
+  };
+
+  let mv = Move::from_u16(mv);
+
+  if !board.position.board.legal_moves::<All>().contains(&mv) {
+    return -1;
+  }
+
+  board.position = board.position.play_move(mv);
+  0
+}
+
+/// Find the legal move corresponding to a move in UCI notation (e.g.,
+/// "e2e4", or "e7e8q"). Returns 0 if there is no such legal move.
+///
+/// # Safety
+/// `board` should be a valid board, and `uci` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_board_parse_move(
+  board: *const SimbelmyneBoard,
+  uci: *const c_char,
+) -> u16 {
+  let Some(board) = board.as_ref() else {
+    return 0;
+  };
+
+  read_str(uci)
+    .and_then(|uci| uci.parse::<BareMove>().ok())
+    .and_then(|bare| board.position.board.find_move(bare))
+    .map_or(0, Move::to_u16)
+}
+
+/// Write a move in UCI notation into `buf`, and return the length of the
+/// move string.
+///
+/// # Safety
+/// `buf` should be valid for `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_move_to_uci(
+  mv: u16,
+  buf: *mut c_char,
+  len: usize,
+) -> usize {
+  write_str(&Move::from_u16(mv).to_string(), buf, len)
+}
+
+/// Count the number of leaf nodes at the given depth.
+///
+/// # Safety
+/// `board` should be a valid board.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_board_perft(
+  board: *const SimbelmyneBoard,
+  depth: u32,
+) -> u64 {
+  board
+    .as_ref()
+    .map_or(0, |board| board.position.board.perft(depth as usize))
+}
@@ -0,0 +1,55 @@
+//! C bindings for Simbelmyne
+//!
+//! Exposes a small, stable C API around the move generator and the search, so
+//! the engine can be used from non-Rust tooling without spawning a process.
+//! The corresponding header is generated by the build script, and a copy is
+//! checked in at `include/simbelmyne.h`. A test makes sure the copy is up to
+//! date (see `tests/header.rs` for how to update it).
+//!
+//! Conventions:
+//! - Boards and engines are opaque pointers, created by a `*_new` function
+//!   and released with the matching `*_free` function.
+//! - Moves are passed around as `uint16_t` codes, which can be converted from
+//!   and to UCI notation with `simbelmyne_board_parse_move` and
+//!   `simbelmyne_move_to_uci`. The code 0 never represents a legal move.
+//! - Functions that can fail return 0 on success and a negative value on
+//!   failure.
+//! - Strings are written into caller-provided buffers, and are always
+//!   NUL-terminated when the buffer is non-empty. The return value is the
+//!   length of the full string, like `snprintf`.
+
+use std::ffi::c_char;
+use std::ffi::CStr;
+
+mod board;
+mod search;
+
+pub use board::*;
+pub use search::*;
+
+/// Read a C string into a Rust `&str`, if it's valid UTF-8.
+///
+/// # Safety
+/// The pointer should either be null, or point to a NUL-terminated string.
+unsafe fn read_str<'a>(ptr: *const c_char) -> Option<&'a str> {
+  if ptr.is_null() {
+    return None;
+  }
+
+  CStr::from_ptr(ptr).to_str().ok()
+}
+
+/// Copy a string into a caller-provided buffer, truncating it if needed, and
+/// return the length of the full string.
+///
+/// # Safety
+/// The buffer should either be null, or be valid for `len` bytes.
+unsafe fn write_str(s: &str, buf: *mut c_char, len: usize) -> usize {
+  if !buf.is_null() && len > 0 {
+    let count = s.len().min(len - 1);
+    std::ptr::copy_nonoverlapping(s.as_ptr() as *const c_char, buf, count);
+    *buf.add(count) = 0;
+  }
+
+  s.len()
+}
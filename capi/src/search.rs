@@ -0,0 +1,252 @@
+//! Bindings for running searches in the background
+
+use crate::board::SimbelmyneBoard;
+use crate::read_str;
+use chess::board::Board;
+use chess::movegen::sliders;
+use engine::engine::Engine;
+use engine::engine::Limits;
+use engine::engine::SearchHandle;
+use engine::search::SearchReport;
+use std::ffi::c_char;
+use std::time::Duration;
+use uci::search_info::Score;
+use uci::search_info::SearchInfo;
+use uci::time_control::TimeControl;
+use uci::wdl::WDL_MODEL;
+
+/// An engine that runs searches on a background thread
+pub struct SimbelmyneEngine {
+  engine: Engine,
+
+  /// The last search that was started, along with the board it searched.
+  /// The engine's position may have changed since, so we hold on to the
+  /// board to interpret the results.
+  search: Option<(SearchHandle, Board)>,
+}
+
+/// The outcome of a search
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SimbelmyneSearchResult {
+  /// The best move found
+  pub best_move: u16,
+
+  /// The score of the position, in centipawns from the side to move's
+  /// perspective. Scaled so that 100cp corresponds to a 50% chance of
+  /// winning.
+  pub score: i32,
+
+  /// The number of moves until mate (negative when getting mated), or 0 if
+  /// there's no mate in sight.
+  pub mate: i32,
+
+  /// The depth that was searched to
+  pub depth: u32,
+
+  /// The number of nodes searched
+  pub nodes: u64,
+}
+
+/// Create an engine, and spin up its search thread.
+#[no_mangle]
+pub extern "C" fn simbelmyne_engine_new() -> *mut SimbelmyneEngine {
+  sliders::init();
+  Box::into_raw(Box::new(SimbelmyneEngine {
+    engine: Engine::new(),
+    search: None,
+  }))
+}
+
+/// Release an engine, stopping any search that's still running. Passing
+/// NULL is a no-op.
+///
+/// # Safety
+/// `engine` should be NULL or a pointer returned by `simbelmyne_engine_new`
+/// that hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_engine_free(engine: *mut SimbelmyneEngine) {
+  if !engine.is_null() {
+    drop(Box::from_raw(engine));
+  }
+}
+
+/// Set an engine option, using the UCI option names (e.g., "Hash", or
+/// "Threads"). Returns 0 on success, and -1 if the option or value is
+/// invalid.
+///
+/// # Safety
+/// `engine` should be a valid engine, `name` and `value` NUL-terminated
+/// strings.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_engine_set_option(
+  engine: *mut SimbelmyneEngine,
+  name: *const c_char,
+  value: *const c_char,
+) -> i32 {
+  let (Some(engine), Some(name), Some(value)) =
+    (engine.as_mut(), read_str(name), read_str(value))
+  else {
+    return -1;
+  };
+
+  match engine.engine.set_option(name, value) {
+    Ok(()) => 0,
+    Err(_) => -1,
+  }
+}
+
+/// Set the position to search. The board is copied, so it can be freed or
+/// modified afterwards.
+///
+/// # Safety
+/// `engine` should be a valid engine, and `board` a valid board.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_engine_set_position(
+  engine: *mut SimbelmyneEngine,
+  board: *const SimbelmyneBoard,
+) {
+  if let (Some(engine), Some(board)) = (engine.as_mut(), board.as_ref()) {
+    engine.engine.set_position(board.position.clone());
+  }
+}
+
+/// Clear the engine's transposition and history tables, in preparation of
+/// a new game.
+///
+/// # Safety
+/// `engine` should be a valid engine.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_engine_new_game(
+  engine: *mut SimbelmyneEngine,
+) {
+  if let Some(engine) = engine.as_mut() {
+    engine.engine.new_game();
+    engine.search = None;
+  }
+}
+
+/// Start searching the current position in the background. The search is
+/// limited to `depth` plies and `movetime_ms` milliseconds, where a limit
+/// of 0 means no limit. Without any limits, the search runs until stopped.
+///
+/// # Safety
+/// `engine` should be a valid engine.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_engine_go(
+  engine: *mut SimbelmyneEngine,
+  depth: u32,
+  movetime_ms: u64,
+) -> i32 {
+  let Some(engine) = engine.as_mut() else {
+    return -1;
+  };
+
+  let time_control = match movetime_ms {
+    0 => TimeControl::Infinite,
+    ms => TimeControl::FixedTime(Duration::from_millis(ms)),
+  };
+
+  let limits = Limits {
+    time_control,
+    depth: (depth > 0).then_some(depth as usize),
+  };
+
+  let board = engine.engine.position().board;
+  engine.search = Some((engine.engine.go(limits), board));
+  0
+}
+
+/// Stop the running search. The search still reports the best move it found
+/// so far.
+///
+/// # Safety
+/// `engine` should be a valid engine.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_engine_stop(engine: *mut SimbelmyneEngine) {
+  if let Some(engine) = engine.as_ref() {
+    engine.engine.stop();
+  }
+}
+
+/// Check whether the last search has finished. Returns 1 and fills in
+/// `result` if it has, 0 if it's still running, and -1 if no search was
+/// started.
+///
+/// # Safety
+/// `engine` should be a valid engine, and `result` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_engine_poll(
+  engine: *const SimbelmyneEngine,
+  result: *mut SimbelmyneSearchResult,
+) -> i32 {
+  let Some(engine) = engine.as_ref() else {
+    return -1;
+  };
+
+  let Some((search, board)) = &engine.search else {
+    return -1;
+  };
+
+  match search.try_report() {
+    Some(report) => {
+      write_result(board, &report, result);
+      1
+    }
+
+    None => 0,
+  }
+}
+
+/// Block until the last search has finished, and fill in `result`. Returns
+/// 0 on success, and -1 if no search was started.
+///
+/// # Safety
+/// `engine` should be a valid engine, and `result` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn simbelmyne_engine_wait(
+  engine: *const SimbelmyneEngine,
+  result: *mut SimbelmyneSearchResult,
+) -> i32 {
+  let Some(engine) = engine.as_ref() else {
+    return -1;
+  };
+
+  let Some((search, board)) = &engine.search else {
+    return -1;
+  };
+
+  write_result(board, &search.wait(), result);
+  0
+}
+
+/// Translate the report of a search on `board` into a
+/// `SimbelmyneSearchResult`
+///
+/// # Safety
+/// `result` should be NULL or a valid pointer.
+unsafe fn write_result(
+  board: &Board,
+  report: &SearchReport,
+  result: *mut SimbelmyneSearchResult,
+) {
+  let Some(result) = result.as_mut() else {
+    return;
+  };
+
+  let wdl_params = WDL_MODEL.params(board);
+
+  let (score, mate) = match SearchInfo::from(report).score {
+    Some(Score::Cp(cp)) => (wdl_params.wdl_normalized(cp), 0),
+    Some(Score::Mate(n)) => (0, n),
+    None => (0, 0),
+  };
+
+  *result = SimbelmyneSearchResult {
+    best_move: report.pv.first().map_or(0, |mv| mv.to_u16()),
+    score,
+    mate,
+    depth: report.depth as u32,
+    nodes: report.nodes as u64,
+  };
+}
@@ -0,0 +1,111 @@
+// Exercise the C API the way a C consumer would: through the generated header
+// and the shared library.
+
+#include <stdio.h>
+#include <string.h>
+
+#include "simbelmyne.h"
+
+#define CHECK(cond)                                                        \
+  do {                                                                     \
+    if (!(cond)) {                                                         \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__,     \
+              #cond);                                                      \
+      return 1;                                                            \
+    }                                                                      \
+  } while (0)
+
+static const char *KIWIPETE =
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+static int test_board(void) {
+  SimbelmyneBoard *board = simbelmyne_board_new();
+  CHECK(board != NULL);
+  CHECK(simbelmyne_board_side_to_move(board) == 0);
+  CHECK(!simbelmyne_board_in_check(board));
+
+  uint16_t moves[SIMBELMYNE_MAX_MOVES];
+  CHECK(simbelmyne_board_legal_moves(board, moves, SIMBELMYNE_MAX_MOVES) == 20);
+  CHECK(simbelmyne_board_perft(board, 3) == 8902);
+
+  // Round-trip a move through UCI notation
+  uint16_t e4 = simbelmyne_board_parse_move(board, "e2e4");
+  CHECK(e4 != 0);
+
+  char buf[128];
+  CHECK(simbelmyne_move_to_uci(e4, buf, sizeof buf) == 4);
+  CHECK(strcmp(buf, "e2e4") == 0);
+
+  // Illegal moves are rejected, and leave the board untouched
+  CHECK(simbelmyne_board_parse_move(board, "e2e5") == 0);
+  CHECK(simbelmyne_board_play_move(board, 0) == -1);
+
+  CHECK(simbelmyne_board_play_move(board, e4) == 0);
+  CHECK(simbelmyne_board_side_to_move(board) == 1);
+  CHECK(simbelmyne_board_play_move(board, e4) == -1);
+
+  size_t len = simbelmyne_board_to_fen(board, buf, sizeof buf);
+  CHECK(len == strlen(buf));
+  CHECK(strncmp(buf, "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b", 47) == 0);
+
+  // Truncated output is still NUL-terminated
+  char small[8];
+  CHECK(simbelmyne_board_to_fen(board, small, sizeof small) == len);
+  CHECK(strlen(small) == sizeof small - 1);
+
+  simbelmyne_board_free(board);
+
+  CHECK(simbelmyne_board_from_fen("not a fen") == NULL);
+
+  board = simbelmyne_board_from_fen(KIWIPETE);
+  CHECK(board != NULL);
+  CHECK(simbelmyne_board_legal_moves(board, NULL, 0) == 48);
+  CHECK(simbelmyne_board_perft(board, 2) == 2039);
+  simbelmyne_board_free(board);
+
+  return 0;
+}
+
+static int test_search(void) {
+  SimbelmyneEngine *engine = simbelmyne_engine_new();
+  CHECK(engine != NULL);
+  CHECK(simbelmyne_engine_set_option(engine, "Hash", "4") == 0);
+  CHECK(simbelmyne_engine_set_option(engine, "NoSuchOption", "1") == -1);
+
+  SimbelmyneSearchResult result;
+  CHECK(simbelmyne_engine_poll(engine, &result) == -1);
+
+  // Mate in one: the engine should find Qh5xf7#
+  SimbelmyneBoard *board = simbelmyne_board_from_fen(
+      "r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4");
+  CHECK(board != NULL);
+  simbelmyne_engine_set_position(engine, board);
+
+  CHECK(simbelmyne_engine_go(engine, 6, 0) == 0);
+  CHECK(simbelmyne_engine_wait(engine, &result) == 0);
+  CHECK(result.best_move == simbelmyne_board_parse_move(board, "h5f7"));
+  CHECK(result.mate == 1);
+  CHECK(result.depth == 6);
+  simbelmyne_board_free(board);
+
+  // An infinite search keeps running until it's stopped
+  simbelmyne_engine_new_game(engine);
+  CHECK(simbelmyne_engine_go(engine, 0, 0) == 0);
+  CHECK(simbelmyne_engine_poll(engine, &result) == 0);
+  simbelmyne_engine_stop(engine);
+  CHECK(simbelmyne_engine_wait(engine, &result) == 0);
+  CHECK(result.best_move != 0);
+  CHECK(simbelmyne_engine_poll(engine, &result) == 1);
+
+  simbelmyne_engine_free(engine);
+  return 0;
+}
+
+int main(void) {
+  if (test_board() != 0 || test_search() != 0) {
+    return 1;
+  }
+
+  printf("ok\n");
+  return 0;
+}
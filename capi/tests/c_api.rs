@@ -0,0 +1,50 @@
+//! Compile the C test program against the generated header and the shared
+//! library, and run it.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn c_test_program() {
+  let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+  // Integration tests live in `target/<profile>/deps`, next to the shared
+  // library that cargo built for this crate.
+  let lib_dir = std::env::current_exe()
+    .unwrap()
+    .parent()
+    .unwrap()
+    .to_path_buf();
+
+  let out_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+  let exe = out_dir.join("test_capi");
+  let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+
+  let status = Command::new(cc)
+    .arg(manifest_dir.join("tests/c/test_capi.c"))
+    .arg("-I")
+    .arg(manifest_dir.join("include"))
+    .arg("-L")
+    .arg(&lib_dir)
+    .arg("-lsimbelmyne")
+    .arg("-Wall")
+    .arg("-Werror")
+    .arg("-o")
+    .arg(&exe)
+    .status()
+    .expect("Failed to run the C compiler");
+
+  assert!(status.success(), "Failed to compile the C test program");
+
+  let output = Command::new(&exe)
+    .env("LD_LIBRARY_PATH", &lib_dir)
+    .env("DYLD_LIBRARY_PATH", &lib_dir)
+    .output()
+    .expect("Failed to run the C test program");
+
+  assert!(
+    output.status.success(),
+    "C test program failed:\n{}",
+    String::from_utf8_lossy(&output.stderr)
+  );
+}
@@ -0,0 +1,30 @@
+//! Check that the checked-in header matches the one generated by the build
+//! script.
+//!
+//! To update the checked-in header after changing the bindings, run
+//!
+//! ```sh
+//! UPDATE_HEADER=1 cargo test -p simbelmyne-capi --test header
+//! ```
+
+use std::path::PathBuf;
+
+#[test]
+fn header_is_up_to_date() {
+  let generated = PathBuf::from(env!("SIMBELMYNE_HEADER"));
+  let checked_in =
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("include/simbelmyne.h");
+
+  if std::env::var_os("UPDATE_HEADER").is_some() {
+    std::fs::copy(&generated, &checked_in).expect("Failed to copy the header");
+  }
+
+  let generated = std::fs::read_to_string(generated).unwrap();
+  let checked_in = std::fs::read_to_string(checked_in).unwrap();
+
+  assert!(
+    generated == checked_in,
+    "include/simbelmyne.h is out of date, run \
+     `UPDATE_HEADER=1 cargo test -p simbelmyne-capi --test header`"
+  );
+}
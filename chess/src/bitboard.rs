@@ -190,6 +190,20 @@ impl Bitboard {
       self << 9 & !FILES[0]
     }
   }
+
+  /// Iterate over every subset of the bitboard, starting with the empty set,
+  /// using the Carry-Rippler trick.
+  pub fn subsets(self) -> impl Iterator<Item = Bitboard> {
+    let mask = self.0;
+    let mut subset = Some(0u64);
+
+    std::iter::from_fn(move || {
+      let current = subset?;
+      let next = current.wrapping_sub(mask) & mask;
+      subset = (next != 0).then_some(next);
+      Some(Bitboard(current))
+    })
+  }
 }
 
 ///////////////////////////////////////////////////////////////////////////////
@@ -10,19 +10,26 @@ use crate::constants::LIGHT_SQUARES;
 use crate::constants::RANKS;
 use crate::movegen::castling::CastlingRights;
 use crate::movegen::lookups::BETWEEN;
+use crate::movegen::sliders::Magics;
+use crate::movegen::sliders::Sliders;
 use crate::piece::Color;
 use crate::piece::Piece;
 use crate::piece::PieceType;
 use crate::square::Square;
+use crate::zobrist::ZHash;
 use colored::Colorize;
 use std::fmt::Display;
+use std::marker::PhantomData;
 use std::str::FromStr;
 
 const WHITE: bool = true;
 const BLACK: bool = false;
 
+/// The board is generic over the backend it uses to look up slider attacks
+/// (see `movegen::sliders`). Boards are always created with magic bitboards,
+/// and can be converted with `Board::with_sliders`.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub struct Board {
+pub struct Board<S: Sliders = Magics> {
   /// The color of the current player
   pub current: Color,
 
@@ -61,6 +68,12 @@ pub struct Board {
 
   /// Mask of all squares attacked by the opponent
   pub threats: Bitboard,
+
+  /// The Zobrist hash of the board, updated incrementally as moves are played
+  pub(crate) hash: ZHash,
+
+  /// The backend used to look up slider attacks
+  sliders: PhantomData<S>,
 }
 
 impl Board {
@@ -87,8 +100,12 @@ impl Board {
       diag_pinrays: [Bitboard::EMPTY; 2],
       checkers: Bitboard::EMPTY,
       threats: Bitboard::EMPTY,
+      hash: ZHash::NULL,
+      sliders: PhantomData,
     };
 
+    board.hash = board.compute_hash();
+
     board.hv_pinrays = [
       board.compute_hv_pinrays::<WHITE>(),
       board.compute_hv_pinrays::<BLACK>(),
@@ -104,6 +121,28 @@ impl Board {
 
     board
   }
+}
+
+impl<S: Sliders> Board<S> {
+  /// Use a different backend to look up slider attacks.
+  pub fn with_sliders<T: Sliders>(self) -> Board<T> {
+    Board {
+      current: self.current,
+      piece_bbs: self.piece_bbs,
+      occupied_squares: self.occupied_squares,
+      piece_list: self.piece_list,
+      castling_rights: self.castling_rights,
+      en_passant: self.en_passant,
+      half_moves: self.half_moves,
+      full_moves: self.full_moves,
+      hv_pinrays: self.hv_pinrays,
+      diag_pinrays: self.diag_pinrays,
+      checkers: self.checkers,
+      threats: self.threats,
+      hash: self.hash,
+      sliders: PhantomData,
+    }
+  }
 
   /// Get the occupation bitboard for a given side.
   #[inline(always)]
@@ -144,6 +183,8 @@ impl Board {
     let bb: Bitboard = square.into();
     self.occupied_squares[piece.color()] |= bb;
     self.piece_bbs[piece.piece_type()] |= bb;
+
+    self.hash.toggle_piece(piece, square);
   }
 
   /// Remove a piece on a given square
@@ -156,9 +197,17 @@ impl Board {
     self.occupied_squares[piece.color()] &= !bb;
     self.piece_bbs[piece.piece_type()] &= !bb;
 
+    self.hash.toggle_piece(piece, square);
+
     Some(piece)
   }
 
+  /// The Zobrist hash of the board
+  #[inline(always)]
+  pub fn hash(&self) -> ZHash {
+    self.hash
+  }
+
   #[inline(always)]
   pub fn pawns(&self, side: Color) -> Bitboard {
     self.piece_bbs[PieceType::Pawn] & self.occupied_by(side)
@@ -263,7 +312,7 @@ impl Board {
 //
 ////////////////////////////////////////////////////////////////////////////////
 
-impl Board {
+impl<S: Sliders> Board<S> {
   /// Calculate a map of squares attacked by the requested color
   pub fn attacked_squares(&self, us: Color) -> Bitboard {
     let mut attacked = Bitboard(0);
@@ -276,11 +325,11 @@ impl Board {
     }
 
     for square in self.diag_sliders(us) {
-      attacked |= square.bishop_squares(blockers);
+      attacked |= S::bishop_squares(square, blockers);
     }
 
     for square in self.hv_sliders(us) {
-      attacked |= square.rook_squares(blockers);
+      attacked |= S::rook_squares(square, blockers);
     }
 
     for square in self.kings(us) {
@@ -309,11 +358,11 @@ impl Board {
     }
 
     for square in self.diag_sliders(!us) {
-      attacked |= square.bishop_squares(blockers);
+      attacked |= S::bishop_squares(square, blockers);
     }
 
     for square in self.hv_sliders(!us) {
-      attacked |= square.rook_squares(blockers);
+      attacked |= S::rook_squares(square, blockers);
     }
 
     for square in self.kings(!us) {
@@ -354,8 +403,29 @@ impl Board {
 
     (self.pawns(them) & blockers & our_king.pawn_attacks(us))
       | (self.knights(them) & our_king.knight_squares())
-      | (self.diag_sliders(them) & our_king.bishop_squares(blockers))
-      | (self.hv_sliders(them) & our_king.rook_squares(blockers))
+      | (self.diag_sliders(them) & S::bishop_squares(our_king, blockers))
+      | (self.hv_sliders(them) & S::rook_squares(our_king, blockers))
+  }
+
+  /// Get a bitboard for all the squares visible to a bishop on a square,
+  /// using the board's slider backend.
+  #[inline(always)]
+  pub fn bishop_squares(&self, square: Square, blockers: Bitboard) -> Bitboard {
+    S::bishop_squares(square, blockers)
+  }
+
+  /// Get a bitboard for all the squares visible to a rook on a square,
+  /// using the board's slider backend.
+  #[inline(always)]
+  pub fn rook_squares(&self, square: Square, blockers: Bitboard) -> Bitboard {
+    S::rook_squares(square, blockers)
+  }
+
+  /// Get a bitboard for all the squares visible to a queen on a square,
+  /// using the board's slider backend.
+  #[inline(always)]
+  pub fn queen_squares(&self, square: Square, blockers: Bitboard) -> Bitboard {
+    S::queen_squares(square, blockers)
   }
 
   /// Find all attackers, black or white, attacking a given square.
@@ -366,9 +436,9 @@ impl Board {
     square.pawn_attacks(Black) & self.pawns(White)
       | square.pawn_attacks(White) & self.pawns(Black)
       | square.knight_squares() & self.piece_bbs[Knight]
-      | square.bishop_squares(blockers)
+      | S::bishop_squares(square, blockers)
         & (self.piece_bbs[Bishop] | self.piece_bbs[Queen])
-      | square.rook_squares(blockers)
+      | S::rook_squares(square, blockers)
         & (self.piece_bbs[Rook] | self.piece_bbs[Queen])
       | square.king_squares() & (self.piece_bbs[King])
   }
@@ -382,7 +452,7 @@ impl Board {
     let ours = self.occupied_by(us);
     let theirs = self.occupied_by(them);
     let hv_sliders = self.hv_sliders(them);
-    let potential_pinners = king_sq.rook_squares(theirs) & hv_sliders;
+    let potential_pinners = S::rook_squares(king_sq, theirs) & hv_sliders;
 
     potential_pinners
       .map(|pinner| BETWEEN[pinner][king_sq] | pinner.into())
@@ -399,7 +469,7 @@ impl Board {
     let ours = self.occupied_by(us);
     let theirs = self.occupied_by(them);
     let diag_sliders = self.diag_sliders(them);
-    let potential_pinners = king_sq.bishop_squares(theirs) & diag_sliders;
+    let potential_pinners = S::bishop_squares(king_sq, theirs) & diag_sliders;
 
     potential_pinners
       .map(|pinner| BETWEEN[pinner][king_sq] | pinner.into())
@@ -414,7 +484,7 @@ impl Board {
 //
 ////////////////////////////////////////////////////////////////////////////////
 
-impl Board {
+impl<S: Sliders> Board<S> {
   /// Check whether the current player is in check
   pub fn in_check(&self) -> bool {
     !self.get_checkers().is_empty()
@@ -529,17 +599,39 @@ fn blank_line(rank: usize) -> String {
   line.join("")
 }
 
-impl Display for Board {
+impl<S: Sliders> Display for Board<S> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.pretty(false))
+  }
+}
+
+impl<S: Sliders> Board<S> {
+  /// Pretty-print the board, as seen from White's side, or from Black's side
+  /// when `flipped` is set.
+  pub fn pretty(&self, flipped: bool) -> String {
+    let files = if flipped {
+      "     h    g    f    e    d    c    b    a   "
+    } else {
+      "     a    b    c    d    e    f    g    h   "
+    };
+
+    let mut ranks = Square::RANKS;
+
+    if flipped {
+      ranks.reverse();
+      ranks.iter_mut().for_each(|rank| rank.reverse());
+    }
+
     let mut lines: Vec<String> = vec![];
-    lines.push("     a    b    c    d    e    f    g    h   ".to_string());
+    lines.push(files.to_string());
     lines.push("  ╔════════════════════════════════════════╗".to_string());
 
-    for (rank, squares) in Square::RANKS.into_iter().enumerate() {
+    for (rank, squares) in ranks.into_iter().enumerate() {
+      let label = if flipped { rank + 1 } else { 8 - rank };
       lines.push(blank_line(rank));
 
       let mut line: Vec<String> = vec![];
-      line.push((8 - rank).to_string());
+      line.push(label.to_string());
       line.push(" ║".to_string());
       for (file, sq) in squares.into_iter().enumerate() {
         let square = if (rank + file) % 2 == 0 {
@@ -557,16 +649,16 @@ impl Display for Board {
         line.push(square.to_string());
       }
       line.push("║ ".to_string());
-      line.push((8 - rank).to_string());
+      line.push(label.to_string());
       let line = line.join("");
       lines.push(line);
 
       lines.push(blank_line(rank));
     }
     lines.push("  ╚════════════════════════════════════════╝".to_string());
-    lines.push("     a    b    c    d    e    f    g    h   ".to_string());
+    lines.push(files.to_string());
 
-    write!(f, "{}", lines.join("\n"))
+    lines.join("\n")
   }
 }
 
@@ -576,7 +668,7 @@ impl Display for Board {
 //
 ////////////////////////////////////////////////////////////////////////////////
 
-impl Board {
+impl<S: Sliders> Board<S> {
   /// Values assignd to each piece type to calculate the approximate stage
   /// of the game
   const GAME_PHASE_VALUES: [u8; PieceType::COUNT] = [0, 1, 1, 2, 4, 0];
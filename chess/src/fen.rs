@@ -50,6 +50,7 @@ use std::str::FromStr;
 use crate::bitboard::Bitboard;
 use crate::board::Board;
 use crate::movegen::castling::CastlingRights;
+use crate::movegen::sliders::Sliders;
 use crate::piece::Color;
 use crate::piece::Piece;
 use crate::piece::PieceType;
@@ -57,7 +58,7 @@ use crate::square::Square;
 use anyhow::anyhow;
 use itertools::Itertools;
 
-impl Board {
+impl<S: Sliders> Board<S> {
   // Serialize a board into a FEN string
   pub fn to_fen(&self) -> String {
     let ranks = self.piece_list.into_iter().chunks(8);
@@ -94,7 +95,9 @@ impl Board {
 
     format!("{pieces} {next_player} {castling} {en_passant} {half_moves} {full_moves}")
   }
+}
 
+impl Board {
   // Parse a board from a FEN string
   pub fn from_fen(fen: &str) -> anyhow::Result<Board> {
     let mut parts = fen.split(' ');
@@ -0,0 +1,406 @@
+//! Keeping track of a game in progress, and deciding when it's over
+//!
+//! A `Board` only knows about the current position. Deciding whether a game
+//! has ended by repetition requires knowing every position that came before,
+//! so the `Game` keeps the full list of moves and the hashes of all the
+//! positions along the way.
+
+use crate::board::Board;
+use crate::movegen::legal_moves::All;
+use crate::movegen::moves::BareMove;
+use crate::movegen::moves::Move;
+use crate::piece::Color;
+use crate::zobrist::ZHash;
+use anyhow::anyhow;
+use std::fmt::Display;
+
+/// A game in progress, starting from some initial position
+#[derive(Debug, Clone)]
+pub struct Game {
+  /// The position the game was started from
+  initial: Board,
+
+  /// The current position
+  board: Board,
+
+  /// The moves played so far
+  moves: Vec<Move>,
+
+  /// The hashes of all the positions before the current one, starting with
+  /// the initial position
+  hashes: Vec<ZHash>,
+}
+
+/// The ways in which a game can end, as far as the rules are concerned
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameOutcome {
+  /// The side to move is checkmated
+  Checkmate { winner: Color },
+
+  /// The side to move has no legal moves, but isn't in check
+  Stalemate,
+
+  /// The current position occurred at least three times
+  ThreefoldRepetition,
+
+  /// The current position occurred at least five times
+  FivefoldRepetition,
+
+  /// Fifty moves were played by each side without a capture or pawn move
+  FiftyMoves,
+
+  /// Seventy-five moves were played by each side without a capture or pawn
+  /// move
+  SeventyFiveMoves,
+
+  /// Neither side can possibly checkmate the other
+  DeadPosition,
+}
+
+impl Game {
+  /// Start a new game from the provided position
+  pub fn new(board: Board) -> Self {
+    Self {
+      initial: board,
+      board,
+      moves: Vec::new(),
+      hashes: Vec::new(),
+    }
+  }
+
+  /// The position the game was started from
+  pub fn initial(&self) -> &Board {
+    &self.initial
+  }
+
+  /// The current position
+  pub fn board(&self) -> &Board {
+    &self.board
+  }
+
+  /// The moves played so far
+  pub fn moves(&self) -> &[Move] {
+    &self.moves
+  }
+
+  /// The hashes of all the positions before the current one, in the order
+  /// they were played.
+  ///
+  /// En-passant squares are only part of the hash when the en-passant capture
+  /// is legal, so positions that only differ by an unusable en-passant square
+  /// hash the same, as the repetition rules require.
+  pub fn hashes(&self) -> &[ZHash] {
+    &self.hashes
+  }
+
+  /// Play a move, assuming it is legal in the current position
+  pub fn play_move(&mut self, mv: Move) {
+    self.hashes.push(repetition_hash(&self.board));
+    self.moves.push(mv);
+    self.board = self.board.play_move(mv);
+  }
+
+  /// Play a bare move (e.g., one received over UCI), if it is legal in the
+  /// current position
+  pub fn play_bare_move(&mut self, bare: BareMove) -> anyhow::Result<Move> {
+    let mv = self
+      .board
+      .find_move(bare)
+      .ok_or(anyhow!("Illegal move {bare} in {}", self.board.to_fen()))?;
+
+    self.play_move(mv);
+    Ok(mv)
+  }
+
+  /// Take back the last move, if any, and return it
+  pub fn undo_move(&mut self) -> Option<Move> {
+    let mv = self.moves.pop()?;
+    self.hashes.pop();
+
+    // We don't hold on to the information needed to unmake the move, so
+    // replay the game up to this point instead.
+    self.board = self
+      .moves
+      .iter()
+      .fold(self.initial, |board, &mv| board.play_move(mv));
+
+    Some(mv)
+  }
+
+  /// The number of times the current position has occurred in the game,
+  /// including the current occurrence.
+  pub fn repetitions(&self) -> usize {
+    let current = repetition_hash(&self.board);
+
+    // Only the positions since the last capture or pawn move can repeat
+    let reversible = self.board.half_moves as usize;
+    let start = self.hashes.len().saturating_sub(reversible);
+
+    let previous = self.hashes[start..]
+      .iter()
+      .filter(|&&hash| hash == current)
+      .count();
+
+    previous + 1
+  }
+
+  /// Check whether the game has ended.
+  ///
+  /// Threefold repetition and the fifty-move rule only end the game when one
+  /// of the players claims the draw, so they're only reported when none of
+  /// the outcomes that end the game outright apply.
+  pub fn outcome(&self) -> Option<GameOutcome> {
+    use GameOutcome::*;
+
+    // Checkmate takes precedence over the other rules, even when the mating
+    // move was the 75th move without a capture or pawn move.
+    if self.board.legal_moves::<All>().is_empty() {
+      return if self.board.in_check() {
+        Some(Checkmate {
+          winner: !self.board.current,
+        })
+      } else {
+        Some(Stalemate)
+      };
+    }
+
+    let repetitions = self.repetitions();
+
+    if self.board.insufficient_material() {
+      Some(DeadPosition)
+    } else if repetitions >= 5 {
+      Some(FivefoldRepetition)
+    } else if self.board.half_moves >= 150 {
+      Some(SeventyFiveMoves)
+    } else if repetitions >= 3 {
+      Some(ThreefoldRepetition)
+    } else if self.board.half_moves >= 100 {
+      Some(FiftyMoves)
+    } else {
+      None
+    }
+  }
+}
+
+impl Default for Game {
+  fn default() -> Self {
+    Self::new(Board::default())
+  }
+}
+
+/// The hash of a board, for the purposes of detecting repetitions.
+///
+/// The board's own hash includes the en-passant square after every double
+/// push, but as far as the rules are concerned, it's only part of the position
+/// when the capture can actually be played.
+fn repetition_hash(board: &Board) -> ZHash {
+  let mut hash = board.hash();
+
+  if let Some(ep_sq) = board.en_passant {
+    let can_capture = board
+      .legal_moves::<All>()
+      .iter()
+      .any(|mv| mv.is_en_passant());
+
+    if !can_capture {
+      hash.toggle_ep(ep_sq);
+    }
+  }
+
+  hash
+}
+
+impl GameOutcome {
+  /// The side that won the game, if any
+  pub fn winner(&self) -> Option<Color> {
+    match self {
+      GameOutcome::Checkmate { winner } => Some(*winner),
+      _ => None,
+    }
+  }
+
+  /// Whether the game ended in a draw
+  pub fn is_draw(&self) -> bool {
+    self.winner().is_none()
+  }
+
+  /// Whether the game only ends when one of the players claims the draw
+  pub fn is_claimable(&self) -> bool {
+    matches!(
+      self,
+      GameOutcome::ThreefoldRepetition | GameOutcome::FiftyMoves
+    )
+  }
+
+  /// The result of the game, as written in a PGN file
+  pub fn result(&self) -> &'static str {
+    match self.winner() {
+      Some(Color::White) => "1-0",
+      Some(Color::Black) => "0-1",
+      None => "1/2-1/2",
+    }
+  }
+}
+
+impl Display for GameOutcome {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    use GameOutcome::*;
+
+    match self {
+      Checkmate {
+        winner: Color::White,
+      } => write!(f, "White mates"),
+      Checkmate {
+        winner: Color::Black,
+      } => write!(f, "Black mates"),
+      Stalemate => write!(f, "Draw by stalemate"),
+      ThreefoldRepetition => write!(f, "Draw by threefold repetition"),
+      FivefoldRepetition => write!(f, "Draw by fivefold repetition"),
+      FiftyMoves => write!(f, "Draw by fifty-move rule"),
+      SeventyFiveMoves => write!(f, "Draw by seventy-five-move rule"),
+      DeadPosition => write!(f, "Draw by dead position"),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Set up a game by playing the provided moves, in UCI notation
+  fn play(fen: &str, moves: &str) -> Game {
+    let mut game = Game::new(fen.parse().unwrap());
+
+    for mv in moves.split_whitespace() {
+      game.play_bare_move(mv.parse().unwrap()).unwrap();
+    }
+
+    game
+  }
+
+  const STARTPOS: &str =
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+  const SHUFFLE: &str = "g1f3 g8f6 f3g1 f6g8";
+
+  #[test]
+  fn checkmate() {
+    let game = play(STARTPOS, "f2f3 e7e5 g2g4 d8h4");
+
+    assert_eq!(
+      game.outcome(),
+      Some(GameOutcome::Checkmate {
+        winner: Color::Black
+      })
+    );
+    assert_eq!(game.outcome().unwrap().result(), "0-1");
+  }
+
+  #[test]
+  fn stalemate() {
+    let game = play("k7/8/8/2Q5/8/8/8/7K w - - 0 1", "h1g2");
+    assert_eq!(game.outcome(), None);
+
+    let game = play("k7/8/8/2Q5/8/8/8/7K w - - 0 1", "c5c7");
+    assert_eq!(game.outcome(), Some(GameOutcome::Stalemate));
+  }
+
+  #[test]
+  fn repetitions() {
+    let game = play(STARTPOS, SHUFFLE);
+    assert_eq!(game.repetitions(), 2);
+    assert_eq!(game.outcome(), None);
+
+    let game = play(STARTPOS, &[SHUFFLE; 2].join(" "));
+    assert_eq!(game.repetitions(), 3);
+    assert_eq!(game.outcome(), Some(GameOutcome::ThreefoldRepetition));
+    assert!(game.outcome().unwrap().is_claimable());
+
+    let game = play(STARTPOS, &[SHUFFLE; 4].join(" "));
+    assert_eq!(game.repetitions(), 5);
+    assert_eq!(game.outcome(), Some(GameOutcome::FivefoldRepetition));
+    assert!(!game.outcome().unwrap().is_claimable());
+  }
+
+  #[test]
+  fn repetitions_reset_on_irreversible_moves() {
+    let moves = [SHUFFLE, "e2e4 g8f6 g1f3 f6g8 f3g1"].join(" ");
+    let game = play(STARTPOS, &moves);
+
+    assert_eq!(game.hashes().len(), 9);
+    assert_eq!(game.repetitions(), 2);
+  }
+
+  #[test]
+  fn unusable_en_passant_square_is_ignored() {
+    // After 1. e4, there's an en-passant square on e3, but black can't
+    // capture, so the position repeats after the knights shuffle back.
+    let game = play(STARTPOS, "e2e4 g8f6 g1f3 f6g8 f3g1");
+    assert_eq!(game.repetitions(), 2);
+
+    // With a black pawn on d4, the en-passant capture is possible, so the
+    // position after 2. e4 is different from the one after the shuffle.
+    let fen = "rnbqkbnr/ppp1pppp/8/8/3p4/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    let game = play(fen, "e2e4 g8f6 g1f3 f6g8 f3g1");
+    assert_eq!(game.repetitions(), 1);
+  }
+
+  #[test]
+  fn move_rules() {
+    let fen = "4k3/8/8/8/8/8/4P3/R3K3 w - - 98 80";
+    let game = play(fen, "a1a2");
+    assert_eq!(game.outcome(), None);
+
+    let game = play(fen, "a1a2 e8d8");
+    assert_eq!(game.outcome(), Some(GameOutcome::FiftyMoves));
+
+    let fen = "4k3/8/8/8/8/8/4P3/R3K3 w - - 148 80";
+    let game = play(fen, "a1a2 e8d8");
+    assert_eq!(game.outcome(), Some(GameOutcome::SeventyFiveMoves));
+  }
+
+  #[test]
+  fn checkmate_beats_seventy_five_moves() {
+    let game = play("7k/8/6K1/8/8/8/8/R7 w - - 149 80", "a1a8");
+
+    assert_eq!(
+      game.outcome(),
+      Some(GameOutcome::Checkmate {
+        winner: Color::White
+      })
+    );
+  }
+
+  #[test]
+  fn dead_position() {
+    let game = play("4k3/8/8/8/8/8/3r4/4K3 w - - 0 1", "e1d2");
+    assert_eq!(game.outcome(), Some(GameOutcome::DeadPosition));
+  }
+
+  #[test]
+  fn undo_move() {
+    let mut game = play(STARTPOS, "e2e4 e7e5");
+    let after_e4 = play(STARTPOS, "e2e4");
+
+    assert_eq!(
+      game.undo_move().map(|mv| mv.to_string()),
+      Some("e7e5".into())
+    );
+    assert_eq!(game.board(), after_e4.board());
+    assert_eq!(game.moves(), after_e4.moves());
+    assert_eq!(game.hashes(), after_e4.hashes());
+
+    game.undo_move();
+    assert_eq!(game.board(), game.initial());
+    assert_eq!(game.undo_move(), None);
+  }
+
+  #[test]
+  fn illegal_moves_are_rejected() {
+    let mut game = Game::default();
+
+    assert!(game.play_bare_move("e2e5".parse().unwrap()).is_err());
+    assert!(game.moves().is_empty());
+    assert!(game.hashes().is_empty());
+  }
+}
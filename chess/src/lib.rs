@@ -2,9 +2,12 @@ pub mod bitboard;
 pub mod board;
 pub mod constants;
 pub mod fen;
+pub mod game;
 pub mod movegen;
 pub mod perft;
+pub mod pgn;
 pub mod piece;
 pub mod san;
 pub mod see;
 pub mod square;
+pub mod zobrist;
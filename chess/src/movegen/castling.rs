@@ -2,6 +2,7 @@ use crate::bitboard::Bitboard;
 use crate::board::Board;
 use crate::movegen::moves::Move;
 use crate::movegen::moves::MoveType;
+use crate::movegen::sliders::Sliders;
 use crate::piece::Color;
 use crate::square::Square;
 use anyhow::anyhow;
@@ -11,7 +12,7 @@ use std::ops::IndexMut;
 use std::str::FromStr;
 use Square::*;
 
-impl Board {
+impl<S: Sliders> Board<S> {
   /// Return an iterator over the legal castle types for the current side
   ///
   /// Castling is permitted only if
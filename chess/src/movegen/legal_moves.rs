@@ -18,6 +18,7 @@ use crate::movegen::lookups::BETWEEN;
 use crate::movegen::lookups::RAYS;
 use crate::movegen::moves::Move;
 use crate::movegen::moves::MoveType;
+use crate::movegen::sliders::Sliders;
 use crate::piece::Color;
 use crate::piece::PieceType;
 
@@ -51,7 +52,7 @@ impl GenType for Quiets {
   const QUIETS: bool = true;
 }
 
-impl Board {
+impl<S: Sliders> Board<S> {
   pub fn legal_moves_for<const WHITE: bool, GT: GenType>(
     &self,
     moves: &mut MoveList,
@@ -386,7 +387,7 @@ impl Board {
 
     // Unpinned sliders can move freely
     for square in sliders & !pinrays {
-      let targets = square.bishop_squares(blockers) & valid_targets;
+      let targets = S::bishop_squares(square, blockers) & valid_targets;
 
       if GT::TACTICALS {
         for target in targets & theirs {
@@ -408,7 +409,7 @@ impl Board {
     // Diagonally pinned sliders can move along their pinray
     for square in sliders & diag_pinrays {
       let valid_targets = valid_targets & diag_pinrays;
-      let targets = square.bishop_squares(blockers) & valid_targets;
+      let targets = S::bishop_squares(square, blockers) & valid_targets;
 
       if GT::TACTICALS {
         for target in targets & theirs {
@@ -441,7 +442,7 @@ impl Board {
 
     // Unpinned sliders can move freely
     for square in sliders & !pinrays {
-      let targets = square.rook_squares(blockers) & valid_targets;
+      let targets = S::rook_squares(square, blockers) & valid_targets;
 
       if GT::TACTICALS {
         for target in targets & theirs {
@@ -463,7 +464,7 @@ impl Board {
     // HV-pinned sliders can move along their pinray
     for square in sliders & hv_pinrays {
       let valid_targets = valid_targets & hv_pinrays;
-      let targets = square.rook_squares(blockers) & valid_targets;
+      let targets = S::rook_squares(square, blockers) & valid_targets;
 
       if GT::TACTICALS {
         for target in targets & theirs {
@@ -578,9 +579,9 @@ impl Board {
       Pawn if mv.is_capture() => src.pawn_attacks(us),
       Pawn => src.pawn_squares(us, blockers),
       Knight => src.knight_squares(),
-      Bishop => src.bishop_squares(blockers),
-      Rook => src.rook_squares(blockers),
-      Queen => src.queen_squares(blockers),
+      Bishop => S::bishop_squares(src, blockers),
+      Rook => S::rook_squares(src, blockers),
+      Queen => S::queen_squares(src, blockers),
       King => src.king_squares(),
     };
 
@@ -639,9 +640,9 @@ impl Board {
         ^ Bitboard::from(tgt)
         ^ Bitboard::from(capture_sq);
 
-      let hv_checkers = king.rook_squares(blockers) & self.hv_sliders(!us);
+      let hv_checkers = S::rook_squares(king, blockers) & self.hv_sliders(!us);
       let diag_checkers =
-        king.bishop_squares(blockers) & self.diag_sliders(!us);
+        S::bishop_squares(king, blockers) & self.diag_sliders(!us);
 
       if !hv_checkers.is_empty() || !diag_checkers.is_empty() {
         return false;
@@ -688,8 +689,9 @@ impl Board {
     if piece.is_king() {
       let blockers = blockers & !self.kings(us);
       return !self.threats.contains(tgt)
-        && (tgt.bishop_squares(blockers) & self.diag_sliders(!us)).is_empty()
-        && (tgt.rook_squares(blockers) & self.hv_sliders(!us)).is_empty();
+        && (S::bishop_squares(tgt, blockers) & self.diag_sliders(!us))
+          .is_empty()
+        && (S::rook_squares(tgt, blockers) & self.hv_sliders(!us)).is_empty();
     }
 
     // If piece is pinned, make sure target square is inside pinray
@@ -3,8 +3,15 @@ use super::lookups::gen_rook_attacks;
 use crate::bitboard::Bitboard;
 use crate::square::Square;
 
+/// The number of entries in the bishop attack table
+pub const BISHOP_TABLE_SIZE: usize = 5248;
+
+/// The number of entries in the rook attack table
+pub const ROOK_TABLE_SIZE: usize = 102400;
+
 #[derive(Debug, Copy, Clone)]
 pub struct MagicEntry {
+  /// The squares whose occupancy affects the attacks
   pub mask: Bitboard,
   pub magic: u64,
   pub shift: u8,
@@ -19,28 +26,51 @@ impl MagicEntry {
   }
 }
 
+/// A "black" magic entry, which ORs the complement of the mask into the
+/// blockers before multiplying, and uses the same shift for every square.
+///
+/// The move generator doesn't use these, but the magics generator can
+/// produce them, so they're kept as a separate type to keep the regular
+/// lookups free of the extra OR.
+#[derive(Debug, Copy, Clone)]
+pub struct BlackMagicEntry {
+  /// The squares whose occupancy affects the attacks
+  pub mask: Bitboard,
+  pub magic: u64,
+  pub shift: u8,
+  pub offset: u32,
+}
+
+impl BlackMagicEntry {
+  pub const fn index(&self, blockers: Bitboard) -> usize {
+    let blockers = blockers.0 | !self.mask.0;
+    let offset = self.offset as usize;
+    offset + (self.magic.wrapping_mul(blockers) >> self.shift) as usize
+  }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 //
-// Square method impls
+// Lookups
 //
 ////////////////////////////////////////////////////////////////////////////////
 
-impl Square {
-  /// Get a bitboard for all the squares visible to a bishop on this square.
-  pub fn bishop_squares(self, blockers: Bitboard) -> Bitboard {
-    let magic = BISHOP_MAGICS[self];
-    let idx = magic.index(blockers);
+/// Get a bitboard for all the squares visible to a bishop on this square.
+#[inline(always)]
+pub fn bishop_squares(square: Square, blockers: Bitboard) -> Bitboard {
+  let magic = BISHOP_MAGICS[square];
+  let idx = magic.index(blockers);
 
-    BISHOP_ATTACKS[idx]
-  }
+  BISHOP_ATTACKS[idx]
+}
 
-  /// Get a bitboard for all the squares visible to a rook on this square.
-  pub fn rook_squares(self, blockers: Bitboard) -> Bitboard {
-    let magic = ROOK_MAGICS[self];
-    let idx = magic.index(blockers);
+/// Get a bitboard for all the squares visible to a rook on this square.
+#[inline(always)]
+pub fn rook_squares(square: Square, blockers: Bitboard) -> Bitboard {
+  let magic = ROOK_MAGICS[square];
+  let idx = magic.index(blockers);
 
-    ROOK_ATTACKS[idx]
-  }
+  ROOK_ATTACKS[idx]
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -49,11 +79,12 @@ impl Square {
 //
 ////////////////////////////////////////////////////////////////////////////////
 
-pub const BISHOP_ATTACKS: [Bitboard; 5248] = gen_bishop_attacks_table();
-pub const ROOK_ATTACKS: [Bitboard; 102400] = gen_rook_attacks_table();
+pub static BISHOP_ATTACKS: [Bitboard; BISHOP_TABLE_SIZE] =
+  gen_bishop_attacks_table();
+pub static ROOK_ATTACKS: [Bitboard; ROOK_TABLE_SIZE] = gen_rook_attacks_table();
 
-const fn gen_bishop_attacks_table() -> [Bitboard; 5248] {
-  let mut table = [Bitboard::EMPTY; 5248];
+const fn gen_bishop_attacks_table() -> [Bitboard; BISHOP_TABLE_SIZE] {
+  let mut table = [Bitboard::EMPTY; BISHOP_TABLE_SIZE];
   let mut sq: usize = 0;
 
   while sq < 64 {
@@ -84,8 +115,8 @@ const fn gen_bishop_attacks_table() -> [Bitboard; 5248] {
   table
 }
 
-const fn gen_rook_attacks_table() -> [Bitboard; 102400] {
-  let mut table = [Bitboard::EMPTY; 102400];
+const fn gen_rook_attacks_table() -> [Bitboard; ROOK_TABLE_SIZE] {
+  let mut table = [Bitboard::EMPTY; ROOK_TABLE_SIZE];
   let mut sq: usize = 0;
 
   while sq < 64 {
@@ -920,6 +951,7 @@ fn test_subsets() {
 
 #[test]
 fn test_gen_bishop_mask() {
+  use super::lookups::bishop_mask;
   use Square::*;
   assert_eq!(bishop_mask(E3), Bitboard(0x24428002800));
   assert_eq!(bishop_mask(H1), Bitboard(0x2040810204000));
@@ -935,6 +967,7 @@ fn test_gen_bishop_attacks() {
 
 #[test]
 fn test_rook_mask() {
+  use super::lookups::rook_mask;
   use Square::*;
 
   assert_eq!(rook_mask(E3), Bitboard(0x101010106e1000));
@@ -945,6 +978,6 @@ fn test_rook_mask() {
 fn test_rook_attacks() {
   use Square::*;
 
-  let attacks = rook_attacks(E3, Bitboard(0xb0430800420423));
+  let attacks = gen_rook_attacks(E3, Bitboard(0xb0430800420423));
   assert_eq!(attacks, Bitboard(0x101010106e1010));
 }
@@ -34,6 +34,17 @@ impl Move {
     Move(value)
   }
 
+  /// Return the packed representation of the move
+  pub const fn to_u16(self) -> u16 {
+    self.0
+  }
+
+  /// Create a move from its packed representation. There is no guarantee
+  /// that the resulting move is legal, or even valid.
+  pub const fn from_u16(value: u16) -> Move {
+    Move(value)
+  }
+
   ///  Get the source square for a move
   pub fn src(self) -> Square {
     // SAFETY: The mask guarantees that the index is in bounds
@@ -6,31 +6,34 @@ use super::lookups::gen_bishop_attacks;
 use super::lookups::gen_rook_attacks;
 use super::lookups::rook_mask;
 
-#[cfg(not(all(target_arch = "x86_64", target_feature = "bmi2")))]
-compile_error!("pext feature can only be enabled if target has BMI2.");
-
 ////////////////////////////////////////////////////////////////////////////////
 //
-// Square method impls
+// Lookups
 //
 ////////////////////////////////////////////////////////////////////////////////
 
-impl Square {
-  /// Get a bitboard for all the squares visible to a bishop on this square.
-  pub fn bishop_squares(self, blockers: Bitboard) -> Bitboard {
-    let pext_entry = BISHOP_ENTRIES[self];
-    let idx = pext_entry.index(blockers);
+/// Get a bitboard for all the squares visible to a bishop on this square.
+///
+/// # Safety
+/// The CPU needs to support BMI2.
+#[inline(always)]
+pub unsafe fn bishop_squares(square: Square, blockers: Bitboard) -> Bitboard {
+  let pext_entry = BISHOP_ENTRIES[square];
+  let idx = pext_entry.index(blockers);
 
-    BISHOP_ATTACKS[idx]
-  }
+  BISHOP_ATTACKS[idx]
+}
 
-  /// Get a bitboard for all the squares visible to a rook on this square.
-  pub fn rook_squares(self, blockers: Bitboard) -> Bitboard {
-    let pext_entry = ROOK_ENTRIES[self];
-    let idx = pext_entry.index(blockers);
+/// Get a bitboard for all the squares visible to a rook on this square.
+///
+/// # Safety
+/// The CPU needs to support BMI2.
+#[inline(always)]
+pub unsafe fn rook_squares(square: Square, blockers: Bitboard) -> Bitboard {
+  let pext_entry = ROOK_ENTRIES[square];
+  let idx = pext_entry.index(blockers);
 
-    ROOK_ATTACKS[idx]
-  }
+  ROOK_ATTACKS[idx]
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -39,10 +42,10 @@ impl Square {
 //
 ////////////////////////////////////////////////////////////////////////////////
 
-const BISHOP_ATTACKS: [Bitboard; 5248] = gen_bishop_attacks_table();
+static BISHOP_ATTACKS: [Bitboard; 5248] = gen_bishop_attacks_table();
 
 #[allow(long_running_const_eval)]
-const ROOK_ATTACKS: [Bitboard; 102400] = gen_rook_attacks_table();
+static ROOK_ATTACKS: [Bitboard; 102400] = gen_rook_attacks_table();
 
 const BISHOP_ENTRIES: [PextEntry; Square::COUNT] = gen_entries::<true>();
 const ROOK_ENTRIES: [PextEntry; Square::COUNT] = gen_entries::<false>();
@@ -124,8 +127,12 @@ pub struct PextEntry {
 }
 
 impl PextEntry {
-  /// Given an entry,
-  pub fn index(&self, blockers: Bitboard) -> usize {
+  /// Given an entry, compute the index of the attacks for the given blockers
+  ///
+  /// # Safety
+  /// The CPU needs to support BMI2.
+  #[inline(always)]
+  pub unsafe fn index(&self, blockers: Bitboard) -> usize {
     let index = pext_u64(blockers.0, self.mask.0) as usize;
     let offset = self.offset as usize;
     offset + index
@@ -144,11 +151,34 @@ impl PextEntry {
 //
 ////////////////////////////////////////////////////////////////////////////////
 
-/// A Sofe wrapper around the _pext_u64 intrinsic
-fn pext_u64(value: u64, mask: u64) -> u64 {
-  // SAFETY: A compile error is raised if PEXT is not available. PEXT is
-  // always safe if available.
-  unsafe { core::arch::x86_64::_pext_u64(value, mask) }
+/// A wrapper around the `pext` instruction
+///
+/// When BMI2 is enabled at compile time, we use the intrinsic. Otherwise, we
+/// emit the instruction through inline assembly, since a function with
+/// `#[target_feature(enable = "bmi2")]` can't be inlined into code compiled
+/// without it, and a function call on every lookup would defeat the purpose.
+///
+/// # Safety
+/// The CPU needs to support BMI2.
+#[inline(always)]
+unsafe fn pext_u64(value: u64, mask: u64) -> u64 {
+  #[cfg(target_feature = "bmi2")]
+  {
+    core::arch::x86_64::_pext_u64(value, mask)
+  }
+
+  #[cfg(not(target_feature = "bmi2"))]
+  {
+    let result: u64;
+    core::arch::asm!(
+      "pext {result}, {value}, {mask}",
+      result = lateout(reg) result,
+      value = in(reg) value,
+      mask = in(reg) mask,
+      options(pure, nomem, nostack, preserves_flags),
+    );
+    result
+  }
 }
 
 /// Poor man's pext that can run at compile time
@@ -205,6 +235,8 @@ pub const fn gen_entries<const BISHOP: bool>() -> [PextEntry; Square::COUNT] {
 mod tests {
   use super::*;
   use crate::movegen::lookups::gen_rook_attacks;
+  use crate::movegen::sliders::Magics;
+  use crate::movegen::sliders::Sliders;
 
   #[test]
   fn test_indexing() {
@@ -215,7 +247,12 @@ mod tests {
     let entry = ROOK_ENTRIES[sq];
     println!("Entry: {entry:?}");
 
-    let index = entry.index(blockers);
+    if !std::is_x86_feature_detected!("bmi2") {
+      return;
+    }
+
+    // SAFETY: We checked that BMI2 is available
+    let index = unsafe { entry.index(blockers) };
     let index_const = entry.index_const(blockers);
 
     assert_eq!(index, index_const);
@@ -238,11 +275,11 @@ mod tests {
     let blockers: Bitboard = vec![D3, F4, A4, D7].into_iter().collect();
 
     let generated = gen_rook_attacks(sq, blockers);
-    let lookup = sq.rook_squares(blockers);
+    let lookup = Magics::rook_squares(sq, blockers);
 
     println!("Generated:\n{generated}");
     println!("Lookup:\n{lookup}");
 
-    assert_eq!(generated, sq.rook_squares(blockers));
+    assert_eq!(generated, Magics::rook_squares(sq, blockers));
   }
 }
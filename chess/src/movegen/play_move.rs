@@ -1,32 +1,40 @@
 //!  Logic that updates a Board state according to a provided move
 //!
-//! Many other engines (in particular so-called "pseudo-legal" engines rely
-//! on a mechanism of "MakeMove" and "UnmakeMove", where a single board gets
-//! updated in-place and reverted in-place.
+//! There are two ways of applying a move to a board:
 //!
-//! This makes sense, because they already have the machinery for make/unmake
-//! for generating legal moves (all pseudo-legal moves ar made, and the ones
-//! that lead to illegal boards are "unmade".
+//! 1. Copy-make (`Board::play_move`): copy the board and update the copy,
+//!    leaving the original untouched. Since everything is stack-allocated,
+//!    this hasn't really cost us much of a slowdown. It seems like Carp and
+//!    Viridithas get away with it, so why shouldn't we?
 //!
-//! Since verything is stack-allocated, this hasn't really cost us much
-//! of a slowdown. It seems like Carp and Viridithas get away with it, so why
-//! shouldn't we?
+//! 2. Make/unmake (`Board::make_move` and `Board::unmake_move`): update the
+//!    board in-place, and return an undo record with everything needed to
+//!    revert the move afterwards. This is what most other engines do, and it
+//!    saves us copying the entire board on every move.
+//!
+//! Both need to produce the exact same boards, which is checked by the tests
+//! at the bottom of this module.
 
 use super::castling::CastleType;
+use super::castling::CastlingRights;
 use super::moves::Move;
+use crate::bitboard::Bitboard;
 use crate::board::Board;
+use crate::movegen::sliders::Sliders;
 use crate::piece::Color;
 use crate::piece::Piece;
+use crate::piece::PieceType;
 use crate::square::Square;
+use crate::zobrist::ZHash;
 
-impl Board {
+impl<S: Sliders> Board<S> {
   /// Given a board state and a move to play, update the board state to
   /// reflect that move.
   ///
   /// Note that this method will panic when used with NULL moves. If you want
   /// to play a "null" move (e.g., for null move pruning), use`
   /// Self::play_null_move` instead.
-  pub fn play_move(&self, mv: Move) -> Board {
+  pub fn play_move(&self, mv: Move) -> Self {
     use Square::*;
     let mut new_board = self.clone();
     let source = mv.src();
@@ -153,6 +161,9 @@ impl Board {
     new_board.checkers = new_board.compute_checkers();
     new_board.threats = new_board.attacked_squares(!new_board.current);
 
+    new_board.rehash_state(self.castling_rights, self.en_passant);
+    new_board.check_hash();
+
     new_board
   }
 
@@ -184,6 +195,352 @@ impl Board {
     new_board.checkers = new_board.compute_checkers();
     new_board.threats = new_board.attacked_squares(!new_board.current);
 
+    new_board.rehash_state(self.castling_rights, self.en_passant);
+    new_board.check_hash();
+
     return new_board;
   }
 }
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Make/unmake
+//
+////////////////////////////////////////////////////////////////////////////////
+
+/// Everything we need to restore a board after playing a move in-place
+///
+/// Most of the board can be restored from the move itself, but the captured
+/// piece and the irreversible state (castling rights, en-passant square and
+/// half-move clock) need to be saved. We also hold on to the auxiliary
+/// bitboards, since copying them back is a lot cheaper than recomputing them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BoardUndo {
+  /// The piece that was captured by the move, if any
+  pub captured: Option<Piece>,
+
+  /// The castling rights before the move
+  pub castling_rights: CastlingRights,
+
+  /// The en-passant square before the move
+  pub en_passant: Option<Square>,
+
+  /// The half-move clock before the move
+  pub half_moves: u8,
+
+  /// The pinrays, checkers and threats before the move
+  pub hv_pinrays: [Bitboard; Color::COUNT],
+  pub diag_pinrays: [Bitboard; Color::COUNT],
+  pub checkers: Bitboard,
+  pub threats: Bitboard,
+
+  /// The Zobrist hash before the move
+  pub hash: ZHash,
+}
+
+impl<S: Sliders> Board<S> {
+  /// Play a move in-place, and return the information needed to take it back
+  /// with `Board::unmake_move`.
+  ///
+  /// Like `Board::play_move`, this will panic when used with NULL moves. Use
+  /// `Board::make_null_move` instead.
+  pub fn make_move(&mut self, mv: Move) -> BoardUndo {
+    use Square::*;
+    let source = mv.src();
+    let target = mv.tgt();
+    let us = self.current;
+
+    let mut undo = self.undo_record();
+
+    ////////////////////////////////////////////////////////////////////////
+    //
+    // Play move
+    //
+    ////////////////////////////////////////////////////////////////////////
+
+    let piece = self.remove_at(source).unwrap();
+
+    // Figure out what piece to place at the target (considers promotions)
+    let new_piece = mv.get_promo_piece(us).unwrap_or(piece);
+
+    // Remove the captured piece, which might not be on the target square in
+    // case of en-passant
+    if mv.is_capture() {
+      undo.captured = self.remove_at(mv.get_capture_sq());
+    }
+
+    self.add_at(target, new_piece);
+
+    // Should we set the EP square?
+    if mv.is_double_push() {
+      self.en_passant = target.backward(us);
+    } else {
+      self.en_passant = None;
+    }
+
+    ////////////////////////////////////////////////////////////////////////
+    //
+    // Update castling rights
+    //
+    ////////////////////////////////////////////////////////////////////////
+
+    if piece.is_king() {
+      // In case of castle, also move the rook to the appropriate square
+      if mv.is_castle() {
+        let rook_move = CastleType::from_move(mv).unwrap().rook_move();
+        let rook = self.remove_at(rook_move.src()).unwrap();
+        self.add_at(rook_move.tgt(), rook);
+      }
+
+      if us.is_white() {
+        self.castling_rights.remove(CastleType::WQ);
+        self.castling_rights.remove(CastleType::WK);
+      } else {
+        self.castling_rights.remove(CastleType::BQ);
+        self.castling_rights.remove(CastleType::BK);
+      }
+    }
+
+    if piece.is_rook() || undo.captured.is_some_and(|piece| piece.is_rook()) {
+      for square in [source, target] {
+        match square {
+          A1 => self.castling_rights.remove(CastleType::WQ),
+          H1 => self.castling_rights.remove(CastleType::WK),
+          A8 => self.castling_rights.remove(CastleType::BQ),
+          H8 => self.castling_rights.remove(CastleType::BK),
+          _ => {}
+        }
+      }
+    }
+
+    ////////////////////////////////////////////////////////////////////////
+    //
+    // Update counters and flags
+    //
+    ////////////////////////////////////////////////////////////////////////
+
+    self.current = !us;
+
+    if us.is_black() {
+      self.full_moves += 1;
+    }
+
+    if mv.is_capture() || piece.is_pawn() {
+      self.half_moves = 0;
+    } else {
+      self.half_moves += 1;
+    }
+
+    self.update_aux_bitboards();
+    self.rehash_state(undo.castling_rights, undo.en_passant);
+    self.check_hash();
+
+    undo
+  }
+
+  /// Take back a move that was played with `Board::make_move`, restoring the
+  /// board to the exact state it was in before.
+  pub fn unmake_move(&mut self, mv: Move, undo: BoardUndo) {
+    let source = mv.src();
+    let target = mv.tgt();
+    let us = !self.current;
+
+    // Move the piece back, undoing any promotion
+    let moved = self.remove_at(target).unwrap();
+
+    if mv.is_promotion() {
+      self.add_at(source, Piece::new(PieceType::Pawn, us));
+    } else {
+      self.add_at(source, moved);
+    }
+
+    if let Some(captured) = undo.captured {
+      self.add_at(mv.get_capture_sq(), captured);
+    }
+
+    if mv.is_castle() {
+      let rook_move = CastleType::from_move(mv).unwrap().rook_move();
+      let rook = self.remove_at(rook_move.tgt()).unwrap();
+      self.add_at(rook_move.src(), rook);
+    }
+
+    self.current = us;
+
+    if us.is_black() {
+      self.full_moves -= 1;
+    }
+
+    self.restore(undo);
+  }
+
+  /// Pass the turn in-place, and return the information needed to take it
+  /// back with `Board::unmake_null_move`.
+  pub fn make_null_move(&mut self) -> BoardUndo {
+    let undo = self.undo_record();
+    let us = self.current;
+
+    self.current = !us;
+    self.en_passant = None;
+    self.half_moves += 1;
+
+    if us.is_black() {
+      self.full_moves += 1;
+    }
+
+    self.checkers = self.compute_checkers();
+    self.threats = self.attacked_squares(!self.current);
+
+    self.rehash_state(undo.castling_rights, undo.en_passant);
+    self.check_hash();
+
+    undo
+  }
+
+  /// Take back a null move that was played with `Board::make_null_move`
+  pub fn unmake_null_move(&mut self, undo: BoardUndo) {
+    let us = !self.current;
+    self.current = us;
+
+    if us.is_black() {
+      self.full_moves -= 1;
+    }
+
+    self.restore(undo);
+  }
+
+  /// Save the irreversible state of the board
+  fn undo_record(&self) -> BoardUndo {
+    BoardUndo {
+      captured: None,
+      castling_rights: self.castling_rights,
+      en_passant: self.en_passant,
+      half_moves: self.half_moves,
+      hv_pinrays: self.hv_pinrays,
+      diag_pinrays: self.diag_pinrays,
+      checkers: self.checkers,
+      threats: self.threats,
+      hash: self.hash,
+    }
+  }
+
+  /// Restore the irreversible state of the board from an undo record
+  fn restore(&mut self, undo: BoardUndo) {
+    self.castling_rights = undo.castling_rights;
+    self.en_passant = undo.en_passant;
+    self.half_moves = undo.half_moves;
+    self.hv_pinrays = undo.hv_pinrays;
+    self.diag_pinrays = undo.diag_pinrays;
+    self.checkers = undo.checkers;
+    self.threats = undo.threats;
+    self.hash = undo.hash;
+  }
+
+  /// Update the hash for the changes in castling rights, en-passant square
+  /// and side to move, given their values before the move. Any pieces that
+  /// were added or removed have already been hashed by `add_at` and
+  /// `remove_at`.
+  fn rehash_state(
+    &mut self,
+    castling_rights: CastlingRights,
+    en_passant: Option<Square>,
+  ) {
+    self.hash.toggle_castling(castling_rights);
+    self.hash.toggle_castling(self.castling_rights);
+
+    if let Some(ep_sq) = en_passant {
+      self.hash.toggle_ep(ep_sq);
+    }
+
+    if let Some(ep_sq) = self.en_passant {
+      self.hash.toggle_ep(ep_sq);
+    }
+
+    self.hash.toggle_side();
+  }
+
+  /// Recompute the pinrays, checkers and threats for the current position
+  fn update_aux_bitboards(&mut self) {
+    self.hv_pinrays = [
+      self.compute_hv_pinrays::<true>(),
+      self.compute_hv_pinrays::<false>(),
+    ];
+
+    self.diag_pinrays = [
+      self.compute_diag_pinrays::<true>(),
+      self.compute_diag_pinrays::<false>(),
+    ];
+
+    self.checkers = self.compute_checkers();
+    self.threats = self.attacked_squares(!self.current);
+  }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Tests
+//
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::movegen::legal_moves::All;
+
+  const POSITIONS: [&str; 6] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+  ];
+
+  /// Walk the move tree, checking at every node that `make_move` produces the
+  /// same board as `play_move`, and that `unmake_move` restores the original.
+  fn check_make_unmake(board: &mut Board, depth: usize) {
+    if depth == 0 {
+      return;
+    }
+
+    for mv in board.legal_moves::<All>() {
+      let original = *board;
+      let expected = board.play_move(mv);
+
+      let undo = board.make_move(mv);
+      assert_eq!(*board, expected, "make_move {mv} on {}", original.to_fen());
+
+      check_make_unmake(board, depth - 1);
+
+      board.unmake_move(mv, undo);
+      assert_eq!(*board, original, "unmake_move {mv} on {}", board.to_fen());
+    }
+  }
+
+  #[test]
+  fn make_move_matches_play_move() {
+    for fen in POSITIONS {
+      let mut board: Board = fen.parse().unwrap();
+      check_make_unmake(&mut board, 3);
+    }
+  }
+
+  #[test]
+  fn make_null_move_matches_play_null_move() {
+    for fen in POSITIONS {
+      let mut board: Board = fen.parse().unwrap();
+      let original = board;
+
+      let undo = board.make_null_move();
+      assert_eq!(board, original.play_null_move());
+
+      board.unmake_null_move(undo);
+      assert_eq!(board, original);
+    }
+  }
+
+  #[test]
+  fn make_unmake_perft() {
+    let mut board: Board = POSITIONS[1].parse().unwrap();
+    assert_eq!(board.perft_in_place(4), 4085603);
+  }
+}
@@ -0,0 +1,247 @@
+//! Sliding piece attacks
+//!
+//! There are two ways of looking up the attacks for sliding pieces: magic
+//! bitboards, which work everywhere, and PEXT bitboards, which use the BMI2
+//! `pext` instruction to compute the table index. PEXT is a little faster on
+//! CPUs that support it natively, but terribly slow on AMD CPUs before Zen 3,
+//! that emulate it in microcode.
+//!
+//! The backend is a type parameter on `Board` (see `Sliders`), so every lookup
+//! compiles down to a direct, inlined table access. Code that doesn't care
+//! about speed uses the default, `Magics`. Code that does, like the search, is
+//! written generically over `S: Sliders`, and instantiated through `dispatch`,
+//! which picks the backend that `init` found to be the fastest on this CPU.
+
+use super::magics;
+use crate::bitboard::Bitboard;
+use crate::square::Square;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+
+#[cfg(target_arch = "x86_64")]
+use super::pext;
+
+/// Whether `init` found that the CPU has fast PEXT
+static USE_PEXT: AtomicBool = AtomicBool::new(false);
+
+/// The method used to look up sliding piece attacks
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SliderBackend {
+  Pext,
+  Magics,
+}
+
+impl Display for SliderBackend {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      SliderBackend::Pext => write!(f, "pext"),
+      SliderBackend::Magics => write!(f, "magics"),
+    }
+  }
+}
+
+/// Pick the fastest backend for the CPU we're running on, and return it.
+pub fn init() -> SliderBackend {
+  USE_PEXT.store(has_fast_pext(), Relaxed);
+  backend()
+}
+
+/// The backend that `dispatch` picks. Until `init` is called, that's magic
+/// bitboards.
+pub fn backend() -> SliderBackend {
+  if USE_PEXT.load(Relaxed) {
+    SliderBackend::Pext
+  } else {
+    SliderBackend::Magics
+  }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Backends
+//
+////////////////////////////////////////////////////////////////////////////////
+
+mod sealed {
+  pub trait Sealed {}
+}
+
+/// A way of looking up the attacks for sliding pieces
+///
+/// The trait is sealed: the PEXT backend is only safe to use on CPUs with
+/// BMI2, so the only way to get code running with it is through `dispatch`.
+pub trait Sliders:
+  sealed::Sealed + Debug + Copy + Eq + Hash + Send + Sync + 'static
+{
+  /// Get a bitboard for all the squares visible to a bishop on a square.
+  fn bishop_squares(square: Square, blockers: Bitboard) -> Bitboard;
+
+  /// Get a bitboard for all the squares visible to a rook on a square.
+  fn rook_squares(square: Square, blockers: Bitboard) -> Bitboard;
+
+  /// Get a bitboard for all the squares visible to a queen on a square.
+  #[inline(always)]
+  fn queen_squares(square: Square, blockers: Bitboard) -> Bitboard {
+    Self::bishop_squares(square, blockers)
+      | Self::rook_squares(square, blockers)
+  }
+}
+
+/// Magic bitboards, which work on any CPU
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Magics;
+
+impl sealed::Sealed for Magics {}
+
+impl Sliders for Magics {
+  #[inline(always)]
+  fn bishop_squares(square: Square, blockers: Bitboard) -> Bitboard {
+    magics::bishop_squares(square, blockers)
+  }
+
+  #[inline(always)]
+  fn rook_squares(square: Square, blockers: Bitboard) -> Bitboard {
+    magics::rook_squares(square, blockers)
+  }
+}
+
+/// PEXT bitboards
+///
+/// Deliberately private, so it can only be picked by `dispatch`, after `init`
+/// checked that the CPU supports BMI2.
+#[cfg(target_arch = "x86_64")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct Pext;
+
+#[cfg(target_arch = "x86_64")]
+impl sealed::Sealed for Pext {}
+
+#[cfg(target_arch = "x86_64")]
+impl Sliders for Pext {
+  #[inline(always)]
+  fn bishop_squares(square: Square, blockers: Bitboard) -> Bitboard {
+    // SAFETY: `dispatch` only uses `Pext` when the CPU supports BMI2
+    unsafe { pext::bishop_squares(square, blockers) }
+  }
+
+  #[inline(always)]
+  fn rook_squares(square: Square, blockers: Bitboard) -> Bitboard {
+    // SAFETY: `dispatch` only uses `Pext` when the CPU supports BMI2
+    unsafe { pext::rook_squares(square, blockers) }
+  }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Dispatch
+//
+////////////////////////////////////////////////////////////////////////////////
+
+/// A piece of code that's generic over the slider backend
+///
+/// This is a trait rather than a closure, since closures can't be generic.
+pub trait WithSliders {
+  type Output;
+
+  fn run<S: Sliders>(self) -> Self::Output;
+}
+
+/// Run `f` with the backend picked by `init`.
+///
+/// Everything `f` does with a `Board<S>` is compiled separately for each
+/// backend, so this should be called once, as high up as possible (e.g., when
+/// spinning up the search threads), rather than in a hot loop.
+pub fn dispatch<F: WithSliders>(f: F) -> F::Output {
+  #[cfg(target_arch = "x86_64")]
+  if USE_PEXT.load(Relaxed) {
+    return f.run::<Pext>();
+  }
+
+  f.run::<Magics>()
+}
+
+/// Check whether the CPU supports PEXT, and doesn't emulate it in microcode.
+#[cfg(target_arch = "x86_64")]
+fn has_fast_pext() -> bool {
+  use std::arch::x86_64::__cpuid;
+
+  if !std::is_x86_feature_detected!("bmi2") {
+    return false;
+  }
+
+  let vendor = __cpuid(0);
+  let is_amd = [vendor.ebx, vendor.edx, vendor.ecx] == AUTHENTIC_AMD;
+
+  if !is_amd {
+    return true;
+  }
+
+  let info = __cpuid(1).eax;
+  let base_family = (info >> 8) & 0xf;
+  let ext_family = (info >> 20) & 0xff;
+  let family = if base_family == 0xf {
+    base_family + ext_family
+  } else {
+    base_family
+  };
+
+  // Zen 3 and later (family 0x19) implement PEXT in hardware
+  family >= 0x19
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn has_fast_pext() -> bool {
+  false
+}
+
+/// "AuthenticAMD", as returned in ebx, edx and ecx by `cpuid`
+#[cfg(target_arch = "x86_64")]
+const AUTHENTIC_AMD: [u32; 3] = [0x6874_7541, 0x6974_6e65, 0x444d_4163];
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::movegen::lookups::bishop_mask;
+  use crate::movegen::lookups::gen_bishop_attacks;
+  use crate::movegen::lookups::gen_rook_attacks;
+  use crate::movegen::lookups::rook_mask;
+
+  #[test]
+  fn magics_match_rays() {
+    for sq in Square::ALL {
+      for blockers in bishop_mask(sq).subsets() {
+        let expected = gen_bishop_attacks(sq, blockers);
+        assert_eq!(Magics::bishop_squares(sq, blockers), expected);
+      }
+
+      for blockers in rook_mask(sq).subsets() {
+        let expected = gen_rook_attacks(sq, blockers);
+        assert_eq!(Magics::rook_squares(sq, blockers), expected);
+      }
+    }
+  }
+
+  #[test]
+  #[cfg(target_arch = "x86_64")]
+  fn pext_matches_rays() {
+    if !std::is_x86_feature_detected!("bmi2") {
+      return;
+    }
+
+    // Using `Pext` directly is fine here, since we checked for BMI2 above
+    for sq in Square::ALL {
+      for blockers in bishop_mask(sq).subsets() {
+        let expected = gen_bishop_attacks(sq, blockers);
+        assert_eq!(Pext::bishop_squares(sq, blockers), expected);
+      }
+
+      for blockers in rook_mask(sq).subsets() {
+        let expected = gen_rook_attacks(sq, blockers);
+        assert_eq!(Pext::rook_squares(sq, blockers), expected);
+      }
+    }
+  }
+}
@@ -1,8 +1,29 @@
+//! Perft: count the leaf nodes of the move tree to a given depth
+//!
+//! Perft is the standard way of validating a move generator: the node counts
+//! for a bunch of well-known positions are known, so any discrepancy points
+//! at a bug in the move generation.
+//!
+//! Besides the plain recursive count, we provide a parallel version that
+//! splits the root moves over a number of threads, and a version that caches
+//! subtree counts in a hash table, since the same positions are reached
+//! through many different move orders. Both can be combined, which makes
+//! deep perft runs (depth 7 and beyond) a lot more bearable.
+//!
+//! All of these copy the board for every move they play. The in-place
+//! version plays and takes back the moves on a single board instead.
+
 use crate::board::Board;
 use crate::movegen::legal_moves::All;
 use crate::movegen::moves::Move;
+use crate::movegen::sliders::Sliders;
+use rayon::iter::IntoParallelRefIterator;
+use rayon::iter::ParallelIterator;
+use std::mem::size_of;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 
-impl Board {
+impl<S: Sliders> Board<S> {
   /// Count and return the number of leave nodes at a given depth
   pub fn perft(&self, depth: usize) -> u64 {
     if depth == 0 {
@@ -23,6 +44,81 @@ impl Board {
       .sum()
   }
 
+  /// Count the number of leaf nodes at a given depth, playing the moves
+  /// in-place with `make_move`/`unmake_move` instead of copying the board.
+  pub fn perft_in_place(&mut self, depth: usize) -> u64 {
+    if depth <= 1 {
+      return self.perft(depth);
+    }
+
+    let mut nodes = 0;
+
+    for mv in self.legal_moves::<All>() {
+      let undo = self.make_move(mv);
+      nodes += self.perft_in_place(depth - 1);
+      self.unmake_move(mv, undo);
+    }
+
+    nodes
+  }
+
+  /// Count the number of leaf nodes at a given depth, using the table to
+  /// look up and store the counts for the subtrees we come across.
+  pub fn perft_hashed(&self, depth: usize, table: &PerftTable) -> u64 {
+    if depth <= 1 {
+      return self.perft(depth);
+    }
+
+    let hash = self.hash().into();
+
+    if let Some(nodes) = table.probe(hash, depth) {
+      return nodes;
+    }
+
+    let nodes = self
+      .legal_moves::<All>()
+      .iter()
+      .map(|&mv| self.play_move(mv).perft_hashed(depth - 1, table))
+      .sum();
+
+    table.store(hash, depth, nodes);
+    nodes
+  }
+
+  /// Count the number of leaf nodes at a given depth, splitting the root
+  /// moves over `threads` threads. If a table is provided, the threads share
+  /// it to cache their subtree counts.
+  pub fn perft_parallel(
+    &self,
+    depth: usize,
+    threads: usize,
+    table: Option<&PerftTable>,
+  ) -> u64 {
+    if depth <= 1 {
+      return self.perft(depth);
+    }
+
+    if threads <= 1 {
+      return match table {
+        Some(table) => self.perft_hashed(depth, table),
+        None => self.perft(depth),
+      };
+    }
+
+    let pool = rayon::ThreadPoolBuilder::new()
+      .num_threads(threads)
+      .build()
+      .expect("Failed to start the perft threads");
+
+    let moves = self.legal_moves::<All>();
+    let count = |board: Self| match table {
+      Some(table) => board.perft_hashed(depth - 1, table),
+      None => board.perft(depth - 1),
+    };
+
+    pool.install(|| moves.par_iter().map(|&mv| count(self.play_move(mv))).sum())
+  }
+
   /// Count and return the number of leave nodes at a given depth, grouped
   /// by the first move.
   pub fn perft_divide(&self, depth: usize) -> Vec<(Move, u64)> {
@@ -36,3 +132,87 @@ impl Board {
       .collect()
   }
 }
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Perft table
+//
+////////////////////////////////////////////////////////////////////////////////
+
+/// A hash table of perft counts, keyed by the boards' Zobrist hashes, that
+/// can be shared between threads.
+pub struct PerftTable {
+  entries: Vec<PerftEntry>,
+}
+
+/// A single entry in the perft table.
+///
+/// The entry stores the node count and the depth, packed into a single
+/// `u64`, and the hash of the position XOR-ed with that data. That way, when
+/// two threads write to the same entry at the same time, the mismatching key
+/// and data make sure we never read back a corrupted count.
+#[derive(Default)]
+struct PerftEntry {
+  key: AtomicU64,
+  data: AtomicU64,
+}
+
+impl PerftTable {
+  /// Create a table of the given size, in MB
+  pub fn new(mb_size: usize) -> Self {
+    let size = ((mb_size << 20) / size_of::<PerftEntry>()).max(1);
+    let entries = (0..size).map(|_| PerftEntry::default()).collect();
+
+    Self { entries }
+  }
+
+  fn entry(&self, hash: u64) -> &PerftEntry {
+    let idx = (hash as u128 * self.entries.len() as u128) >> 64;
+    &self.entries[idx as usize]
+  }
+
+  /// Look up the node count for a position at the given depth
+  fn probe(&self, hash: u64, depth: usize) -> Option<u64> {
+    let entry = self.entry(hash);
+    let data = entry.data.load(Ordering::Relaxed);
+    let key = entry.key.load(Ordering::Relaxed);
+
+    if key ^ data == hash && data & 0xff == depth as u64 {
+      Some(data >> 8)
+    } else {
+      None
+    }
+  }
+
+  /// Store the node count for a position at the given depth, overwriting
+  /// whatever was there before.
+  fn store(&self, hash: u64, depth: usize, nodes: u64) {
+    let entry = self.entry(hash);
+    let data = nodes << 8 | depth as u64;
+    entry.key.store(hash ^ data, Ordering::Relaxed);
+    entry.data.store(data, Ordering::Relaxed);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const KIWIPETE: &str =
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+  #[test]
+  fn parallel_perft_matches() {
+    let board: Board = KIWIPETE.parse().unwrap();
+    assert_eq!(board.perft_parallel(3, 4, None), 97862);
+  }
+
+  #[test]
+  fn hashed_perft_matches() {
+    let board: Board = KIWIPETE.parse().unwrap();
+    let table = PerftTable::new(1);
+
+    assert_eq!(board.perft_hashed(4, &table), 4085603);
+    assert_eq!(board.perft_parallel(4, 4, Some(&table)), 4085603);
+  }
+}
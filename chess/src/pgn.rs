@@ -0,0 +1,521 @@
+//! Reading and writing games in Portable Game Notation (PGN)
+
+use crate::board::Board;
+use crate::movegen::moves::Move;
+use crate::san::ToSan;
+use anyhow::anyhow;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The maximum line length of the movetext section, as recommended by the PGN
+/// specification.
+const MAX_LINE_LENGTH: usize = 80;
+
+/// A game, as recorded in a PGN file
+#[derive(Debug, Clone)]
+pub struct Pgn {
+  /// The tag pairs describing the game, in the order they will be written
+  pub tags: Vec<(String, String)>,
+
+  /// The position the game was started from
+  pub board: Board,
+
+  /// The moves played in the game
+  pub moves: Vec<Move>,
+
+  /// Annotations for the moves in the game, where the annotation at index
+  /// `i` belongs to the `i`-th move. Moves without a corresponding entry are
+  /// not annotated.
+  pub annotations: Vec<Annotation>,
+}
+
+/// Additional information attached to a move
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Annotation {
+  /// Numeric Annotation Glyphs (e.g., 2 for `?`, 4 for `??`)
+  pub nags: Vec<u8>,
+
+  /// A comment following the move
+  pub comment: Option<String>,
+
+  /// An alternative line, starting from the position _before_ the move
+  pub variation: Vec<Move>,
+}
+
+impl Pgn {
+  /// Create a new game record, starting from the provided position, with the
+  /// "Seven Tag Roster" filled in with placeholder values.
+  pub fn new(board: Board) -> Self {
+    let mut pgn = Self {
+      tags: Vec::new(),
+      board,
+      moves: Vec::new(),
+      annotations: Vec::new(),
+    };
+
+    pgn.set_tag("Event", "?");
+    pgn.set_tag("Site", "?");
+    pgn.set_tag("Date", "????.??.??");
+    pgn.set_tag("Round", "?");
+    pgn.set_tag("White", "?");
+    pgn.set_tag("Black", "?");
+    pgn.set_tag("Result", "*");
+
+    // Games that don't start from the initial position need to say so
+    if board.to_fen() != Board::default().to_fen() {
+      pgn.set_tag("SetUp", "1");
+      pgn.set_tag("FEN", &board.to_fen());
+    }
+
+    pgn
+  }
+
+  /// Set the value of a tag, overwriting the previous value if it was
+  /// already set.
+  pub fn set_tag(&mut self, name: &str, value: &str) {
+    match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+      Some((_, old)) => *old = value.to_string(),
+      None => self.tags.push((name.to_string(), value.to_string())),
+    }
+  }
+
+  /// Get the value of a tag, if it was set.
+  pub fn tag(&self, name: &str) -> Option<&str> {
+    self
+      .tags
+      .iter()
+      .find(|(tag, _)| tag == name)
+      .map(|(_, value)| value.as_str())
+  }
+
+  /// The result of the game ("1-0", "0-1", "1/2-1/2", or "*" when the game
+  /// hasn't finished)
+  pub fn result(&self) -> &str {
+    self.tag("Result").unwrap_or("*")
+  }
+}
+
+impl Display for Pgn {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    for (name, value) in self.tags.iter() {
+      let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+      writeln!(f, "[{name} \"{value}\"]")?;
+    }
+
+    writeln!(f)?;
+
+    // Collect the movetext into tokens, so we can wrap the lines
+    let mut tokens = Vec::new();
+    let mut board = self.board;
+
+    // Black's moves need a move number when they're the first move, or when
+    // they follow a comment or variation.
+    let mut needs_number = true;
+
+    for (i, &mv) in self.moves.iter().enumerate() {
+      let annotation = self.annotations.get(i);
+
+      // Keep move numbers and moves together, so they don't get split up
+      // when wrapping lines.
+      let number = if board.current.is_white() {
+        format!("{}. ", board.full_moves)
+      } else if needs_number {
+        format!("{}... ", board.full_moves)
+      } else {
+        String::new()
+      };
+
+      let nags = annotation.map(|ann| ann.nags.as_slice()).unwrap_or(&[]);
+      let san = mv.to_san(&board);
+      tokens.push(format!("{number}{san}{}", format_nags(nags)));
+      needs_number = false;
+
+      if let Some(annotation) = annotation {
+        if let Some(comment) = &annotation.comment {
+          tokens.push(format!("{{{comment}}}"));
+          needs_number = true;
+        }
+
+        if !annotation.variation.is_empty() {
+          let mut variation = movetext(board, &annotation.variation);
+          variation[0].insert(0, '(');
+          variation.last_mut().unwrap().push(')');
+          tokens.extend(variation);
+          needs_number = true;
+        }
+      }
+
+      board = board.play_move(mv);
+    }
+
+    tokens.push(self.result().to_string());
+
+    let mut line = String::new();
+
+    for token in tokens {
+      if !line.is_empty() && line.len() + token.len() + 1 > MAX_LINE_LENGTH {
+        writeln!(f, "{line}")?;
+        line.clear();
+      }
+
+      if !line.is_empty() {
+        line.push(' ');
+      }
+
+      line.push_str(&token);
+    }
+
+    writeln!(f, "{line}")
+  }
+}
+
+/// Render a sequence of moves, starting from the provided board, as a list
+/// of SAN tokens, prefixed with move numbers where needed.
+fn movetext(mut board: Board, moves: &[Move]) -> Vec<String> {
+  let mut tokens = Vec::new();
+
+  for (i, &mv) in moves.iter().enumerate() {
+    let san = mv.to_san(&board);
+
+    if board.current.is_white() {
+      tokens.push(format!("{}. {san}", board.full_moves));
+    } else if i == 0 {
+      tokens.push(format!("{}... {san}", board.full_moves));
+    } else {
+      tokens.push(san);
+    }
+
+    board = board.play_move(mv);
+  }
+
+  tokens
+}
+
+/// Render NAGs, using the conventional symbols for the common ones.
+fn format_nags(nags: &[u8]) -> String {
+  nags
+    .iter()
+    .map(|nag| match nag {
+      1 => "!".to_string(),
+      2 => "?".to_string(),
+      3 => "!!".to_string(),
+      4 => "??".to_string(),
+      5 => "!?".to_string(),
+      6 => "?!".to_string(),
+      nag => format!(" ${nag}"),
+    })
+    .collect()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Parsing
+//
+////////////////////////////////////////////////////////////////////////////////
+
+/// The tokens that make up a PGN file
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+  Tag(String, String),
+  San(String),
+  Result(String),
+  VariationStart,
+  VariationEnd,
+}
+
+/// Split a PGN file into tokens. Comments, NAGs, move numbers and escaped
+/// lines are dropped along the way.
+fn tokenize(input: &str) -> anyhow::Result<Vec<Token>> {
+  let mut tokens = Vec::new();
+  let mut chars = input.chars().peekable();
+  let mut line_start = true;
+
+  while let Some(c) = chars.next() {
+    match c {
+      // Lines starting with `%` are escaped, and should be ignored
+      '%' if line_start => {
+        chars.by_ref().take_while(|&c| c != '\n').for_each(drop);
+      }
+
+      // Rest-of-line comment
+      ';' => {
+        chars.by_ref().take_while(|&c| c != '\n').for_each(drop);
+      }
+
+      // Brace comment
+      '{' => {
+        chars.by_ref().take_while(|&c| c != '}').for_each(drop);
+      }
+
+      // Tag pair, `[Name "Value"]`
+      '[' => {
+        let name = chars
+          .by_ref()
+          .skip_while(|c| c.is_whitespace())
+          .take_while(|c| !c.is_whitespace())
+          .collect::<String>();
+
+        chars.by_ref().take_while(|&c| c != '"').for_each(drop);
+
+        let mut value = String::new();
+
+        while let Some(c) = chars.next() {
+          match c {
+            '\\' => value.extend(chars.next()),
+            '"' => break,
+            c => value.push(c),
+          }
+        }
+
+        chars.by_ref().take_while(|&c| c != ']').for_each(drop);
+        tokens.push(Token::Tag(name, value));
+      }
+
+      '(' => tokens.push(Token::VariationStart),
+      ')' => tokens.push(Token::VariationEnd),
+
+      c if c.is_whitespace() => {}
+
+      // Anything else is a word: a move number, NAG, result, or move
+      c => {
+        let mut word = String::from(c);
+
+        while let Some(&c) = chars.peek() {
+          if c.is_whitespace() || "{}()[];".contains(c) {
+            break;
+          }
+
+          word.push(c);
+          chars.next();
+        }
+
+        match word.as_str() {
+          "1-0" | "0-1" | "1/2-1/2" | "*" => {
+            tokens.push(Token::Result(word));
+          }
+
+          word if word.starts_with('$') => {}
+
+          word => {
+            // Strip any leading move numbers (`12.`, `12...`, `12.e4`)
+            let san =
+              word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+            if !san.is_empty() {
+              tokens.push(Token::San(san.to_string()));
+            }
+          }
+        }
+      }
+    }
+
+    line_start = c == '\n';
+  }
+
+  Ok(tokens)
+}
+
+impl Pgn {
+  /// Parse all the games in a PGN file.
+  ///
+  /// Only the tags and the main line are kept: any comments, NAGs and
+  /// variations in the input are dropped.
+  pub fn parse_all(input: &str) -> anyhow::Result<Vec<Pgn>> {
+    let mut games = Vec::new();
+    let mut tags: Vec<(String, String)> = Vec::new();
+    let mut moves = Vec::new();
+    let mut start: Option<Board> = None;
+    let mut board = Board::default();
+    let mut depth = 0;
+
+    let mut finish =
+      |tags: &mut Vec<_>, moves: &mut Vec<_>, start: &mut Option<Board>| {
+        if !tags.is_empty() || !moves.is_empty() {
+          games.push(Pgn {
+            tags: std::mem::take(tags),
+            board: start.take().unwrap_or_default(),
+            moves: std::mem::take(moves),
+            annotations: Vec::new(),
+          });
+        }
+      };
+
+    for token in tokenize(input)? {
+      match token {
+        Token::VariationStart => depth += 1,
+        Token::VariationEnd => depth -= 1,
+
+        // Skip everything inside variations
+        _ if depth > 0 => {}
+
+        Token::Tag(name, value) => {
+          // A tag after the movetext starts a new game
+          if start.is_some() {
+            finish(&mut tags, &mut moves, &mut start);
+          }
+
+          tags.push((name, value));
+        }
+
+        Token::San(san) => {
+          // The starting position is only known once all tags are read
+          let board = match start {
+            Some(_) => &mut board,
+            None => {
+              let initial = match tags.iter().find(|(tag, _)| tag == "FEN") {
+                Some((_, fen)) => fen.parse()?,
+                None => Board::default(),
+              };
+
+              start = Some(initial);
+              board = initial;
+              &mut board
+            }
+          };
+
+          let mv = Move::from_san(&san, board)
+            .ok_or(anyhow!("Illegal move in PGN: {san}"))?;
+
+          *board = board.play_move(mv);
+          moves.push(mv);
+        }
+
+        Token::Result(result) => {
+          match tags.iter_mut().find(|(tag, _)| tag == "Result") {
+            Some((_, value)) => *value = result,
+            None => tags.push(("Result".to_string(), result)),
+          }
+
+          start.get_or_insert_with(Board::default);
+          finish(&mut tags, &mut moves, &mut start);
+        }
+      }
+    }
+
+    finish(&mut tags, &mut moves, &mut start);
+
+    Ok(games)
+  }
+}
+
+impl FromStr for Pgn {
+  type Err = anyhow::Error;
+
+  /// Parse the first game in a PGN file
+  fn from_str(s: &str) -> anyhow::Result<Self> {
+    Pgn::parse_all(s)?
+      .into_iter()
+      .next()
+      .ok_or(anyhow!("No games found in PGN"))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::movegen::moves::BareMove;
+
+  fn play(board: Board, moves: &[&str]) -> Vec<Move> {
+    let mut board = board;
+    let mut played = Vec::new();
+
+    for mv in moves {
+      let bare: BareMove = mv.parse().unwrap();
+      let mv = board.find_move(bare).unwrap();
+      played.push(mv);
+      board = board.play_move(mv);
+    }
+
+    played
+  }
+
+  #[test]
+  fn test_movetext() {
+    let mut pgn = Pgn::new(Board::default());
+    pgn.moves = play(pgn.board, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+    pgn.set_tag("Result", "0-1");
+
+    let output = pgn.to_string();
+
+    assert!(output.starts_with("[Event \"?\"]\n"));
+    assert!(output.contains("[Result \"0-1\"]\n"));
+    assert!(!output.contains("FEN"));
+    assert!(output.ends_with("\n1. f3 e5 2. g4 Qh4# 0-1\n"));
+  }
+
+  #[test]
+  fn test_black_to_move() {
+    let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+    let mut pgn = Pgn::new(fen.parse().unwrap());
+    pgn.moves = play(pgn.board, &["e7e5", "g1f3"]);
+
+    let output = pgn.to_string();
+
+    assert!(output.contains(&format!("[FEN \"{fen}\"]")));
+    assert!(output.ends_with("\n1... e5 2. Nf3 *\n"));
+  }
+
+  #[test]
+  fn test_parse() {
+    let input = r#"
+[Event "Test"]
+[White "Alice"]
+[Result "1-0"]
+
+1. e4 {A comment} e5 (1... c5 2. Nf3) 2. Qh5?! $6 Nc6
+3.Bc4 Nf6?? ; Oops
+4. Qxf7# 1-0
+
+[Event "Second"]
+[SetUp "1"]
+[FEN "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"]
+
+1... e5 *
+"#;
+
+    let games = Pgn::parse_all(input).unwrap();
+    assert_eq!(games.len(), 2);
+
+    let first = &games[0];
+    assert_eq!(first.tag("White"), Some("Alice"));
+    assert_eq!(first.result(), "1-0");
+    assert_eq!(
+      first.moves,
+      play(
+        Board::default(),
+        &["e2e4", "e7e5", "d1h5", "b8c6", "f1c4", "g8f6", "h5f7"]
+      )
+    );
+
+    let second = &games[1];
+    assert_eq!(second.tag("Event"), Some("Second"));
+    assert!(second.board.current.is_black());
+    assert_eq!(second.moves, play(second.board, &["e7e5"]));
+    assert_eq!(second.result(), "*");
+  }
+
+  #[test]
+  fn test_annotations() {
+    let mut pgn = Pgn::new(Board::default());
+    pgn.moves = play(pgn.board, &["e2e4", "e7e5", "d1h5"]);
+    pgn.annotations = vec![
+      Annotation::default(),
+      Annotation {
+        comment: Some("[%eval 0.30]".to_string()),
+        ..Annotation::default()
+      },
+      Annotation {
+        nags: vec![6],
+        variation: play(pgn.board, &["e2e4", "e7e5", "g1f3"])[2..].to_vec(),
+        comment: None,
+      },
+    ];
+
+    let output = pgn.to_string();
+    assert!(output.ends_with("\n1. e4 e5 {[%eval 0.30]} 2. Qh5?! (2. Nf3) *\n"));
+
+    // Annotated games can be read back
+    let parsed: Pgn = output.parse().unwrap();
+    assert_eq!(parsed.moves, pgn.moves);
+  }
+}
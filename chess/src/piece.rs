@@ -12,6 +12,7 @@ use PieceType::*;
 
 use crate::bitboard::Bitboard;
 use crate::board::Board;
+use crate::movegen::sliders::Sliders;
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 /// A Piece
@@ -323,7 +324,7 @@ impl<T> IndexMut<Piece> for [T; 12] {
   }
 }
 
-impl Index<PieceType> for Board {
+impl<S: Sliders> Index<PieceType> for Board<S> {
   type Output = Bitboard;
 
   fn index(&self, piece_type: PieceType) -> &Self::Output {
@@ -331,7 +332,7 @@ impl Index<PieceType> for Board {
   }
 }
 
-impl Index<Color> for Board {
+impl<S: Sliders> Index<Color> for Board<S> {
   type Output = Bitboard;
 
   fn index(&self, color: Color) -> &Self::Output {
@@ -4,6 +4,8 @@ use crate::constants::RANKS;
 use crate::movegen::castling::CastleType;
 use crate::movegen::legal_moves::All;
 use crate::movegen::moves::Move;
+use crate::movegen::sliders::Magics;
+use crate::movegen::sliders::Sliders;
 use crate::piece::PieceType;
 use std::fmt::Write;
 
@@ -52,9 +54,9 @@ impl ToSan for Move {
     let sources = match piece_type {
       Pawn => self.tgt().pawn_squares(!us, blockers),
       Knight => self.tgt().knight_squares(),
-      Bishop => self.tgt().bishop_squares(blockers),
-      Rook => self.tgt().rook_squares(blockers),
-      Queen => self.tgt().queen_squares(blockers),
+      Bishop => Magics::bishop_squares(self.tgt(), blockers),
+      Rook => Magics::rook_squares(self.tgt(), blockers),
+      Queen => Magics::queen_squares(self.tgt(), blockers),
       King => self.tgt().king_squares(),
     };
 
@@ -131,6 +133,31 @@ impl PieceType {
   }
 }
 
+impl Move {
+  /// Find the legal move on the board that matches the provided move in
+  /// Short Algebraic Notation.
+  ///
+  /// We're lenient in what we accept: check(mate) markers and annotations
+  /// (`!`, `?`) are ignored, castling may be written with zeroes, and the `=`
+  /// in promotions is optional.
+  pub fn from_san(san: &str, board: &Board) -> Option<Self> {
+    let normalize = |san: &str| {
+      san
+        .trim()
+        .trim_end_matches(['+', '#', '!', '?'])
+        .replace('0', "O")
+        .replace('=', "")
+    };
+
+    let san = normalize(san);
+
+    board
+      .legal_moves::<All>()
+      .into_iter()
+      .find(|mv| normalize(&mv.to_san(board)) == san)
+  }
+}
+
 #[derive(Copy, Clone)]
 enum CheckState {
   Check,
@@ -196,6 +223,26 @@ mod tests {
       }
     }
   }
+
+  #[test]
+  fn test_from_san() {
+    for pos in SAN_SUITE {
+      let mut parts = pos.split(";");
+      let fen = parts.next().unwrap().trim();
+      let uci = parts.next().unwrap().trim();
+      let san = parts.next().unwrap().trim();
+      let board: Board = fen.parse().unwrap();
+      let bare_move = BareMove::from_str(uci).expect("Invalid move: {uci}");
+
+      assert_eq!(
+        Move::from_san(san, &board),
+        Move::from_bare(bare_move, &board)
+      );
+    }
+
+    let board = Board::default();
+    assert!(Move::from_san("Nf6", &board).is_none());
+  }
 }
 
 impl<T: IntoIterator<Item = Move>> ToSan for T {
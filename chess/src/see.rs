@@ -12,6 +12,7 @@
 use crate::bitboard::Bitboard;
 use crate::board::Board;
 use crate::movegen::moves::Move;
+use crate::movegen::sliders::Sliders;
 use crate::piece::Color;
 use crate::piece::PieceType;
 use crate::square::Square;
@@ -21,7 +22,7 @@ type Eval = i32;
 pub const SEE_VALUES: [i32; PieceType::COUNT] =
   [100, 300, 300, 500, 900, 10000];
 
-impl Board {
+impl<S: Sliders> Board<S> {
   /// Check whether a move passes a given SEE threshold by trading off all the
   /// pieces attacking the target square.
   pub fn see(&self, mv: Move, threshold: Eval) -> bool {
@@ -122,11 +123,11 @@ impl Board {
 
       // Any discovered attackers?
       if attacker.is_pawn() || attacker.is_diag_slider() {
-        attackers |= tgt.bishop_squares(remaining) & diag_sliders;
+        attackers |= S::bishop_squares(tgt, remaining) & diag_sliders;
       }
 
       if attacker.is_hv_slider() {
-        attackers |= tgt.rook_squares(remaining) & hv_sliders;
+        attackers |= S::rook_squares(tgt, remaining) & hv_sliders;
       }
 
       // Update balance
@@ -11,6 +11,7 @@ use crate::movegen::lookups::KNIGHT_ATTACKS;
 use crate::movegen::lookups::PAWN_ATTACKS;
 use crate::movegen::lookups::PAWN_DBLPUSHES;
 use crate::movegen::lookups::PAWN_PUSHES;
+use crate::movegen::sliders::Sliders;
 use crate::piece::Color;
 use crate::piece::Piece;
 use anyhow::anyhow;
@@ -236,11 +237,6 @@ impl Square {
     KNIGHT_ATTACKS[self]
   }
 
-  /// Get a bitboard for all the squares visible to a queen on this square.
-  pub fn queen_squares(self, blockers: Bitboard) -> Bitboard {
-    self.bishop_squares(blockers) | self.rook_squares(blockers)
-  }
-
   /// Get a bitboard for all the squares visible to a king on this square.
   pub fn king_squares(self) -> Bitboard {
     KING_ATTACKS[self]
@@ -306,7 +302,7 @@ impl<T> IndexMut<Square> for [T; 64] {
   }
 }
 
-impl Index<Square> for Board {
+impl<S: Sliders> Index<Square> for Board<S> {
   type Output = Option<Piece>;
 
   fn index(&self, sq: Square) -> &Self::Output {
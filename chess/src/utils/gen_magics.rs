@@ -1,184 +1,447 @@
-// use simbelmyne_chess::{bitboard::Bitboard, movegen::lookups::{bishop_mask, rook_mask}, square::Square};
-// use simbelmyne_chess::movegen::magics::MagicEntry;
-//
-// const BISHOP: bool = true;
-// const ROOK: bool = false;
-//
-//
-// fn main() {
-//     let bishop_magics = gen_magics::<BISHOP>();
-//     let rook_magics = gen_magics::<ROOK>();
-//
-//     println!("Bishop magics:\n{bishop_magics:#?}");
-//     println!("Rook magics:\n{rook_magics:#?}");
-// }
-//
-// ////////////////////////////////////////////////////////////////////////////////
-// //
-// // Find magics
-// //
-// ////////////////////////////////////////////////////////////////////////////////
-//
-// /// The number of bits we expect the magic number for each square to hash into
-// const ROOK_KEY_WIDTH: [u32; Square::COUNT] = [
-//   12, 11, 11, 11, 11, 11, 11, 12,
-//   11, 10, 10, 10, 10, 10, 10, 11,
-//   11, 10, 10, 10, 10, 10, 10, 11,
-//   11, 10, 10, 10, 10, 10, 10, 11,
-//   11, 10, 10, 10, 10, 10, 10, 11,
-//   11, 10, 10, 10, 10, 10, 10, 11,
-//   11, 10, 10, 10, 10, 10, 10, 11,
-//   12, 11, 11, 11, 11, 11, 11, 12
-// ];
-//
-// /// The number of bits we expect the magic number for each square to hash into
-// const BISHOP_KEY_WIDTH: [u32; Square::COUNT] = [
-//   6, 5, 5, 5, 5, 5, 5, 6,
-//   5, 5, 5, 5, 5, 5, 5, 5,
-//   5, 5, 7, 7, 7, 7, 5, 5,
-//   5, 5, 7, 9, 9, 7, 5, 5,
-//   5, 5, 7, 9, 9, 7, 5, 5,
-//   5, 5, 7, 7, 7, 7, 5, 5,
-//   5, 5, 5, 5, 5, 5, 5, 5,
-//   6, 5, 5, 5, 5, 5, 5, 6
-// ];
-//
-//
-// /// Generate a set of magic numbers for a slider type
-// pub fn gen_magics<const BISHOP: bool>() -> [MagicEntry; Square::COUNT] {
-//     let mut offset = 0;
-//     let mut magics: [MagicEntry; Square::COUNT] = [
-//         MagicEntry { magic: 0, mask: Bitboard::EMPTY, shift: 0, offset: 0 }; 
-//         Square::COUNT
-//     ];
-//
-//     for sq in Square::ALL {
-//         let mask = if BISHOP { 
-//             bishop_mask(sq) 
-//         } else { 
-//             rook_mask(sq) 
-//         };
-//
-//
-//         let num_bits = if BISHOP { 
-//             BISHOP_KEY_WIDTH[sq] 
-//         } else { 
-//             ROOK_KEY_WIDTH[sq] 
-//         };
-//
-//         let shift = 64 - num_bits as u8;
-//         let magic = find_magic(mask, num_bits);
-//         let entry = MagicEntry { magic, mask, shift, offset };
-//
-//         magics[sq] = entry;
-//
-//         offset += 1 << num_bits;
-//     }
-//
-//     magics
-// }
-//
-// /// Find a single magic number for the given movement mask that maps to a 
-// /// keyspace of `bits` bits wide.
-// fn find_magic(mask: Bitboard, bits: u32) -> u64 {
-//     let mut rng = Rng::new();
-//
-//     loop  {
-//         let candidate = rng.rand() & rng.rand() & rng.rand();
-//
-//         if is_magic(candidate, mask, bits) {
-//             return candidate;
-//         } 
-//     }
-// }
-//
-// /// Check whether a supposed magic number manages to map every subset in the
-// /// mask to a unique index. If there's no collisions, the number's magic!
-// fn is_magic(candidate: u64, mask: Bitboard, bits: u32) -> bool {
-//     let shift = 64 - bits;
-//     let mut seen: [bool; 4096] = [false; 4096];
-//     
-//     if candidate.wrapping_mul(*mask) >> shift < 6 { return false };
-//
-//     for subset in Subsets::from(mask) {
-//         let index = candidate.wrapping_mul(subset.0) >> shift;
-//
-//         if seen[index as usize] {
-//             return false;
-//         } else {
-//             seen[index as usize] = true;
-//         }
-//     }
-//
-//     true
-// }
-//
-//
-// ////////////////////////////////////////////////////////////////////////////////
-// //
-// // Utilities
-// //
-// ////////////////////////////////////////////////////////////////////////////////
-//
-// /// Helper struct to iterate over all the subsets of a bitboard
-// pub struct Subsets { 
-//     subset: Bitboard,
-//     mask: Bitboard,
-//     done: bool
-// }
-//
-// impl From<Bitboard> for Subsets {
-//     fn from(value: Bitboard) -> Self {
-//         Subsets {
-//             subset: Bitboard::EMPTY,
-//             mask: value,
-//             done: false
-//         }
-//     }
-// }
-//
-// impl Iterator for Subsets {
-//     type Item = Bitboard;
-//
-//     fn next(&mut self) -> Option<Self::Item> {
-//         if self.done { return None; }
-//
-//         self.subset = Bitboard(
-//             self.subset.0.wrapping_sub(self.mask.0) & self.mask.0
-//         );
-//
-//         if self.subset.is_empty() {
-//             self.done = true;
-//         }
-//
-//         Some(self.subset)
-//     }
-// }
-//
-// /// Quick and dirty RNG
-// struct Rng {
-//     state: u128,
-// }
-//
-// impl Rng {
-//     fn new() -> Self {
-//         use std::time;
-//         Self { 
-//             state: time::SystemTime::now()
-//                 .duration_since(time::UNIX_EPOCH)
-//                 .unwrap()
-//                 .as_nanos() 
-//         }
-//     }
-//
-//     fn rand(&mut self) -> u64 {
-//         let mut x = self.state;
-//         x ^= x >> 12;
-//         x ^= x << 25;
-//         x ^= x >> 27;
-//         self.state = x;
-//         #[allow(clippy::cast_possible_truncation)]
-//         let r = x as u64; // truncation is the intended behavior here.
-//         r ^ (x >> 64) as u64 // add in the high bits.
-//     }
-// }
+//! Generate the magic numbers used for the sliding piece lookups
+//!
+//! Usage:
+//!
+//! ```text
+//! cargo run --release -p simbelmyne-chess --bin magics -- [OPTIONS]
+//!
+//! Options:
+//!   --black        Generate fixed-shift "black" magics
+//!   --tries <N>    Number of candidate magics to try per square (default 1)
+//!   --seed <N>     Seed for the random number generator
+//! ```
+//!
+//! Regular magics map the relevant blockers for a square onto a table with
+//! exactly as many bits as there are relevant squares. The tables for all
+//! squares are laid out one after the other.
+//!
+//! Black magics OR the complement of the mask into the blockers before
+//! multiplying, and use a fixed shift for every square (see
+//! `BlackMagicEntry`). That leaves gaps in
+//! the individual tables, which allows us to make them overlap wherever their
+//! entries agree. The more candidates we try for every square, the better the
+//! odds of finding a tight fit. Note that it takes a long search for black
+//! magics to beat the regular layout: with only a handful of tries, the table
+//! ends up larger. The table sizes are reported, so it's easy to tell whether
+//! a run found anything worth keeping.
+//!
+//! Every set of magics is checked against the ray-based attack generator for
+//! every subset of blockers, before being printed as Rust source that can be
+//! pasted into `movegen/magics.rs`. Note that the move generator only uses
+//! regular magics, so black magics need the lookups to be switched over to
+//! `BlackMagicEntry` as well.
+
+use anyhow::anyhow;
+use simbelmyne_chess::bitboard::Bitboard;
+use simbelmyne_chess::movegen::lookups::bishop_mask;
+use simbelmyne_chess::movegen::lookups::gen_bishop_attacks;
+use simbelmyne_chess::movegen::lookups::gen_rook_attacks;
+use simbelmyne_chess::movegen::lookups::rook_mask;
+use simbelmyne_chess::movegen::magics::BlackMagicEntry;
+use simbelmyne_chess::movegen::magics::MagicEntry;
+use simbelmyne_chess::square::Square;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+const USAGE: &str = "Usage: magics [--black] [--tries <N>] [--seed <N>]";
+
+fn main() -> anyhow::Result<()> {
+  let config = Config::from_args(std::env::args().skip(1))?;
+  let mut rng = Rng::new(config.seed);
+
+  eprintln!("Using seed {}", config.seed);
+
+  let bishops = gen_magics(Slider::Bishop, &config, &mut rng);
+  verify(Slider::Bishop, &bishops)?;
+  eprintln!("Bishop table: {} entries", bishops.size);
+
+  let rooks = gen_magics(Slider::Rook, &config, &mut rng);
+  verify(Slider::Rook, &rooks)?;
+  eprintln!("Rook table: {} entries", rooks.size);
+
+  println!("/// The number of entries in the bishop attack table");
+  println!("pub const BISHOP_TABLE_SIZE: usize = {};", bishops.size);
+  println!();
+  println!("/// The number of entries in the rook attack table");
+  println!("pub const ROOK_TABLE_SIZE: usize = {};", rooks.size);
+  println!();
+  print_entries("BISHOP_MAGICS", &bishops.entries);
+  println!();
+  print_entries("ROOK_MAGICS", &rooks.entries);
+
+  Ok(())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Configuration
+//
+////////////////////////////////////////////////////////////////////////////////
+
+struct Config {
+  /// Whether to generate fixed-shift black magics
+  black: bool,
+
+  /// The number of candidate magics to try for every square
+  tries: usize,
+
+  /// The seed for the random number generator
+  seed: u64,
+}
+
+impl Config {
+  fn from_args(mut args: impl Iterator<Item = String>) -> anyhow::Result<Self> {
+    let mut config = Self {
+      black: false,
+      tries: 1,
+      seed: SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos() as u64,
+    };
+
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "--black" => config.black = true,
+
+        "--tries" => {
+          let tries = args.next().ok_or(anyhow!(USAGE))?;
+          config.tries = tries.parse::<usize>()?.max(1);
+        }
+
+        "--seed" => {
+          let seed = args.next().ok_or(anyhow!(USAGE))?;
+          config.seed = seed.parse()?;
+        }
+
+        _ => return Err(anyhow!(USAGE)),
+      }
+    }
+
+    Ok(config)
+  }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Sliders
+//
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Copy, Clone)]
+enum Slider {
+  Bishop,
+  Rook,
+}
+
+impl Slider {
+  /// The squares whose occupancy affects the slider's attacks
+  fn mask(self, square: Square) -> Bitboard {
+    match self {
+      Slider::Bishop => bishop_mask(square),
+      Slider::Rook => rook_mask(square),
+    }
+  }
+
+  /// The slider's attacks, computed by walking the rays
+  fn attacks(self, square: Square, blockers: Bitboard) -> Bitboard {
+    match self {
+      Slider::Bishop => gen_bishop_attacks(square, blockers),
+      Slider::Rook => gen_rook_attacks(square, blockers),
+    }
+  }
+
+  /// The fixed number of index bits for black magics, i.e., the size of the
+  /// largest mask.
+  fn black_bits(self) -> u32 {
+    match self {
+      Slider::Bishop => 9,
+      Slider::Rook => 12,
+    }
+  }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Find magics
+//
+////////////////////////////////////////////////////////////////////////////////
+
+/// A magic entry of either kind
+#[derive(Debug, Copy, Clone)]
+enum Entry {
+  Regular(MagicEntry),
+  Black(BlackMagicEntry),
+}
+
+impl Entry {
+  /// The squares whose occupancy affects the attacks
+  fn mask(&self) -> Bitboard {
+    match self {
+      Entry::Regular(entry) => entry.mask,
+      Entry::Black(entry) => entry.mask,
+    }
+  }
+
+  /// The index of the attacks for a set of blockers, the same way the move
+  /// generator computes it.
+  fn index(&self, blockers: Bitboard) -> usize {
+    match self {
+      Entry::Regular(entry) => entry.index(blockers),
+      Entry::Black(entry) => entry.index(blockers),
+    }
+  }
+}
+
+/// A full set of magics, along with the size of the table they index into
+struct Magics {
+  entries: Vec<Entry>,
+  size: usize,
+}
+
+/// Generate a set of magic numbers for a slider type
+fn gen_magics(slider: Slider, config: &Config, rng: &mut Rng) -> Magics {
+  let mut table: Vec<Bitboard> = Vec::new();
+  let mut entries = Vec::new();
+
+  for square in Square::ALL {
+    let mask = slider.mask(square);
+
+    let (bits, fill) = if config.black {
+      (slider.black_bits(), !mask)
+    } else {
+      (mask.count(), Bitboard::EMPTY)
+    };
+
+    let attacks = mask
+      .subsets()
+      .map(|blockers| (blockers, slider.attacks(square, blockers)))
+      .collect::<Vec<_>>();
+
+    // Regular magics are simply appended to the table, so there's no point in
+    // trying more than one.
+    let tries = if config.black { config.tries } else { 1 };
+    let mut best: Option<(u64, Vec<Bitboard>, usize)> = None;
+
+    for _ in 0..tries {
+      let (magic, subtable) = find_magic(&attacks, fill, bits, rng);
+      let offset = if config.black {
+        find_offset(&table, &subtable)
+      } else {
+        table.len()
+      };
+
+      let end = offset + subtable.len();
+
+      if best
+        .as_ref()
+        .is_none_or(|(_, sub, off)| end < off + sub.len())
+      {
+        best = Some((magic, subtable, offset));
+      }
+    }
+
+    let (magic, subtable, offset) = best.expect("We try at least once");
+    place(&mut table, &subtable, offset);
+
+    let shift = (64 - bits) as u8;
+    let offset = offset as u32;
+
+    entries.push(if config.black {
+      Entry::Black(BlackMagicEntry {
+        mask,
+        magic,
+        shift,
+        offset,
+      })
+    } else {
+      Entry::Regular(MagicEntry {
+        mask,
+        magic,
+        shift,
+        offset,
+      })
+    });
+  }
+
+  Magics {
+    entries,
+    size: table.len(),
+  }
+}
+
+/// Find a magic number that maps every set of blockers onto a `bits`-wide
+/// index, without mapping different attacks onto the same index. Returns the
+/// magic, along with the table of attacks it produces. Unused entries in the
+/// table are left empty.
+fn find_magic(
+  attacks: &[(Bitboard, Bitboard)],
+  fill: Bitboard,
+  bits: u32,
+  rng: &mut Rng,
+) -> (u64, Vec<Bitboard>) {
+  let shift = 64 - bits;
+
+  'search: loop {
+    // Magics with few bits set tend to work better
+    let magic = rng.next_u64() & rng.next_u64() & rng.next_u64();
+    let mut subtable = vec![Bitboard::EMPTY; 1 << bits];
+
+    for &(blockers, attacks) in attacks {
+      let idx = ((blockers.0 | fill.0).wrapping_mul(magic) >> shift) as usize;
+
+      if subtable[idx].is_empty() {
+        subtable[idx] = attacks;
+      } else if subtable[idx] != attacks {
+        continue 'search;
+      }
+    }
+
+    // Drop unused entries at the end, they might as well overlap with
+    // whatever comes after.
+    while subtable.last().is_some_and(|bb| bb.is_empty()) {
+      subtable.pop();
+    }
+
+    return (magic, subtable);
+  }
+}
+
+/// Find the lowest offset at which the subtable fits into the table, without
+/// overwriting any entries that disagree with it.
+fn find_offset(table: &[Bitboard], subtable: &[Bitboard]) -> usize {
+  let fits = |offset: usize| {
+    subtable.iter().enumerate().all(|(i, &attacks)| {
+      let existing = table.get(offset + i).copied().unwrap_or_default();
+      attacks.is_empty() || existing.is_empty() || existing == attacks
+    })
+  };
+
+  (0..=table.len())
+    .find(|&offset| fits(offset))
+    .unwrap_or(table.len())
+}
+
+/// Copy the subtable into the table at the given offset
+fn place(table: &mut Vec<Bitboard>, subtable: &[Bitboard], offset: usize) {
+  let end = offset + subtable.len();
+
+  if table.len() < end {
+    table.resize(end, Bitboard::EMPTY);
+  }
+
+  for (i, &attacks) in subtable.iter().enumerate() {
+    if !attacks.is_empty() {
+      table[offset + i] = attacks;
+    }
+  }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Verification
+//
+////////////////////////////////////////////////////////////////////////////////
+
+/// Build the attack table the same way the move generator does, and check the
+/// lookups against the ray-based attacks for every subset of blockers.
+fn verify(slider: Slider, magics: &Magics) -> anyhow::Result<()> {
+  let mut table = vec![Bitboard::EMPTY; magics.size];
+
+  for (square, entry) in Square::ALL.into_iter().zip(&magics.entries) {
+    for blockers in entry.mask().subsets() {
+      table[entry.index(blockers)] = slider.attacks(square, blockers);
+    }
+  }
+
+  for (square, entry) in Square::ALL.into_iter().zip(&magics.entries) {
+    for blockers in entry.mask().subsets() {
+      if table[entry.index(blockers)] != slider.attacks(square, blockers) {
+        return Err(anyhow!(
+          "{slider:?} magic for {square} fails for blockers {:#x}",
+          blockers.0
+        ));
+      }
+    }
+  }
+
+  Ok(())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Utilities
+//
+////////////////////////////////////////////////////////////////////////////////
+
+/// Print the entries in the same format as `movegen/magics.rs`
+fn print_entries(name: &str, entries: &[Entry]) {
+  let ty = match entries.first() {
+    Some(Entry::Black(_)) => "BlackMagicEntry",
+    _ => "MagicEntry",
+  };
+
+  println!("pub const {name}: [{ty}; Square::COUNT] = [");
+
+  for entry in entries {
+    let (mask, magic, shift, offset) = match entry {
+      Entry::Regular(e) => (e.mask, e.magic, e.shift, e.offset),
+      Entry::Black(e) => (e.mask, e.magic, e.shift, e.offset),
+    };
+
+    println!("  {ty} {{");
+    println!("    mask: Bitboard({}),", mask.0);
+    println!("    magic: {magic},");
+    println!("    shift: {shift},");
+    println!("    offset: {offset},");
+    println!("  }},");
+  }
+
+  println!("];");
+}
+
+/// Quick and dirty RNG (SplitMix64)
+struct Rng {
+  state: u64,
+}
+
+impl Rng {
+  fn new(seed: u64) -> Self {
+    Self { state: seed }
+  }
+
+  fn next_u64(&mut self) -> u64 {
+    self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = self.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Generate a set of magics from a fixed seed, and check it
+  fn gen_and_verify(slider: Slider, black: bool, tries: usize) {
+    let config = Config {
+      black,
+      tries,
+      seed: 0x5eed,
+    };
+
+    let magics = gen_magics(slider, &config, &mut Rng::new(config.seed));
+    assert_eq!(magics.entries.len(), Square::COUNT);
+    verify(slider, &magics).unwrap();
+  }
+
+  #[test]
+  fn regular_magics() {
+    gen_and_verify(Slider::Bishop, false, 1);
+    gen_and_verify(Slider::Rook, false, 1);
+  }
+
+  #[test]
+  fn black_magics() {
+    // Goes through `find_offset` and `place`, which let the tables overlap
+    gen_and_verify(Slider::Bishop, true, 4);
+  }
+}
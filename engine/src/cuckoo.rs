@@ -0,0 +1,195 @@
+//! Cuckoo tables for detecting upcoming repetitions
+//!
+//! `Position::is_repetition` only tells us about repetitions that have already
+//! happened on the board. Often, though, the side to move could repeat a
+//! previous position with a single move, meaning that the position is worth
+//! _at least_ a draw to them.
+//!
+//! Marcel van Kervinck came up with a neat way to detect these cheaply: the
+//! difference between the Zobrist hashes of the current position and an
+//! earlier position, where only one of our pieces has moved, is exactly the
+//! hash of a reversible move (the piece keys for both squares, and the side
+//! key). There are only 3668 such moves for all non-pawn pieces on an empty
+//! board, so we store them all in a cuckoo hash table, indexed by their hash.
+//! Checking whether two positions are a single reversible move apart then
+//! comes down to (at most) two table lookups.
+//!
+//! The tables are built at compile time, from the same Zobrist keys the
+//! board uses.
+
+use crate::zobrist::ZHash;
+use crate::zobrist::PIECE_KEYS;
+use crate::zobrist::SIDE_KEY;
+use chess::bitboard::Bitboard;
+use chess::movegen::lookups::gen_bishop_attacks;
+use chess::movegen::lookups::gen_rook_attacks;
+use chess::movegen::lookups::KING_ATTACKS;
+use chess::movegen::lookups::KNIGHT_ATTACKS;
+use chess::movegen::moves::Move;
+use chess::movegen::moves::MoveType;
+use chess::piece::Piece;
+use chess::square::Square;
+
+/// The number of entries in the cuckoo tables. Needs to be a power of two
+/// that's comfortably larger than the number of moves we're storing.
+const CUCKOO_SIZE: usize = 8192;
+
+/// The number of reversible moves for all non-pawn pieces on an empty board
+const REVERSIBLE_MOVES: usize = 3668;
+
+/// The cuckoo tables that map the hash of a move to the move itself
+struct CuckooTable {
+  /// The hash of the move, or `ZHash::NULL` if the slot is empty
+  keys: [ZHash; CUCKOO_SIZE],
+
+  /// The move corresponding to each of the keys
+  moves: [Move; CUCKOO_SIZE],
+}
+
+static CUCKOO: CuckooTable = CuckooTable::new();
+
+/// Look up the reversible move that takes a position to another position,
+/// given the difference between their Zobrist hashes.
+///
+/// Note that we only store one direction for every move, so the source and
+/// target of the returned move may need to be swapped.
+pub fn lookup(diff: ZHash) -> Option<Move> {
+  let idx = h1(diff.0);
+
+  if CUCKOO.keys[idx] == diff {
+    return Some(CUCKOO.moves[idx]);
+  }
+
+  let idx = h2(diff.0);
+
+  if CUCKOO.keys[idx] == diff {
+    return Some(CUCKOO.moves[idx]);
+  }
+
+  None
+}
+
+/// The first hash function used to index the cuckoo tables
+const fn h1(key: u64) -> usize {
+  (key & 0x1fff) as usize
+}
+
+/// The second hash function used to index the cuckoo tables
+const fn h2(key: u64) -> usize {
+  ((key >> 16) & 0x1fff) as usize
+}
+
+impl CuckooTable {
+  /// Build the cuckoo tables by inserting every reversible move.
+  const fn new() -> Self {
+    let mut keys = [ZHash::NULL; CUCKOO_SIZE];
+    let mut moves = [Move::NULL; CUCKOO_SIZE];
+    let mut count = 0;
+
+    let mut piece = 0;
+    while piece < Piece::COUNT {
+      let mut sq1 = 0;
+      while sq1 < Square::COUNT {
+        let attacks = empty_board_attacks(Piece::ALL[piece], Square::ALL[sq1]);
+
+        // Only store every move in one direction, since the hash for the
+        // move back is the same.
+        let mut sq2 = sq1 + 1;
+        while sq2 < Square::COUNT {
+          if attacks.0 & (1 << sq2) != 0 {
+            let src = Square::ALL[sq1];
+            let tgt = Square::ALL[sq2];
+            let mut mv = Move::new(src, tgt, MoveType::Quiet);
+            let mut key = PIECE_KEYS[piece][sq1] ^ PIECE_KEYS[piece][sq2];
+            key ^= SIDE_KEY;
+
+            // Insert the move in its first slot. If that kicks out another
+            // move, move that one to its alternative slot, and so on, until
+            // we land on an empty slot.
+            let mut idx = h1(key);
+
+            loop {
+              let evicted_key = keys[idx].0;
+              let evicted_move = moves[idx];
+              keys[idx] = ZHash(key);
+              moves[idx] = mv;
+
+              if evicted_key == 0 {
+                break;
+              }
+
+              key = evicted_key;
+              mv = evicted_move;
+              idx = if idx == h1(key) { h2(key) } else { h1(key) };
+            }
+
+            count += 1;
+          }
+
+          sq2 += 1;
+        }
+
+        sq1 += 1;
+      }
+
+      piece += 1;
+    }
+
+    assert!(count == REVERSIBLE_MOVES);
+
+    Self { keys, moves }
+  }
+}
+
+/// The squares a piece attacks on an empty board. Pawn moves are never
+/// reversible, so pawns don't attack anything, as far as we're concerned.
+const fn empty_board_attacks(piece: Piece, square: Square) -> Bitboard {
+  use Piece::*;
+
+  match piece {
+    WP | BP => Bitboard::EMPTY,
+    WN | BN => KNIGHT_ATTACKS[square as usize],
+    WB | BB => gen_bishop_attacks(square, Bitboard::EMPTY),
+    WR | BR => gen_rook_attacks(square, Bitboard::EMPTY),
+    WQ | BQ => {
+      let diagonals = gen_bishop_attacks(square, Bitboard::EMPTY);
+      let orthogonals = gen_rook_attacks(square, Bitboard::EMPTY);
+      Bitboard(diagonals.0 | orthogonals.0)
+    }
+    WK | BK => KING_ATTACKS[square as usize],
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chess::board::Board;
+  use chess::square::Square::*;
+
+  #[test]
+  fn every_move_can_be_found() {
+    let stored = CUCKOO.keys.iter().filter(|&&key| key != ZHash::NULL);
+    assert_eq!(stored.count(), REVERSIBLE_MOVES);
+
+    for (&key, &mv) in CUCKOO.keys.iter().zip(CUCKOO.moves.iter()) {
+      if key != ZHash::NULL {
+        assert_eq!(lookup(key), Some(mv));
+      }
+    }
+  }
+
+  #[test]
+  fn lookup_knight_move() {
+    let board: Board = "4k3/8/8/8/8/8/8/4K1N1 w - - 0 1".parse().unwrap();
+    let mv = Move::new(G1, F3, MoveType::Quiet);
+    let next = board.play_move(mv);
+
+    let diff = ZHash(board.hash().0 ^ next.hash().0);
+    assert_eq!(lookup(diff), Some(mv));
+
+    // Pawn pushes aren't reversible, so they aren't stored
+    let board: Board = "4k3/8/8/8/8/8/6P1/4K3 w - - 0 1".parse().unwrap();
+    let next = board.play_move(Move::new(G2, G3, MoveType::Quiet));
+    assert_eq!(lookup(ZHash(board.hash().0 ^ next.hash().0)), None);
+  }
+}
@@ -0,0 +1,560 @@
+//! A high-level interface for embedding the engine in other applications.
+//!
+//! Searching a position involves quite a bit of machinery: a transposition
+//! table that's shared between threads, a node counter, a number of search
+//! runners that each run on their own thread, a time controller to tell them
+//! when to stop, etc. The `Engine` type wires all of that up, and exposes
+//! roughly the same operations a UCI GUI would use:
+//!
+//! ```ignore
+//! let mut engine = Engine::new();
+//! engine.set_option("Threads", "4")?;
+//! engine.set_position(Position::new(Board::default()));
+//!
+//! let search = engine.go(TimeControl::Depth(12));
+//! let report = search.wait();
+//! println!("bestmove {}", report.pv[0]);
+//! ```
+//!
+//! The search runs on a dedicated thread that owns the transposition table
+//! and the search runners, so `go` returns immediately. Progress can be
+//! followed by installing a `SearchObserver` through `set_observer`.
+
+use crate::evaluate::Score;
+use crate::position::Position;
+use crate::search::contempt::Contempt;
+use crate::search::observer::SearchObserver;
+use crate::search::observer::SilentObserver;
+use crate::search::params::DEFAULT_TT_SIZE;
+use crate::search::params::MAX_TT_SIZE;
+use crate::search::strength::Handicap;
+use crate::search::strength::Strength;
+use crate::search::SearchReport;
+use crate::thread_pool::ThreadPool;
+use crate::time_control::TimeControlHandle;
+use crate::time_control::TimeController;
+use crate::transpositions::TTable;
+use anyhow::anyhow;
+use chess::board::Board;
+use chess::movegen::sliders;
+use chess::movegen::sliders::Sliders;
+use chess::movegen::sliders::WithSliders;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU32;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use uci::opponent::Opponent;
+use uci::time_control::TimeControl;
+
+/// The constraints placed on a search
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Limits {
+  /// The time control to search under
+  pub time_control: TimeControl,
+
+  /// An additional cap on the search depth, on top of the time control
+  pub depth: Option<usize>,
+}
+
+impl Default for Limits {
+  fn default() -> Self {
+    Self {
+      time_control: TimeControl::Infinite,
+      depth: None,
+    }
+  }
+}
+
+impl From<TimeControl> for Limits {
+  fn from(time_control: TimeControl) -> Self {
+    Self {
+      time_control,
+      depth: None,
+    }
+  }
+}
+
+/// A chess engine, ready to search positions
+///
+/// Searches are queued up on the engine's search thread, so starting a new
+/// search while another one is still running will only start the new search
+/// once the previous one has finished (or has been stopped).
+pub struct Engine {
+  /// The position to search
+  position: Position,
+
+  /// The contempt settings, as set through the various options
+  contempt: Contempt,
+
+  /// The strength settings, as set through the various options
+  strength: Strength,
+
+  /// The most recently started search
+  current: Option<SearchHandle>,
+
+  /// The channel to the search thread
+  tx: Sender<PoolCommand>,
+}
+
+impl Engine {
+  /// Create a new engine, and spin up its search thread.
+  ///
+  /// The engine doesn't report on the search progress until an observer is
+  /// installed with `set_observer`.
+  pub fn new() -> Self {
+    let (tx, rx) = std::sync::mpsc::channel::<PoolCommand>();
+
+    std::thread::spawn(move || sliders::dispatch(SearchThread { rx }));
+
+    Self {
+      position: Position::new(Board::default()),
+      contempt: Contempt::default(),
+      strength: Strength::default(),
+      current: None,
+      tx,
+    }
+  }
+
+  /// Set the position to search. Build the position from the game's history
+  /// (e.g., with `Position::from_game`) rather than from the final board, so
+  /// the search can detect repetitions.
+  pub fn set_position(&mut self, position: Position) {
+    self.position = position;
+  }
+
+  /// The position that will be searched
+  pub fn position(&self) -> &Position {
+    &self.position
+  }
+
+  /// Set one of the engine's options, using the same names and values as the
+  /// corresponding UCI options.
+  pub fn set_option(&mut self, name: &str, value: &str) -> anyhow::Result<()> {
+    match name {
+      "Hash" => {
+        let size = value.parse()?;
+
+        if !(1..=MAX_TT_SIZE).contains(&size) {
+          return Err(anyhow!(
+            "Hash size should be between 1 and {MAX_TT_SIZE}"
+          ));
+        }
+
+        self.send(PoolCommand::ResizeTT(size))
+      }
+      "Clear Hash" => self.send(PoolCommand::ClearHash),
+      "Threads" => {
+        let threads = value.parse()?;
+
+        if threads == 0 {
+          return Err(anyhow!("Need at least one search thread"));
+        }
+
+        self.send(PoolCommand::SetThreads(threads))
+      }
+      "Contempt" => self.contempt.base = value.parse()?,
+      "Dynamic Contempt" => self.contempt.dynamic = value.parse()?,
+      "UCI_AnalyseMode" => self.contempt.analysis = value.parse()?,
+      "UCI_Opponent" => {
+        self.contempt.opponent_elo = value.parse::<Opponent>()?.elo;
+      }
+      "UCI_LimitStrength" => self.strength.limit_strength = value.parse()?,
+      "UCI_Elo" => self.strength.elo = value.parse()?,
+      "Skill Level" => self.strength.skill_level = value.parse()?,
+      "Skill Seed" => self.strength.seed = value.parse()?,
+
+      // Treat any other options as search params for SPSA purposes.
+      #[cfg(feature = "spsa")]
+      _ => crate::search::params::set_param(name, value.parse()?),
+
+      #[cfg(not(feature = "spsa"))]
+      _ => return Err(anyhow!("Unknown option: {name}")),
+    }
+
+    // Our own rating feeds into the (dynamic) contempt.
+    self.contempt.engine_elo = self.strength.elo();
+
+    Ok(())
+  }
+
+  /// Install an observer that gets notified of the progress of all
+  /// subsequent searches.
+  pub fn set_observer(&self, observer: impl SearchObserver + 'static) {
+    self.send(PoolCommand::SetObserver(Box::new(observer)));
+  }
+
+  /// Start searching the current position within the provided limits, and
+  /// return a handle to the search.
+  pub fn go(&mut self, limits: impl Into<Limits>) -> SearchHandle {
+    let limits = limits.into();
+    let (mut tc, tc_handle) =
+      TimeController::new(limits.time_control, self.position.board.current);
+
+    if let Some(depth) = limits.depth {
+      tc.limit_depth(depth);
+    }
+
+    let handle = SearchHandle {
+      tc_handle,
+      result: Arc::new(SearchResult::default()),
+    };
+
+    self.send(PoolCommand::Search {
+      position: self.position.clone(),
+      tc,
+      contempt: self.contempt.value(),
+      handicap: self.strength.handicap(),
+      result: handle.result.clone(),
+    });
+
+    self.current = Some(handle.clone());
+    handle
+  }
+
+  /// Stop the most recently started search
+  pub fn stop(&self) {
+    if let Some(search) = &self.current {
+      search.stop();
+    }
+  }
+
+  /// Wait for the most recently started search to finish, and return its
+  /// report, if any search was started.
+  pub fn wait(&self) -> Option<SearchReport> {
+    self.current.as_ref().map(SearchHandle::wait)
+  }
+
+  /// Forget everything about previous games: reset the position, and clear
+  /// the transposition table and history tables. A search that's still
+  /// running is stopped first.
+  pub fn new_game(&mut self) {
+    self.stop();
+    self.wait();
+    self.position = Position::new(Board::default());
+    self.current = None;
+    self.send(PoolCommand::Clear);
+  }
+
+  /// Save the transposition table to a file, so a later session can pick up
+  /// where this one left off. Fails while a search is running.
+  pub fn save_hash(&self, path: impl Into<PathBuf>) -> anyhow::Result<()> {
+    self.request(|reply| PoolCommand::SaveHash(path.into(), reply))
+  }
+
+  /// Replace the transposition table with one saved by `save_hash`. The
+  /// table takes on the size of the saved table. Fails while a search is
+  /// running.
+  ///
+  /// If the file is found to be corrupt only after some of it was read, the
+  /// current table is cleared, rather than left half-loaded.
+  pub fn load_hash(&self, path: impl Into<PathBuf>) -> anyhow::Result<()> {
+    self.request(|reply| PoolCommand::LoadHash(path.into(), reply))
+  }
+
+  /// Merge a table saved by `save_hash` into the current transposition table.
+  /// Both tables need to be the same size. Fails while a search is running.
+  pub fn merge_hash(&self, path: impl Into<PathBuf>) -> anyhow::Result<()> {
+    self.request(|reply| PoolCommand::MergeHash(path.into(), reply))
+  }
+
+  /// Whether the most recently started search (and hence any search queued
+  /// before it) is still running
+  pub fn is_searching(&self) -> bool {
+    self
+      .current
+      .as_ref()
+      .is_some_and(|search| search.try_report().is_none())
+  }
+
+  /// Send a command to the search thread, and wait for its reply.
+  ///
+  /// The search thread only gets to the command once the running search is
+  /// done, which may be never for an infinite search. Rather than blocking
+  /// until someone stops the search (which they can't, while we're blocked),
+  /// refuse the command.
+  fn request(
+    &self,
+    command: impl FnOnce(Sender<anyhow::Result<()>>) -> PoolCommand,
+  ) -> anyhow::Result<()> {
+    if self.is_searching() {
+      return Err(anyhow!("A search is running, stop it first"));
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    self.send(command(tx));
+    rx.recv()?
+  }
+
+  fn send(&self, command: PoolCommand) {
+    // The search thread only stops when the engine is dropped
+    self.tx.send(command).expect("Search thread hung up");
+  }
+}
+
+impl Default for Engine {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Drop for Engine {
+  /// Don't leave a search running in the background
+  fn drop(&mut self) {
+    self.stop();
+  }
+}
+
+/// A handle to a running (or finished) search
+#[derive(Clone)]
+pub struct SearchHandle {
+  tc_handle: TimeControlHandle,
+  result: Arc<SearchResult>,
+}
+
+impl SearchHandle {
+  /// Abort the search. The search still reports the best move found so far.
+  pub fn stop(&self) {
+    self.tc_handle.stop();
+  }
+
+  /// Block until the search has finished, and return its report
+  pub fn wait(&self) -> SearchReport {
+    let mut report = self.result.report.lock().unwrap();
+
+    while report.is_none() {
+      report = self.result.done.wait(report).unwrap();
+    }
+
+    report.clone().unwrap()
+  }
+
+  /// Return the report, if the search has already finished
+  pub fn try_report(&self) -> Option<SearchReport> {
+    self.result.report.lock().unwrap().clone()
+  }
+}
+
+/// The slot the search thread leaves its report in
+#[derive(Default)]
+struct SearchResult {
+  report: Mutex<Option<SearchReport>>,
+  done: Condvar,
+}
+
+impl SearchResult {
+  fn finish(&self, report: SearchReport) {
+    *self.report.lock().unwrap() = Some(report);
+    self.done.notify_all();
+  }
+}
+
+/// Commands sent from the `Engine` to its search thread
+enum PoolCommand {
+  Search {
+    position: Position,
+    tc: TimeController,
+    contempt: Score,
+    handicap: Option<Handicap>,
+    result: Arc<SearchResult>,
+  },
+  Clear,
+  ClearHash,
+  ResizeTT(usize),
+  SetThreads(usize),
+  SetObserver(Box<dyn SearchObserver>),
+  SaveHash(PathBuf, Sender<anyhow::Result<()>>),
+  LoadHash(PathBuf, Sender<anyhow::Result<()>>),
+  MergeHash(PathBuf, Sender<anyhow::Result<()>>),
+}
+
+/// The thread that owns the transposition table and the search threads, and
+/// carries out the commands sent by the engine.
+///
+/// The slider backend is picked once, when the thread starts, so everything
+/// the search does is compiled for the backend it ends up using.
+struct SearchThread {
+  rx: Receiver<PoolCommand>,
+}
+
+impl WithSliders for SearchThread {
+  type Output = ();
+
+  fn run<S: Sliders>(self) {
+    let rx = self.rx;
+    let mut num_threads = 1;
+    let mut tt = TTable::with_capacity(DEFAULT_TT_SIZE);
+    let global_nodes = AtomicU32::new(0);
+    let mut observer: Box<dyn SearchObserver> = Box::new(SilentObserver);
+
+    // The search threads borrow the transposition table, so they're kept
+    // alive for as long as the table and thread count stay the same. When
+    // either changes, the pool is torn down, and rebuilt after applying the
+    // change.
+    loop {
+      let command = std::thread::scope(|s| {
+        let observer = &mut observer;
+        let mut pool = ThreadPool::new(
+          s,
+          num_threads,
+          &tt,
+          &global_nodes,
+          std::mem::replace(observer, Box::new(SilentObserver)),
+        );
+
+        for msg in rx.iter() {
+          match msg {
+            PoolCommand::Search {
+              position,
+              tc,
+              contempt,
+              handicap,
+              result,
+            } => {
+              tt.increment_age();
+              let position = position.with_sliders::<S>();
+              result.finish(pool.search(position, tc, contempt, handicap));
+            }
+
+            PoolCommand::SetObserver(new) => pool.set_observer(new),
+
+            PoolCommand::ClearHash => tt.clear(num_threads),
+
+            PoolCommand::SaveHash(path, reply) => {
+              let _ = reply.send(tt.save(path));
+            }
+
+            PoolCommand::MergeHash(path, reply) => {
+              let _ = reply.send(tt.merge(path));
+            }
+
+            command => {
+              *observer = pool.take_observer();
+              return Some(command);
+            }
+          }
+        }
+
+        // The engine was dropped
+        None
+      });
+
+      match command {
+        Some(PoolCommand::Clear) => tt.clear(num_threads),
+
+        Some(PoolCommand::ResizeTT(size)) => tt.resize(size, num_threads),
+
+        Some(PoolCommand::SetThreads(n)) => num_threads = n,
+
+        Some(PoolCommand::LoadHash(path, reply)) => {
+          let _ = reply.send(tt.load(path, num_threads));
+        }
+
+        _ => break,
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn search_to_depth() {
+    let mut engine = Engine::new();
+    engine.set_option("Hash", "4").unwrap();
+
+    let search = engine.go(TimeControl::Depth(5));
+    let report = search.wait();
+
+    assert_eq!(report.depth, 5);
+    assert!(!report.pv.is_empty());
+    assert_eq!(engine.wait().unwrap().pv, report.pv);
+  }
+
+  #[test]
+  fn stop_infinite_search() {
+    let mut engine = Engine::new();
+    engine.set_option("Hash", "4").unwrap();
+    engine.set_option("Threads", "2").unwrap();
+
+    let search = engine.go(TimeControl::Infinite);
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    engine.stop();
+
+    assert!(!search.wait().pv.is_empty());
+  }
+
+  #[test]
+  fn save_and_load_hash() {
+    let path = std::env::temp_dir()
+      .join(format!("simbelmyne-engine-{}.tt", std::process::id()));
+
+    let mut engine = Engine::new();
+    engine.set_option("Hash", "4").unwrap();
+    engine.go(TimeControl::Depth(6)).wait();
+    engine.save_hash(&path).unwrap();
+
+    let other = Engine::new();
+    other.load_hash(&path).unwrap();
+    assert!(other.merge_hash(&path).is_ok());
+    assert!(other.load_hash(path.with_extension("missing")).is_err());
+
+    std::fs::remove_file(path).unwrap();
+  }
+
+  #[test]
+  fn save_hash_during_infinite_search() {
+    let path = std::env::temp_dir()
+      .join(format!("simbelmyne-infinite-{}.tt", std::process::id()));
+
+    let mut engine = Engine::new();
+    engine.set_option("Hash", "4").unwrap();
+
+    // Saving while the search is running would never finish, so it's refused
+    let search = engine.go(TimeControl::Infinite);
+    assert!(engine.is_searching());
+    assert!(engine.save_hash(&path).is_err());
+    assert!(engine.load_hash(&path).is_err());
+    assert!(engine.merge_hash(&path).is_err());
+
+    engine.stop();
+    search.wait();
+
+    assert!(!engine.is_searching());
+    engine.save_hash(&path).unwrap();
+
+    std::fs::remove_file(path).unwrap();
+  }
+
+  #[test]
+  fn new_game_stops_the_search() {
+    let path = std::env::temp_dir()
+      .join(format!("simbelmyne-new-game-{}.tt", std::process::id()));
+
+    let mut engine = Engine::new();
+    engine.set_option("Hash", "4").unwrap();
+
+    engine.go(TimeControl::Infinite);
+    engine.new_game();
+
+    assert!(!engine.is_searching());
+    engine.save_hash(&path).unwrap();
+
+    std::fs::remove_file(path).unwrap();
+  }
+
+  #[test]
+  fn invalid_options() {
+    let mut engine = Engine::new();
+
+    assert!(engine.set_option("Threads", "0").is_err());
+    assert!(engine.set_option("Hash", "0").is_err());
+    assert!(engine.set_option("Clear Hash", "").is_ok());
+    assert!(engine.set_option("Contempt", "lots").is_err());
+    assert!(engine.set_option("UCI_Elo", "2000").is_ok());
+  }
+}
@@ -1,5 +1,6 @@
 use chess::bitboard::Bitboard;
 use chess::board::Board;
+use chess::movegen::sliders::Sliders;
 use chess::piece::Color;
 use chess::piece::Color::*;
 
@@ -43,7 +44,10 @@ impl Default for KingPawnStructure {
 }
 
 impl KingPawnStructure {
-  pub fn new(board: &Board, mut trace: &mut impl Tracer<EvalTrace>) -> Self {
+  pub fn new(
+    board: &Board<impl Sliders>,
+    mut trace: &mut impl Tracer<EvalTrace>,
+  ) -> Self {
     // Pawn bitboardds
     let white_pawns = board.pawns(White);
     let black_pawns = board.pawns(Black);
@@ -129,7 +133,7 @@ impl KingPawnStructure {
 
   pub fn compute_score<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
     let mut total = S::default();
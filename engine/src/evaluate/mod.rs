@@ -35,7 +35,6 @@ pub mod tuner;
 pub mod util;
 
 use crate::history_tables::history::HistoryIndex;
-use crate::s;
 use crate::zobrist::ZHash;
 
 use self::kp_structure::KingPawnStructure;
@@ -45,6 +44,7 @@ use chess::constants::DARK_SQUARES;
 use chess::movegen::castling::CastleType;
 use chess::movegen::lookups::KING_ATTACKS;
 use chess::movegen::moves::Move;
+use chess::movegen::sliders::Sliders;
 use chess::piece::Color;
 use chess::piece::Piece;
 use chess::piece::PieceType;
@@ -137,17 +137,13 @@ pub struct Eval {
 }
 
 impl Eval {
-  /// A static score that is returned as a draw score.
-  /// A positive contempt would make the engine more likely to draw, a
-  /// negative contempt makes it less likely to settle for a draw.
-  ///
-  /// We don't draw. We go for the kill.
-  const CONTEMPT: S = s!(-50, -10);
-
   /// Create a new score for a board
   /// TODO: Make this more efficient? By running over every single term
   /// exactly once. Then we could re-use this to trace, right?
-  pub fn new(board: &Board, trace: &mut impl Tracer<EvalTrace>) -> Self {
+  pub fn new(
+    board: &Board<impl Sliders>,
+    trace: &mut impl Tracer<EvalTrace>,
+  ) -> Self {
     let mut eval = Self::default();
 
     for (sq_idx, piece) in board.piece_list.into_iter().enumerate() {
@@ -190,7 +186,7 @@ impl Eval {
   /// incremental evaluation terms and the volatile terms.
   pub fn total(
     &mut self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> Score {
     // We pass around an EvalContext so expensive information gathered in
@@ -256,7 +252,7 @@ impl Eval {
   pub fn play_move(
     &self,
     idx: HistoryIndex,
-    board: &Board,
+    board: &Board<impl Sliders>,
     kp_hash: ZHash,
     kp_cache: &mut KingPawnCache,
   ) -> Self {
@@ -315,7 +311,7 @@ impl Eval {
     &mut self,
     piece: Piece,
     sq: Square,
-    board: &Board,
+    board: &Board<impl Sliders>,
     kp_hash: ZHash,
     kp_cache: &mut KingPawnCache,
   ) {
@@ -330,7 +326,7 @@ impl Eval {
     &mut self,
     piece: Piece,
     sq: Square,
-    board: &Board,
+    board: &Board<impl Sliders>,
     kp_hash: ZHash,
     kp_cache: &mut KingPawnCache,
   ) {
@@ -349,7 +345,7 @@ impl Eval {
     piece: Piece,
     from: Square,
     to: Square,
-    board: &Board,
+    board: &Board<impl Sliders>,
     kp_hash: ZHash,
     kp_cache: &mut KingPawnCache,
   ) {
@@ -366,7 +362,7 @@ impl Eval {
   fn update_incremental_terms(
     &mut self,
     piece: Piece,
-    board: &Board,
+    board: &Board<impl Sliders>,
     kp_hash: ZHash,
     kp_cache: &mut KingPawnCache,
   ) {
@@ -508,18 +504,23 @@ impl Eval {
     Self::GAME_PHASE_VALUES[piece.piece_type()]
   }
 
-  /// Return the draw score, taking into account the global contempt factor
-  pub fn draw_score(self, ply: usize, nodes: u32) -> Score {
+  /// Return the draw score, taking into account the contempt factor.
+  ///
+  /// A positive contempt makes the engine less likely to settle for a draw,
+  /// a negative contempt makes it more likely to draw. The contempt is
+  /// tapered, so we're less averse to draws in the endgame.
+  pub fn draw_score(self, contempt: Score, ply: usize, nodes: u32) -> Score {
     let random = nodes as Score & 0b11 - 2;
+    let draw_score = S::new(-contempt, -contempt / 5).lerp(self.game_phase);
 
     // Make sure to make the returned contempt relative to the side-to-move
     // at root.
     //
     // We add a small random contribution to help with repetitions
     if ply % 2 == 0 {
-      Self::CONTEMPT.lerp(self.game_phase) + random
+      draw_score + random
     } else {
-      -(Self::CONTEMPT.lerp(self.game_phase) + random)
+      -(draw_score + random)
     }
   }
 }
@@ -546,7 +547,7 @@ pub struct EvalContext {
 
 impl EvalContext {
   /// Create a new EvalContext
-  pub fn new(board: &Board) -> Self {
+  pub fn new(board: &Board<impl Sliders>) -> Self {
     let white_king = board.kings(Color::White).first();
     let black_king = board.kings(Color::Black).first();
 
@@ -569,7 +570,7 @@ impl EvalContext {
 ////////////////////////////////////////////////////////////////////////////////
 
 // Taken from Weiss for now, will expand upon this at some point...
-pub fn endgame_scaling(board: &Board, eg_score: i32) -> i32 {
+pub fn endgame_scaling(board: &Board<impl Sliders>, eg_score: i32) -> i32 {
   use Color::*;
   use PieceType::*;
 
@@ -11,6 +11,7 @@ use chess::constants::DARK_SQUARES;
 use chess::constants::LIGHT_SQUARES;
 use chess::constants::RANKS;
 use chess::movegen::lookups::BETWEEN;
+use chess::movegen::sliders::Sliders;
 use chess::piece::Color::*;
 use chess::piece::Piece;
 use chess::piece::PieceType;
@@ -107,7 +108,7 @@ impl Eval {
   /// For the implementation of outpost squares, see [PawnStructure::new].
   pub fn knight_outposts<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
     let us = if WHITE { White } else { Black };
@@ -127,7 +128,7 @@ impl Eval {
   /// For the implementation of outpost squares, see [PawnStructure::new].
   pub fn bishop_outposts<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
     let us = if WHITE { White } else { Black };
@@ -146,7 +147,7 @@ impl Eval {
   /// two same-color bishops through a promotion)
   pub fn bishop_pair<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
     let us = if WHITE { White } else { Black };
@@ -165,7 +166,7 @@ impl Eval {
   /// For the implementation of open files, see [PawnStructure].
   pub fn rook_open_file<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
     let us = if WHITE { White } else { Black };
@@ -186,7 +187,7 @@ impl Eval {
   /// For the implementation of semi-open files, see [PawnStructure].
   pub fn rook_semiopen_file<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
     let us = if WHITE { White } else { Black };
@@ -204,7 +205,7 @@ impl Eval {
   /// of each other and are protecting one another.
   pub fn connected_rooks<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
     let us = if WHITE { White } else { Black };
@@ -237,7 +238,7 @@ impl Eval {
   /// or there are powns on the 7th.
   pub fn major_on_seventh<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
     let us = if WHITE { White } else { Black };
@@ -260,7 +261,7 @@ impl Eval {
   /// Identical in spirit and implementation to [Board::rook_open_file]
   pub fn queen_open_file<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
     let us = if WHITE { White } else { Black };
@@ -277,7 +278,7 @@ impl Eval {
   /// Identical in spirit and implementation to [Board::rook_semiopen_file]
   pub fn queen_semiopen_file<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
     let us = if WHITE { White } else { Black };
@@ -309,7 +310,7 @@ impl Eval {
   /// of the other calculated stuff (threats, king zone) would be invalid?
   pub fn mobility<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     ctx: &mut EvalContext,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
@@ -365,7 +366,7 @@ impl Eval {
     }
 
     for sq in board.bishops(us) {
-      let attacks = sq.bishop_squares(blockers);
+      let attacks = board.bishop_squares(sq, blockers);
 
       ctx.threats[us] |= attacks;
       ctx.attacked_by[us][Bishop] |= attacks;
@@ -393,7 +394,7 @@ impl Eval {
     }
 
     for sq in board.rooks(us) {
-      let attacks = sq.rook_squares(blockers);
+      let attacks = board.rook_squares(sq, blockers);
 
       ctx.threats[us] |= attacks;
       ctx.attacked_by[us][Rook] |= attacks;
@@ -416,7 +417,7 @@ impl Eval {
     }
 
     for sq in board.queens(us) {
-      let attacks = sq.queen_squares(blockers);
+      let attacks = board.queen_squares(sq, blockers);
 
       ctx.threats[us] |= attacks;
       ctx.attacked_by[us][Queen] |= attacks;
@@ -454,7 +455,7 @@ impl Eval {
   /// having many slider attack vectors.
   pub fn virtual_mobility<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
     let us = if WHITE { White } else { Black };
@@ -462,7 +463,7 @@ impl Eval {
     let king_sq = board.kings(us).first();
     let blockers = board.all_occupied();
     let ours = board.occupied_by(us);
-    let available_squares = king_sq.queen_squares(blockers) & !ours;
+    let available_squares = board.queen_squares(king_sq, blockers) & !ours;
     let mobility = available_squares.count() as usize;
 
     trace.add(|t| t.virtual_mobility[mobility] += perspective);
@@ -498,7 +499,7 @@ impl Eval {
   /// [Board::mobility].
   pub fn threats<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     ctx: &EvalContext,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
@@ -552,7 +553,7 @@ impl Eval {
   /// safe and unsafe)
   pub fn checks<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     ctx: &EvalContext,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
@@ -579,17 +580,17 @@ impl Eval {
     unsafe_checks[Knight] = knight_checks & !safe;
 
     let bishop_checks =
-      ctx.attacked_by[us][Bishop] & their_king.bishop_squares(blockers);
+      ctx.attacked_by[us][Bishop] & board.bishop_squares(their_king, blockers);
     safe_checks[Bishop] = bishop_checks & safe;
     unsafe_checks[Bishop] = bishop_checks & !safe;
 
     let rook_checks =
-      ctx.attacked_by[us][Rook] & their_king.rook_squares(blockers);
+      ctx.attacked_by[us][Rook] & board.rook_squares(their_king, blockers);
     safe_checks[Rook] = rook_checks & safe;
     unsafe_checks[Rook] = rook_checks & !safe;
 
     let queen_checks =
-      ctx.attacked_by[us][Queen] & their_king.queen_squares(blockers);
+      ctx.attacked_by[us][Queen] & board.queen_squares(their_king, blockers);
     safe_checks[Queen] = queen_checks & safe;
     unsafe_checks[Queen] = queen_checks & !safe;
 
@@ -625,7 +626,7 @@ impl Eval {
   /// Bonus for a knight behind a pawn
   pub fn knight_shelter<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
     let us = if WHITE { White } else { Black };
@@ -640,7 +641,7 @@ impl Eval {
   /// Bonus for a bishop behind a pawn
   pub fn bishop_shelter<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
     let us = if WHITE { White } else { Black };
@@ -656,7 +657,7 @@ impl Eval {
   /// our pawns.
   pub fn bad_bishops<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
     let us = if WHITE { White } else { Black };
@@ -682,7 +683,7 @@ impl Eval {
   /// Passed pawn related evaluation that has to be recomputed on each move.
   pub fn volatile_passers<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     ctx: &EvalContext,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
@@ -740,7 +741,7 @@ impl Eval {
   /// non-pawn pieces and defended by us)
   pub fn push_threats<const WHITE: bool>(
     &self,
-    board: &Board,
+    board: &Board<impl Sliders>,
     ctx: &EvalContext,
     trace: &mut impl Tracer<EvalTrace>,
   ) -> S {
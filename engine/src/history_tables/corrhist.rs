@@ -25,6 +25,7 @@
 //! Those are clearly the ones that need more correction, because the eval got
 //! it _very_ wrong.
 
+use chess::movegen::sliders::Sliders;
 use chess::piece::Color;
 use std::ops::Index;
 use std::ops::IndexMut;
@@ -43,7 +44,11 @@ use super::History;
 pub const CORRHIST_SIZE: usize = 16384;
 
 impl History {
-  pub fn eval_correction(&self, pos: &Position, ply: usize) -> Score {
+  pub fn eval_correction(
+    &self,
+    pos: &Position<impl Sliders>,
+    ply: usize,
+  ) -> Score {
     use Color::*;
     let us = pos.board.current;
 
@@ -74,7 +79,7 @@ impl History {
 
   pub fn update_corrhist(
     &mut self,
-    pos: &Position,
+    pos: &Position<impl Sliders>,
     ply: usize,
     depth: usize,
     diff: Score,
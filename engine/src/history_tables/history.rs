@@ -21,6 +21,7 @@ use crate::search::params::hist_bonus_linear;
 use crate::search::params::hist_bonus_quadratic;
 use chess::board::Board;
 use chess::movegen::moves::Move;
+use chess::movegen::sliders::Sliders;
 use chess::piece::Piece;
 use chess::square::Square;
 use std::ops::Add;
@@ -82,7 +83,7 @@ pub struct HistoryIndex {
 }
 
 impl HistoryIndex {
-  pub fn new(board: &Board, mv: Move) -> Self {
+  pub fn new(board: &Board<impl Sliders>, mv: Move) -> Self {
     let captured = if mv.is_capture() {
       board.get_at(mv.get_capture_sq())
     } else {
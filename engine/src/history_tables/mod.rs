@@ -1,6 +1,7 @@
 use arrayvec::ArrayVec;
 use chess::board::Board;
 use chess::movegen::moves::Move;
+use chess::movegen::sliders::Sliders;
 use chess::piece::Color;
 use chess::piece::PieceType;
 use chess::square::Square;
@@ -61,7 +62,7 @@ impl History {
   }
 
   // History indices
-  pub fn push_mv(&mut self, mv: Move, board: &Board) {
+  pub fn push_mv(&mut self, mv: Move, board: &Board<impl Sliders>) {
     self.indices.push(HistoryIndex::new(board, mv));
   }
 
@@ -85,7 +86,7 @@ impl History {
   pub fn add_hist_bonus(
     &mut self,
     mv: Move,
-    pos: &Position,
+    pos: &Position<impl Sliders>,
     bonus: HistoryScore,
   ) {
     let board = &pos.board;
@@ -133,7 +134,7 @@ impl History {
     }
   }
 
-  pub fn get_hist_score(&self, mv: Move, pos: &Position) -> i32 {
+  pub fn get_hist_score(&self, mv: Move, pos: &Position<impl Sliders>) -> i32 {
     let board = &pos.board;
     let idx = HistoryIndex::new(board, mv);
 
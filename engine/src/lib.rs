@@ -1,9 +1,12 @@
+pub mod cuckoo;
+pub mod engine;
 pub mod evaluate;
 pub mod history_tables;
 pub mod move_picker;
 pub mod position;
 pub mod search;
 pub mod tests;
+pub mod thread_pool;
 pub mod time_control;
 pub mod transpositions;
 pub mod zobrist;
@@ -37,6 +37,7 @@ use chess::movegen::legal_moves::Quiets;
 use chess::movegen::legal_moves::Tacticals;
 use chess::movegen::legal_moves::MAX_MOVES;
 use chess::movegen::moves::Move;
+use chess::movegen::sliders::Sliders;
 use chess::piece::PieceType;
 
 /// The bonus score used to place killer moves ahead of the other quiet moves
@@ -59,7 +60,7 @@ pub enum Stage {
 
 /// A Move Picker is a lazy wrapper around a Vec of moves that sorts and yields
 /// moves as lazily as possible.
-pub struct MovePicker<'pos> {
+pub struct MovePicker {
   /// The current stage the move picker is in
   pub stage: Stage,
 
@@ -81,9 +82,6 @@ pub struct MovePicker<'pos> {
   /// The scores associated with every move, using the same indexing
   scores: [i32; MAX_MOVES],
 
-  /// The current board position
-  position: &'pos Position,
-
   /// Whether or not to skip quiet moves and bad tacticals
   /// Can be set dynamically after we've already started iterating the moves.
   pub only_good_tacticals: bool,
@@ -91,12 +89,11 @@ pub struct MovePicker<'pos> {
   ply: usize,
 }
 
-impl<'pos> MovePicker<'pos> {
+impl MovePicker {
   pub fn new<const ALL_MOVES: bool>(
-    position: &'pos Position,
     tt_move: Option<Move>,
     ply: usize,
-  ) -> MovePicker<'pos> {
+  ) -> MovePicker {
     let scores = [0; MAX_MOVES];
 
     // If we're only interested in tacticals, but the TT move is
@@ -107,7 +104,6 @@ impl<'pos> MovePicker<'pos> {
       stage: Stage::TTMove,
       quiet_index: 0,
       bad_tactical_index: 0,
-      position,
       scores,
       moves: MoveList::new(),
       tt_move,
@@ -182,11 +178,16 @@ impl<'pos> MovePicker<'pos> {
     return Some(best_move);
   }
 
-  fn is_good_tactical(&self, mv: Move, history: &History) -> bool {
+  fn is_good_tactical(
+    &self,
+    mv: Move,
+    position: &Position<impl Sliders>,
+    history: &History,
+  ) -> bool {
     use PieceType::*;
     if mv.is_capture() {
-      let hist_score = history.get_hist_score(mv, &self.position);
-      self.position.board.see(mv, -hist_score / 32)
+      let hist_score = history.get_hist_score(mv, position);
+      position.board.see(mv, -hist_score / 32)
     } else {
       mv.get_promo_type().is_some_and(|pt| pt == Queen)
     }
@@ -194,7 +195,11 @@ impl<'pos> MovePicker<'pos> {
 
   /// Score captures according to MVV-LVA (Most Valuable Victim, Least
   /// Valuable Attacker)
-  fn score_tacticals(&mut self, history: &History) {
+  fn score_tacticals(
+    &mut self,
+    position: &Position<impl Sliders>,
+    history: &History,
+  ) {
     let mut i = self.index;
 
     while i < self.moves.len() {
@@ -207,13 +212,13 @@ impl<'pos> MovePicker<'pos> {
       ////////////////////////////////////////////////////////////////////
 
       if mv.is_capture() {
-        let victim = self.position.board.get_at(mv.get_capture_sq()).unwrap();
+        let victim = position.board.get_at(mv.get_capture_sq()).unwrap();
 
         // MVV-LVA
         self.scores[i] += 32 * piece_vals(victim.piece_type());
 
         // Capthist
-        self.scores[i] += history.get_hist_score(mv, &self.position);
+        self.scores[i] += history.get_hist_score(mv, position);
       }
       ////////////////////////////////////////////////////////////////////
       //
@@ -225,7 +230,7 @@ impl<'pos> MovePicker<'pos> {
       //
       ////////////////////////////////////////////////////////////////////
       else if mv.is_promotion() {
-        self.scores[i] += history.get_hist_score(mv, &self.position);
+        self.scores[i] += history.get_hist_score(mv, position);
       }
 
       i += 1;
@@ -233,7 +238,11 @@ impl<'pos> MovePicker<'pos> {
   }
 
   /// Score quiet moves according to the killer move and history tables
-  fn score_quiets(&mut self, history: &History) {
+  fn score_quiets(
+    &mut self,
+    position: &Position<impl Sliders>,
+    history: &History,
+  ) {
     for i in self.quiet_index..self.moves.len() {
       let mv = self.moves[i];
 
@@ -249,13 +258,18 @@ impl<'pos> MovePicker<'pos> {
         self.scores[i] += COUNTERMOVE_BONUS;
       }
 
-      self.scores[i] += history.get_hist_score(mv, &self.position);
+      self.scores[i] += history.get_hist_score(mv, position);
     }
   }
 }
 
-impl<'a> MovePicker<'a> {
-  pub fn next(&mut self, history: &History) -> Option<Move> {
+impl MovePicker {
+  /// Return the next move to search in the position, if any
+  pub fn next(
+    &mut self,
+    position: &Position<impl Sliders>,
+    history: &History,
+  ) -> Option<Move> {
     const WHITE: bool = true;
     const BLACK: bool = false;
 
@@ -277,10 +291,7 @@ impl<'a> MovePicker<'a> {
     if self.stage == Stage::TTMove {
       self.stage = Stage::GenerateTacticals;
 
-      if self
-        .tt_move
-        .is_some_and(|mv| self.position.board.is_legal(mv))
-      {
+      if self.tt_move.is_some_and(|mv| position.board.is_legal(mv)) {
         return self.tt_move;
       }
     }
@@ -292,14 +303,12 @@ impl<'a> MovePicker<'a> {
     ////////////////////////////////////////////////////////////////////////
 
     if self.stage == Stage::GenerateTacticals {
-      if self.position.board.current.is_white() {
-        self
-          .position
+      if position.board.current.is_white() {
+        position
           .board
           .legal_moves_for::<WHITE, Tacticals>(&mut self.moves);
       } else {
-        self
-          .position
+        position
           .board
           .legal_moves_for::<BLACK, Tacticals>(&mut self.moves);
       }
@@ -329,7 +338,7 @@ impl<'a> MovePicker<'a> {
     ////////////////////////////////////////////////////////////////////////
 
     if self.stage == Stage::ScoreTacticals {
-      self.score_tacticals(history);
+      self.score_tacticals(position, history);
 
       self.stage = Stage::GoodTacticals;
     }
@@ -347,7 +356,7 @@ impl<'a> MovePicker<'a> {
       while self.index < self.bad_tactical_index {
         let tactical = self.partial_sort(self.index, self.bad_tactical_index);
 
-        if self.is_good_tactical(tactical.unwrap(), history) {
+        if self.is_good_tactical(tactical.unwrap(), position, history) {
           self.index += 1;
           return tactical;
         } else {
@@ -370,14 +379,12 @@ impl<'a> MovePicker<'a> {
     ////////////////////////////////////////////////////////////////////////
 
     if self.stage == Stage::GenerateQuiets {
-      if self.position.board.current.is_white() {
-        self
-          .position
+      if position.board.current.is_white() {
+        position
           .board
           .legal_moves_for::<WHITE, Quiets>(&mut self.moves);
       } else {
-        self
-          .position
+        position
           .board
           .legal_moves_for::<BLACK, Quiets>(&mut self.moves);
       }
@@ -406,7 +413,7 @@ impl<'a> MovePicker<'a> {
     ////////////////////////////////////////////////////////////////////////
 
     if self.stage == Stage::ScoreQuiets {
-      self.score_quiets(history);
+      self.score_quiets(position, history);
       self.stage = Stage::Quiets;
     }
 
@@ -499,11 +506,11 @@ mod tests {
     let position = Position::new(board);
     let history = History::boxed();
 
-    let mut picker = MovePicker::new::<true>(&position, None, 0);
+    let mut picker = MovePicker::new::<true>(None, 0);
 
     picker.only_good_tacticals = true;
 
-    while let Some(mv) = picker.next(&history) {
+    while let Some(mv) = picker.next(&position, &history) {
       println!("Yielded {mv}");
     }
   }
@@ -4,29 +4,39 @@
 //! additional game data, that the chess backend doesn't have any knowledge of.
 //! These are things such as evaluation, Zobrist hashing, and game history.
 
+use crate::cuckoo;
+use crate::search::params::MAX_DEPTH;
+use crate::zobrist::EngineKeys;
 use crate::zobrist::ZHash;
+use crate::zobrist::SIDE_KEY;
 use arrayvec::ArrayVec;
 use chess::board::Board;
+use chess::game::Game;
 use chess::movegen::castling::CastleType;
+use chess::movegen::lookups::BETWEEN;
 use chess::movegen::moves::BareMove;
 use chess::movegen::moves::Move;
+use chess::movegen::play_move::BoardUndo;
+use chess::movegen::sliders::Magics;
+use chess::movegen::sliders::Sliders;
 use chess::piece::Color;
 use chess::piece::Piece;
 use chess::piece::PieceType;
 use chess::square::Square;
+use std::ops::Deref;
+use std::ops::DerefMut;
 
-// We don't ever expect to exceed 100 entries, because that would be a draw.
-const HIST_SIZE: usize = 100;
+// We don't ever expect to exceed 100 entries since the last half-move counter
+// reset, because that would be a draw. When playing moves in-place, the
+// history isn't cleared on a reset, so leave room for a full search on top.
+const HIST_SIZE: usize = 100 + MAX_DEPTH;
 
 /// Wrapper around a `Board` that stores additional metadata that is not tied to
 /// the board itself, but rather to the search and evaluation algorithms.
 #[derive(Debug, Clone)]
-pub struct Position {
+pub struct Position<S: Sliders = Magics> {
   /// The board associated with the position.
-  pub board: Board,
-
-  /// The Zobrist hash of the current board
-  pub hash: ZHash,
+  pub board: Board<S>,
 
   /// The Zobrist hash of the current king-pawn structure
   /// Used for indexing the pawn cache.
@@ -50,17 +60,39 @@ pub struct Position {
 
   /// A history of Zobrist hashes going back to the last half-move counter
   /// reset.
+  ///
+  /// When playing moves in-place, the history can't be cleared on a reset,
+  /// since we need it back when the move is taken back. Instead, we mark
+  /// where the current stretch of reversible moves starts.
   pub history: ArrayVec<ZHash, HIST_SIZE>,
+
+  /// The index into `history` of the last half-move counter reset
+  pub history_start: usize,
 }
 
-impl Position {
+/// Everything we need to restore a position after playing a move in-place
+///
+/// Note that the incremental eval isn't part of the position: the search
+/// passes the (cheap to copy) `Eval` down the stack, so the parent's copy is
+/// all we need to undo its deltas.
+#[derive(Debug, Copy, Clone)]
+pub struct PositionUndo {
+  board: BoardUndo,
+  kp_hash: ZHash,
+  pawn_hash: ZHash,
+  nonpawn_hashes: [ZHash; 2],
+  material_hash: ZHash,
+  minor_hash: ZHash,
+  history_start: usize,
+}
+
+impl<S: Sliders> Position<S> {
   /// Create a new `Position` from a `Board`
-  pub fn new(board: Board) -> Self {
+  pub fn new(board: Board<S>) -> Self {
     use Color::*;
 
     Position {
       board,
-      hash: ZHash::from(board),
       kp_hash: ZHash::kp_hash(&board),
       pawn_hash: ZHash::pawn_hash(&board),
       nonpawn_hashes: [
@@ -70,15 +102,64 @@ impl Position {
       material_hash: ZHash::material_hash(&board),
       minor_hash: ZHash::minor_hash(&board),
       history: ArrayVec::new(),
+      history_start: 0,
     }
   }
 
+  /// Use a different backend to look up slider attacks.
+  pub fn with_sliders<T: Sliders>(self) -> Position<T> {
+    Position {
+      board: self.board.with_sliders(),
+      kp_hash: self.kp_hash,
+      pawn_hash: self.pawn_hash,
+      nonpawn_hashes: self.nonpawn_hashes,
+      material_hash: self.material_hash,
+      minor_hash: self.minor_hash,
+      history: self.history,
+      history_start: self.history_start,
+    }
+  }
+}
+
+impl Position {
+  /// Create a new `Position` for the current state of a game, holding on to
+  /// the hashes of the earlier positions that can still be repeated.
+  pub fn from_game(game: &Game) -> Self {
+    let mut position = Self::new(*game.board());
+
+    // Only the positions since the last half-move counter reset can be
+    // repeated. Anything past 100 half-moves is a draw anyway, so don't keep
+    // more than that, and leave room for the search.
+    let reversible =
+      (game.board().half_moves as usize).min(HIST_SIZE - MAX_DEPTH);
+    let hashes = game.hashes();
+    let start = hashes.len().saturating_sub(reversible);
+
+    position
+      .history
+      .try_extend_from_slice(&hashes[start..])
+      .unwrap();
+    position
+  }
+}
+
+impl<S: Sliders> Position<S> {
+  /// The Zobrist hash of the current board
+  pub fn hash(&self) -> ZHash {
+    self.board.hash()
+  }
+
+  /// The hashes of the positions since the last half-move counter reset
+  pub fn repetition_history(&self) -> &[ZHash] {
+    &self.history[self.history_start..]
+  }
+
   /// Check whether the current board state is a repetition by going through
   /// the history list. The history list tends to be fairly short, so it's not
   /// as expensive as it sounds.
   pub fn is_repetition(&self) -> bool {
     self
-      .history
+      .repetition_history()
       .iter()
       // Look through the history backwards
       .rev()
@@ -88,314 +169,269 @@ impl Position {
       // repetitions
       .step_by(2)
       // Check if the zobrist hash matches to indicate a repetition
-      .any(|&historic| historic == self.hash)
+      .any(|&historic| historic == self.hash())
+  }
+
+  /// Check whether the side to move can force a repetition by playing a
+  /// single reversible move, using the cuckoo tables (see `crate::cuckoo`).
+  ///
+  /// We only look at earlier positions where the opponent's pieces are back
+  /// where they are now, so the position can only be reached again by moving
+  /// one of our own pieces. For cycles that reach back before the root, the
+  /// earlier position needs to have occurred twice already, since a single
+  /// repetition wouldn't end the game.
+  pub fn has_upcoming_repetition(&self, ply: usize) -> bool {
+    let history = self.repetition_history();
+    let len = history.len();
+
+    if len < 3 {
+      return false;
+    }
+
+    let hash = self.hash();
+
+    // Keep track of the opponent's moves, so we only consider positions
+    // where the opponent's pieces haven't moved on net.
+    let mut theirs = hash.0 ^ history[len - 1].0 ^ SIDE_KEY;
+
+    for plies_ago in (3..=len).step_by(2) {
+      let earlier = history[len - plies_ago];
+      theirs ^= history[len - plies_ago + 1].0 ^ earlier.0 ^ SIDE_KEY;
+
+      if theirs != 0 {
+        continue;
+      }
+
+      let Some(mv) = cuckoo::lookup(ZHash(hash.0 ^ earlier.0)) else {
+        continue;
+      };
+
+      // The move needs to be playable on the current board
+      let blockers = BETWEEN[mv.src()][mv.tgt()] & self.board.all_occupied();
+
+      if !blockers.is_empty() {
+        continue;
+      }
+
+      if ply > plies_ago || history[..len - plies_ago].contains(&earlier) {
+        return true;
+      }
+    }
+
+    false
   }
 
   /// Play a move and update the board, scores and hashes accordingly.
   pub fn play_move(&self, mv: Move) -> Self {
-    use PieceType::*;
-    use Square::*;
-    let source = mv.src();
-    let target = mv.tgt();
-    let capture_sq = mv.get_capture_sq();
-    let us = self.board.current;
-    let mut new_board = self.board.clone();
-    let mut new_hash = self.hash;
-    let mut new_kp_hash = self.kp_hash;
-    let mut new_pawn_hash = self.pawn_hash;
-    let mut new_nonpawn_hashes = self.nonpawn_hashes;
-    let mut new_material_hash = self.material_hash;
-    let mut new_minor_hash = self.minor_hash;
     assert!(
       mv != Move::NULL,
       "Tried processing a null move in `Position::play_move`"
     );
 
-    ////////////////////////////////////////////////////////////////////////
-    //
-    // Copture
-    //
-    ////////////////////////////////////////////////////////////////////////
-
-    if mv.is_capture() {
-      let captured = new_board.remove_at(capture_sq).unwrap();
-      new_hash.toggle_piece(captured, capture_sq);
+    let piece = self.board.get_at(mv.src()).unwrap();
+    let captured = mv
+      .is_capture()
+      .then(|| self.board.get_at(mv.get_capture_sq()).unwrap());
 
-      // Update the hashes
-      if captured.is_pawn() {
-        new_pawn_hash.toggle_piece(captured, capture_sq);
-        new_kp_hash.toggle_piece(captured, capture_sq);
-      } else {
-        new_nonpawn_hashes[!us].toggle_piece(captured, capture_sq);
+    let mut new_pos = Self {
+      board: self.board.play_move(mv),
+      kp_hash: self.kp_hash,
+      pawn_hash: self.pawn_hash,
+      nonpawn_hashes: self.nonpawn_hashes,
+      material_hash: self.material_hash,
+      minor_hash: self.minor_hash,
+      history: ArrayVec::new(),
+      history_start: 0,
+    };
 
-        if matches!(captured.piece_type(), Knight | Bishop | King) {
-          new_minor_hash.toggle_piece(captured, capture_sq);
-        }
-      }
+    new_pos.update_keys(mv, piece, captured);
 
-      // Decrement the material key for this piece
-      let count = self.board.piece_bb(captured).count();
-      new_material_hash.toggle_material(captured, count);
-      new_material_hash.toggle_material(captured, count - 1);
-
-      // Remove castling rights if captured piece is a rook on its
-      // original square
-      if captured.is_rook() {
-        match target {
-          A1 => new_board.castling_rights.remove(CastleType::WQ),
-          H1 => new_board.castling_rights.remove(CastleType::WK),
-          A8 => new_board.castling_rights.remove(CastleType::BQ),
-          H8 => new_board.castling_rights.remove(CastleType::BK),
-          _ => {}
-        }
-      }
+    // Update the repetition history, unless the half-move clock was reset
+    if new_pos.board.half_moves > 0 {
+      let history = self.repetition_history();
+      new_pos.history.try_extend_from_slice(history).unwrap();
+      new_pos.history.push(self.hash());
     }
 
-    ////////////////////////////////////////////////////////////////////////
-    //
-    // Move piece
-    //
-    ////////////////////////////////////////////////////////////////////////
-
-    // Remove selected piece from board
-    let old_piece = new_board.remove_at(source).unwrap();
-    new_hash.toggle_piece(old_piece, source);
-
-    // Figure out what piece to place at the target (considers promotions)
-    let new_piece = mv.get_promo_piece(us).unwrap_or(old_piece);
-
-    // Add the (new) piece to the board at the target square
-    new_board.add_at(target, new_piece);
-    new_hash.toggle_piece(new_piece, target);
-
-    ////////////////////////////////////////////////////////////////////////
-    //
-    // Update the pawn/non-pawn/material hashes
-    //
-    ////////////////////////////////////////////////////////////////////////
-
-    // Update the pawn and nonpawn hashes
-    if old_piece.is_pawn() {
-      new_pawn_hash.toggle_piece(old_piece, source);
-      new_kp_hash.toggle_piece(old_piece, source);
-    } else if old_piece.is_king() {
-      new_kp_hash.toggle_piece(old_piece, source);
-      new_nonpawn_hashes[us].toggle_piece(old_piece, source);
-      new_minor_hash.toggle_piece(old_piece, source);
-    } else {
-      new_nonpawn_hashes[us].toggle_piece(old_piece, source);
+    new_pos.check_keys();
+    new_pos
+  }
 
-      if matches!(old_piece.piece_type(), Knight | Bishop) {
-        new_minor_hash.toggle_piece(old_piece, source);
-      }
+  pub fn play_null_move(&self) -> Self {
+    Self {
+      board: self.board.play_null_move(),
+      kp_hash: self.kp_hash,
+      pawn_hash: self.pawn_hash,
+      nonpawn_hashes: self.nonpawn_hashes,
+      material_hash: self.material_hash,
+      minor_hash: self.minor_hash,
+      history: ArrayVec::new(),
+      history_start: 0,
     }
+  }
 
-    if new_piece.is_pawn() {
-      new_pawn_hash.toggle_piece(new_piece, target);
-      new_kp_hash.toggle_piece(new_piece, target);
-    } else if new_piece.is_king() {
-      new_kp_hash.toggle_piece(new_piece, target);
-      new_nonpawn_hashes[us].toggle_piece(new_piece, target);
-      new_minor_hash.toggle_piece(new_piece, target);
-    } else {
-      new_nonpawn_hashes[us].toggle_piece(new_piece, target);
+  /// Play a bare move
+  ///
+  /// Given a bare move, try and find a legal move that corresponds to it, and
+  /// play it. Panics if the bare move didn't correspond to a legal move!
+  pub fn play_bare_move(&self, bare: BareMove) -> Self {
+    let mv = self.board.find_move(bare).expect("Not a legal move");
 
-      if matches!(new_piece.piece_type(), Knight | Bishop) {
-        new_minor_hash.toggle_piece(new_piece, target);
-      }
-    }
+    self.play_move(mv)
+  }
 
-    // Update the material hash
-    if old_piece != new_piece {
-      // Decrement the material key for the old piece
-      let count = self.board.piece_bb(old_piece).count();
-      new_material_hash.toggle_material(old_piece, count);
-      new_material_hash.toggle_material(old_piece, count - 1);
+  /// Play a move in-place, and update the hashes accordingly. Returns the
+  /// information needed to take the move back with `Position::unmake_move`.
+  pub fn make_move(&mut self, mv: Move) -> PositionUndo {
+    assert!(
+      mv != Move::NULL,
+      "Tried processing a null move in `Position::make_move`"
+    );
 
-      // Increment the material key for the new piece
-      let count = self.board.piece_bb(new_piece).count();
-      new_material_hash.toggle_material(new_piece, count);
-      new_material_hash.toggle_material(new_piece, count + 1);
-    }
+    let piece = self.board.get_at(mv.src()).unwrap();
+    let board_undo = self.board.make_move(mv);
+    let undo = self.undo_record(board_undo);
 
-    ////////////////////////////////////////////////////////////////////////
-    //
-    // Castling
-    //
-    ////////////////////////////////////////////////////////////////////////
+    self.update_keys(mv, piece, board_undo.captured);
 
-    // If castle: also account for the rook having moved
-    if mv.is_castle() {
-      // In case of castle, also move the rook to the appropriate square
-      let ctype = CastleType::from_move(mv).unwrap();
-      let rook_move = ctype.rook_move();
-      let rook_src = rook_move.src();
-      let rook_tgt = rook_move.tgt();
-      let rook = new_board.remove_at(rook_src).unwrap();
-      new_board.add_at(rook_tgt, rook);
-
-      // Update the hash
-      new_hash.toggle_piece(rook, rook_src);
-      new_hash.toggle_piece(rook, rook_tgt);
-
-      new_nonpawn_hashes[us].toggle_piece(rook, rook_src);
-      new_nonpawn_hashes[us].toggle_piece(rook, rook_tgt);
-    }
-
-    if old_piece.is_king() {
-      if us.is_white() {
-        new_board.castling_rights.remove(CastleType::WQ);
-        new_board.castling_rights.remove(CastleType::WK);
-      } else {
-        new_board.castling_rights.remove(CastleType::BQ);
-        new_board.castling_rights.remove(CastleType::BK);
-      }
-    } else if old_piece.is_rook() {
-      match source {
-        A1 => new_board.castling_rights.remove(CastleType::WQ),
-        H1 => new_board.castling_rights.remove(CastleType::WK),
-        A8 => new_board.castling_rights.remove(CastleType::BQ),
-        H8 => new_board.castling_rights.remove(CastleType::BK),
-        _ => {}
-      }
+    // Rather than clearing the history on a half-move counter reset, move
+    // the start of the history up.
+    self.history.push(board_undo.hash);
+
+    if self.board.half_moves == 0 {
+      self.history_start = self.history.len();
     }
 
-    // Invalidate the previous castling rights, even if the move wasn't a
-    // castle.
-    // FIXME: Improve this
-    new_hash.toggle_castling(self.board.castling_rights);
-    new_hash.toggle_castling(new_board.castling_rights);
+    self.check_keys();
+    undo
+  }
 
-    ////////////////////////////////////////////////////////////////////////
-    //
-    // En-passant
-    //
-    ////////////////////////////////////////////////////////////////////////
+  /// Take back a move that was played with `Position::make_move`
+  pub fn unmake_move(&mut self, mv: Move, undo: PositionUndo) {
+    self.board.unmake_move(mv, undo.board);
+    self.history.pop();
+    self.restore(undo);
+  }
 
-    // Should we unset the old EP square?
-    if let Some(ep_sq) = self.board.en_passant {
-      new_board.en_passant = None;
-      new_hash.toggle_ep(ep_sq)
-    }
+  /// Pass the turn in-place. Returns the information needed to take it back
+  /// with `Position::unmake_null_move`.
+  pub fn make_null_move(&mut self) -> PositionUndo {
+    let board_undo = self.board.make_null_move();
+    let undo = self.undo_record(board_undo);
 
-    // Should we set a new EP square?
-    if mv.is_double_push() {
-      let ep_sq = target.backward(us).unwrap();
-      new_board.en_passant = Some(ep_sq);
-      new_hash.toggle_ep(ep_sq)
-    }
+    // Repetitions don't count across a null move
+    self.history.push(board_undo.hash);
+    self.history_start = self.history.len();
 
-    ////////////////////////////////////////////////////////////////////////
-    //
-    // Update state variables
-    //
-    ////////////////////////////////////////////////////////////////////////
+    undo
+  }
 
-    // Update playing side
-    new_board.current = !us;
-    new_hash.toggle_side();
+  /// Take back a null move that was played with `Position::make_null_move`
+  pub fn unmake_null_move(&mut self, undo: PositionUndo) {
+    self.board.unmake_null_move(undo.board);
+    self.history.pop();
+    self.restore(undo);
+  }
 
-    // Update move counter
-    if us.is_black() {
-      new_board.full_moves += 1;
+  /// Save the partial keys, along with the board's undo record
+  fn undo_record(&self, board: BoardUndo) -> PositionUndo {
+    PositionUndo {
+      board,
+      kp_hash: self.kp_hash,
+      pawn_hash: self.pawn_hash,
+      nonpawn_hashes: self.nonpawn_hashes,
+      material_hash: self.material_hash,
+      minor_hash: self.minor_hash,
+      history_start: self.history_start,
     }
+  }
 
-    // Update half-move clock and repetition history
-    let mut new_history;
+  /// Restore the partial keys from an undo record
+  fn restore(&mut self, undo: PositionUndo) {
+    self.kp_hash = undo.kp_hash;
+    self.pawn_hash = undo.pawn_hash;
+    self.nonpawn_hashes = undo.nonpawn_hashes;
+    self.material_hash = undo.material_hash;
+    self.minor_hash = undo.minor_hash;
+    self.history_start = undo.history_start;
+  }
 
-    if old_piece.is_pawn() || mv.is_capture() {
-      new_board.half_moves = 0;
-      new_history = ArrayVec::new();
-    } else {
-      new_board.half_moves += 1;
-      new_history = self.history.clone();
-      new_history.push(self.hash);
-    }
+  /// Update the partial keys after a move was played on the board.
+  ///
+  /// The board has already been updated, so we need to be told what piece
+  /// moved, and what piece (if any) was captured.
+  fn update_keys(&mut self, mv: Move, piece: Piece, captured: Option<Piece>) {
+    let new_piece = self.board.get_at(mv.tgt()).unwrap();
 
-    ////////////////////////////////////////////////////////////////////////
-    //
-    // Update auxiliary bitboards (pins, checkers, threats)
-    //
-    ////////////////////////////////////////////////////////////////////////
+    // Note that the piece counts are the counts _after_ the move
+    if let Some(captured) = captured {
+      self.toggle_keys(captured, mv.get_capture_sq());
 
-    new_board.hv_pinrays = [
-      new_board.compute_hv_pinrays::<true>(),
-      new_board.compute_hv_pinrays::<false>(),
-    ];
+      let count = self.board.piece_bb(captured).count();
+      self.material_hash.toggle_material(captured, count + 1);
+      self.material_hash.toggle_material(captured, count);
+    }
 
-    new_board.diag_pinrays = [
-      new_board.compute_diag_pinrays::<true>(),
-      new_board.compute_diag_pinrays::<false>(),
-    ];
+    self.toggle_keys(piece, mv.src());
+    self.toggle_keys(new_piece, mv.tgt());
 
-    new_board.checkers = new_board.compute_checkers();
-    new_board.threats = new_board.attacked_squares(!new_board.current);
+    if piece != new_piece {
+      let count = self.board.piece_bb(piece).count();
+      self.material_hash.toggle_material(piece, count + 1);
+      self.material_hash.toggle_material(piece, count);
 
-    Self {
-      board: new_board,
-      hash: new_hash,
-      kp_hash: new_kp_hash,
-      pawn_hash: new_pawn_hash,
-      nonpawn_hashes: new_nonpawn_hashes,
-      material_hash: new_material_hash,
-      minor_hash: new_minor_hash,
-      history: new_history,
+      let count = self.board.piece_bb(new_piece).count();
+      self.material_hash.toggle_material(new_piece, count - 1);
+      self.material_hash.toggle_material(new_piece, count);
+    }
+
+    if mv.is_castle() {
+      let rook_move = CastleType::from_move(mv).unwrap().rook_move();
+      let rook = self.board.get_at(rook_move.tgt()).unwrap();
+      self.toggle_keys(rook, rook_move.src());
+      self.toggle_keys(rook, rook_move.tgt());
     }
   }
 
-  pub fn play_null_move(&self) -> Self {
-    let us = self.board.current;
-    let mut new_board = self.board.clone();
-    let mut new_hash = self.hash;
-    let new_history = ArrayVec::new();
-
-    ////////////////////////////////////////////////////////////////////////
-    //
-    // Update state variables
-    //
-    ////////////////////////////////////////////////////////////////////////
-
-    // Update player
-    new_board.current = !us;
-    new_hash.toggle_side();
+  /// Add or remove a piece from all of the partial keys it belongs to
+  fn toggle_keys(&mut self, piece: Piece, square: Square) {
+    use PieceType::*;
 
-    // Un-set the old en-passant square
-    if let Some(ep_sq) = self.board.en_passant {
-      new_board.en_passant = None;
-      new_hash.toggle_ep(ep_sq)
+    if piece.is_pawn() {
+      self.pawn_hash.toggle_piece(piece, square);
+      self.kp_hash.toggle_piece(piece, square);
+      return;
     }
 
-    // Update half-move counter
-    new_board.half_moves += 1;
+    self.nonpawn_hashes[piece.color()].toggle_piece(piece, square);
 
-    // Update move counter
-    if us.is_black() {
-      new_board.full_moves += 1;
+    if piece.is_king() {
+      self.kp_hash.toggle_piece(piece, square);
     }
 
-    new_board.checkers = new_board.compute_checkers();
-    new_board.threats = new_board.attacked_squares(!new_board.current);
-
-    Self {
-      board: new_board,
-      hash: new_hash,
-      kp_hash: self.kp_hash,
-      pawn_hash: self.pawn_hash,
-      nonpawn_hashes: self.nonpawn_hashes,
-      material_hash: self.material_hash,
-      minor_hash: self.minor_hash,
-      history: new_history,
+    if matches!(piece.piece_type(), Knight | Bishop | King) {
+      self.minor_hash.toggle_piece(piece, square);
     }
   }
 
-  /// Play a bare move
-  ///
-  /// Given a bare move, try and find a legal move that corresponds to it, and
-  /// play it. Panics if the bare move didn't correspond to a legal move!
-  pub fn play_bare_move(&self, bare: BareMove) -> Self {
-    let mv = self.board.find_move(bare).expect("Not a legal move");
-
-    self.play_move(mv)
+  /// With the `check-hashes` feature enabled, check the incrementally
+  /// updated keys against the keys computed from scratch.
+  #[inline(always)]
+  fn check_keys(&self) {
+    if cfg!(feature = "check-hashes") {
+      let fresh = Position::new(self.board);
+      let matches = self.kp_hash == fresh.kp_hash
+        && self.pawn_hash == fresh.pawn_hash
+        && self.nonpawn_hashes == fresh.nonpawn_hashes
+        && self.material_hash == fresh.material_hash
+        && self.minor_hash == fresh.minor_hash;
+
+      assert!(
+        matches,
+        "Incremental key mismatch for {}",
+        self.board.to_fen()
+      );
+    }
   }
 
   /// Return a first approximation of the Zobrist hash after playing the
@@ -406,7 +442,7 @@ impl Position {
   ///
   /// In particular, castling rights are not updated whatsoever.
   pub fn approx_hash_after(&self, mv: Move) -> ZHash {
-    let mut new_hash = self.hash;
+    let mut new_hash = self.hash();
 
     // Update playing side
     new_hash.toggle_side();
@@ -452,6 +488,131 @@ impl Position {
   }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+//
+// Child positions
+//
+////////////////////////////////////////////////////////////////////////////////
+
+/// The position after playing a move, using either copy-make or make/unmake.
+///
+/// This lets the search treat both the same way: the child position derefs to
+/// a `Position`, and when it's played in-place, the move is taken back as soon
+/// as the child goes out of scope.
+pub enum ChildPosition<'a, S: Sliders = Magics> {
+  /// The move was played on a copy of the parent
+  Copied {
+    parent: &'a Position<S>,
+    child: Position<S>,
+  },
+
+  /// The move was played in-place, and needs to be taken back
+  InPlace {
+    position: &'a mut Position<S>,
+    mv: Move,
+    undo: PositionUndo,
+  },
+}
+
+impl<'a, S: Sliders> ChildPosition<'a, S> {
+  /// Play a move on the parent position
+  pub fn new(parent: &'a mut Position<S>, mv: Move, in_place: bool) -> Self {
+    if in_place {
+      let undo = parent.make_move(mv);
+
+      Self::InPlace {
+        position: parent,
+        mv,
+        undo,
+      }
+    } else {
+      let child = parent.play_move(mv);
+      Self::Copied { parent, child }
+    }
+  }
+
+  /// Pass the turn on the parent position
+  pub fn null(parent: &'a mut Position<S>, in_place: bool) -> Self {
+    if in_place {
+      let undo = parent.make_null_move();
+
+      Self::InPlace {
+        position: parent,
+        mv: Move::NULL,
+        undo,
+      }
+    } else {
+      let child = parent.play_null_move();
+      Self::Copied { parent, child }
+    }
+  }
+
+  /// Run a function on the parent position.
+  ///
+  /// When the move was played in-place, it is taken back for the duration of
+  /// the call, so prefer to do this sparingly.
+  pub fn with_parent<T>(&mut self, f: impl FnOnce(&Position<S>) -> T) -> T {
+    match self {
+      Self::Copied { parent, .. } => f(parent),
+
+      Self::InPlace { position, mv, undo } => {
+        position.unmake(*mv, *undo);
+        let result = f(position);
+        *undo = position.make(*mv);
+        result
+      }
+    }
+  }
+}
+
+impl<S: Sliders> Position<S> {
+  /// Play a regular or null move in-place
+  fn make(&mut self, mv: Move) -> PositionUndo {
+    if mv == Move::NULL {
+      self.make_null_move()
+    } else {
+      self.make_move(mv)
+    }
+  }
+
+  /// Take back a regular or null move that was played in-place
+  fn unmake(&mut self, mv: Move, undo: PositionUndo) {
+    if mv == Move::NULL {
+      self.unmake_null_move(undo)
+    } else {
+      self.unmake_move(mv, undo)
+    }
+  }
+}
+
+impl<S: Sliders> Deref for ChildPosition<'_, S> {
+  type Target = Position<S>;
+
+  fn deref(&self) -> &Position<S> {
+    match self {
+      Self::Copied { child, .. } => child,
+      Self::InPlace { position, .. } => position,
+    }
+  }
+}
+
+impl<S: Sliders> DerefMut for ChildPosition<'_, S> {
+  fn deref_mut(&mut self) -> &mut Position<S> {
+    match self {
+      Self::Copied { child, .. } => child,
+      Self::InPlace { position, .. } => position,
+    }
+  }
+}
+
+impl<S: Sliders> Drop for ChildPosition<'_, S> {
+  fn drop(&mut self) {
+    if let Self::InPlace { position, mv, undo } = self {
+      position.unmake(*mv, *undo);
+    }
+  }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 //
 // Tests
@@ -499,10 +660,10 @@ mod tests {
 
     // Check that incremental updates yield the same result as hashing the
     // entire board
-    assert_eq!(final_pos.hash, final_pos.board.into());
+    assert_eq!(final_pos.hash(), final_pos.board.compute_hash());
 
     // Check whether the hash matches the expected board's
-    assert_eq!(final_pos.hash, expected.hash);
+    assert_eq!(final_pos.hash(), expected.hash());
   }
 
   /// Test that, for all of the test suite, playing _every_ single legal move
@@ -511,7 +672,6 @@ mod tests {
   #[test]
   fn incremental_hashing() {
     let mut results: Vec<bool> = Vec::new();
-    use crate::zobrist::Zobrist;
 
     for fen in TEST_POSITIONS {
       let board = fen.parse().unwrap();
@@ -521,7 +681,7 @@ mod tests {
         .legal_moves::<All>()
         .into_iter()
         .map(|mv| position.play_move(mv))
-        .all(|new_pos| new_pos.hash == new_pos.board.hash());
+        .all(|new_pos| new_pos.hash() == new_pos.board.compute_hash());
 
       if all_match {
         println!("{}", fen.green());
@@ -634,4 +794,181 @@ mod tests {
 
     assert_eq!(terminal_inc.pawn_hash, terminal.pawn_hash);
   }
+
+  /// Check that two positions agree on the board, the hashes and the
+  /// positions that count towards repetitions.
+  fn assert_same_position(found: &Position, expected: &Position, msg: &str) {
+    assert_eq!(found.board, expected.board, "board after {msg}");
+    assert_eq!(found.hash(), expected.hash(), "hash after {msg}");
+    assert_eq!(found.kp_hash, expected.kp_hash, "kp hash after {msg}");
+    assert_eq!(found.pawn_hash, expected.pawn_hash, "pawn hash after {msg}");
+    assert_eq!(
+      found.nonpawn_hashes, expected.nonpawn_hashes,
+      "nonpawn hashes after {msg}"
+    );
+    assert_eq!(
+      found.material_hash, expected.material_hash,
+      "material hash after {msg}"
+    );
+    assert_eq!(
+      found.minor_hash, expected.minor_hash,
+      "minor hash after {msg}"
+    );
+    assert_eq!(
+      found.repetition_history(),
+      expected.repetition_history(),
+      "history after {msg}"
+    );
+  }
+
+  /// Play every legal move two plies deep in the test suite, and check that
+  /// make/unmake agrees with copy-make every step of the way.
+  #[test]
+  fn make_move_matches_play_move() {
+    for fen in TEST_POSITIONS {
+      let mut position = Position::new(fen.parse().unwrap());
+
+      for mv in position.board.legal_moves::<All>() {
+        let original = position.clone();
+        let expected = position.play_move(mv);
+
+        let undo = position.make_move(mv);
+        assert_same_position(&position, &expected, &format!("{mv} in {fen}"));
+
+        for reply in position.board.legal_moves::<All>() {
+          let expected = expected.play_move(reply);
+          let undo = position.make_move(reply);
+          let msg = format!("{mv} {reply} in {fen}");
+          assert_same_position(&position, &expected, &msg);
+          position.unmake_move(reply, undo);
+        }
+
+        position.unmake_move(mv, undo);
+        assert_same_position(&position, &original, &format!("undoing {mv}"));
+      }
+
+      let expected = position.play_null_move();
+      let undo = position.make_null_move();
+      assert_same_position(&position, &expected, &format!("null in {fen}"));
+      position.unmake_null_move(undo);
+    }
+  }
+
+  #[test]
+  fn make_move_tracks_repetitions() {
+    let board = "3k4/8/8/8/8/8/8/3K3P w - - 0 1".parse().unwrap();
+    let mut position = Position::new(board);
+
+    for mv in ["d1e1", "d8e8", "e1d1", "e8d8"] {
+      let mv = position.board.find_move(mv.parse().unwrap()).unwrap();
+      position.make_move(mv);
+    }
+
+    assert!(position.is_repetition());
+    assert_eq!(position.repetition_history().len(), 4);
+
+    let mv = position.board.find_move("h1h2".parse().unwrap()).unwrap();
+    let undo = position.make_move(mv);
+    assert!(position.repetition_history().is_empty());
+
+    position.unmake_move(mv, undo);
+    assert!(position.is_repetition());
+  }
+
+  /// Long games should fit in the position's history, and still have their
+  /// repetitions detected.
+  #[test]
+  fn from_game_keeps_repetition_history() {
+    let board = "3k4/8/8/8/8/8/8/3K3P w - - 0 1".parse().unwrap();
+    let mut game = Game::new(board);
+
+    for _ in 0..40 {
+      for mv in ["d1e1", "d8e8", "e1d1", "e8d8"] {
+        game.play_bare_move(mv.parse().unwrap()).unwrap();
+      }
+    }
+
+    let position = Position::from_game(&game);
+    assert!(position.is_repetition());
+    assert_eq!(position.repetition_history().len(), HIST_SIZE - MAX_DEPTH);
+
+    // A pawn move means no earlier positions can be repeated
+    game.play_bare_move("h1h2".parse().unwrap()).unwrap();
+    let position = Position::from_game(&game);
+    assert!(position.repetition_history().is_empty());
+  }
+
+  /// Set up a position by playing the provided moves, in UCI notation
+  fn play_game(fen: &str, moves: &str) -> Position {
+    let mut game = Game::new(fen.parse().unwrap());
+
+    for mv in moves.split_whitespace() {
+      game.play_bare_move(mv.parse().unwrap()).unwrap();
+    }
+
+    Position::from_game(&game)
+  }
+
+  #[test]
+  fn upcoming_repetition_knight_shuffle() {
+    let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    // Black can play Ng8 to get back to the starting position
+    let position = play_game(fen, "g1f3 g8f6 f3g1");
+    assert!(!position.is_repetition());
+    assert!(position.has_upcoming_repetition(4));
+
+    // ... but if the starting position was before the root, it only counts
+    // once it has been repeated already.
+    assert!(!position.has_upcoming_repetition(1));
+
+    let position = play_game(fen, "g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1");
+    assert!(position.has_upcoming_repetition(1));
+
+    // White has changed the position, so there's nothing to repeat
+    let position = play_game(fen, "g1f3 g8f6 e2e3");
+    assert!(!position.has_upcoming_repetition(4));
+  }
+
+  #[test]
+  fn upcoming_repetition_needs_a_free_path() {
+    // The rook goes the long way around from a8 to a1, while the black king
+    // walks around in a circle.
+    let moves = "h6h5 a8b8 h5g5 b8b1 g5g6 b1a1 g6h6";
+
+    // Ra8 gets us back to the start
+    let position = play_game("R7/8/7k/8/2P5/8/8/7K b - - 0 1", moves);
+    assert!(position.has_upcoming_repetition(8));
+
+    // Ra8 gets us back to the start, but the pawn is in the way
+    let position = play_game("R7/8/7k/8/P7/8/8/7K b - - 0 1", moves);
+    assert!(!position.has_upcoming_repetition(8));
+  }
+
+  /// Searching with either path should yield the exact same search.
+  #[test]
+  fn search_paths_agree() {
+    use crate::search::NodeCounter;
+    use crate::search::SearchRunner;
+    use crate::time_control::TimeController;
+    use crate::transpositions::TTable;
+    use std::sync::atomic::AtomicU32;
+    use uci::time_control::TimeControl;
+
+    let board: Board = TEST_POSITIONS[1].parse().unwrap();
+
+    let search = |make_unmake| {
+      let tt = TTable::with_capacity(4);
+      let global_nodes = AtomicU32::new(0);
+      let nodes = NodeCounter::new(&global_nodes);
+      let mut runner = SearchRunner::new(0, &tt, nodes);
+      let (tc, _) = TimeController::new(TimeControl::Depth(8), board.current);
+      runner.make_unmake = make_unmake;
+
+      let report = runner.search::<false>(Position::new(board), tc);
+      (report.score, report.pv, runner.nodes.local())
+    };
+
+    assert_eq!(search(false), search(true));
+  }
 }
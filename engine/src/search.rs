@@ -26,25 +26,34 @@ use crate::evaluate::ScoreExt;
 use crate::history_tables::pv::PVTable;
 use crate::history_tables::History;
 use crate::position::Position;
+use crate::search::contempt::DEFAULT_CONTEMPT;
+use crate::search::observer::default_observer;
+use crate::search::observer::SearchEvent;
+use crate::search::observer::SearchObserver;
 use crate::search::params::MAX_DEPTH;
+use crate::search::strength::Handicap;
+use crate::thread_pool::SharedSearch;
 use crate::time_control::TimeController;
 use crate::transpositions::TTable;
 use chess::movegen::legal_moves::All;
 use chess::movegen::moves::Move;
+use chess::movegen::sliders::Sliders;
 use chess::piece::Color;
-use std::io::IsTerminal;
 use std::sync::atomic::AtomicU32;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use std::time::Duration;
 use uci::search_info::Score as UciScore;
 use uci::search_info::SearchInfo;
 use uci::time_control::TimeControl;
-use uci::wdl::WDL_MODEL;
 
 mod aspiration;
+pub mod contempt;
 mod negamax;
+pub mod observer;
 pub mod params;
 mod quiescence;
+pub mod strength;
 mod zero_window;
 
 const KP_CACHE_SIZE: usize = 2;
@@ -58,8 +67,22 @@ pub struct SearchRunner<'a> {
   pub kp_cache: KingPawnCache,
   pub nodes: NodeCounter<'a>,
   pub tc: TimeController,
+  pub contempt: Score,
+  pub handicap: Option<Handicap>,
+  pub observer: Box<dyn SearchObserver>,
+
+  /// Whether to play moves in-place with make/unmake, rather than copying
+  /// the position for every move
+  pub make_unmake: bool,
+
+  /// Progress shared with the other threads, when part of a multi-threaded
+  /// search
+  pub shared: Option<Arc<SharedSearch>>,
   stack: [SearchStackEntry; MAX_DEPTH + 1],
   aborted: bool,
+
+  /// Whether this runner reports on its progress to the observer
+  reporting: bool,
 }
 
 impl<'a> SearchRunner<'a> {
@@ -77,7 +100,13 @@ impl<'a> SearchRunner<'a> {
       nodes,
       stack: [SearchStackEntry::default(); MAX_DEPTH + 1],
       tc,
+      contempt: DEFAULT_CONTEMPT,
+      handicap: None,
+      observer: default_observer(false),
+      make_unmake: cfg!(feature = "make-unmake"),
+      shared: None,
       aborted: false,
+      reporting: false,
     }
   }
 
@@ -98,9 +127,36 @@ impl<'a> SearchRunner<'a> {
 ////////////////////////////////////////////////////////////////////////////////
 
 impl<'a> SearchRunner<'a> {
+  /// Search the position, and report the best move found.
   pub fn search<const DEBUG: bool>(
     &mut self,
-    mut pos: Position,
+    mut pos: Position<impl Sliders>,
+    tc: TimeController,
+  ) -> SearchReport {
+    let mut report = self.iterative_deepening::<DEBUG>(&mut pos, tc);
+
+    // When playing with a handicap, don't necessarily play the best move,
+    // but pick one of the near-best moves instead.
+    if let Some(handicap) = self.handicap {
+      report = self.pick_handicapped_move(&mut pos, &report, handicap);
+    }
+
+    self.notify(|| SearchEvent::BestMove {
+      board: pos.board.with_sliders(),
+      report: report.clone(),
+    });
+
+    report
+  }
+
+  /// Run the iterative deepening loop, and return the report for the last
+  /// completed iteration.
+  ///
+  /// Unlike `search`, this doesn't pick a move or announce a best move, so the
+  /// result can still be compared against those of other threads.
+  pub(crate) fn iterative_deepening<const DEBUG: bool>(
+    &mut self,
+    pos: &mut Position<impl Sliders>,
     tc: TimeController,
   ) -> SearchReport {
     let mut latest_report = SearchReport::default();
@@ -111,6 +167,7 @@ impl<'a> SearchRunner<'a> {
     let mut score_stability = 0;
     self.reinit(); // Clear previous search data
     self.tc = tc;
+    self.reporting = DEBUG && self.id == 0;
 
     // If there is only one legal move, notify the the time controller that
     // we don't want to waste any more time here.
@@ -118,7 +175,23 @@ impl<'a> SearchRunner<'a> {
       self.tc.stop_early();
     }
 
-    while self.depth <= MAX_DEPTH && self.tc.should_start_search(self.depth) {
+    while self.depth <= MAX_DEPTH
+      && self.tc.should_start_search(self.depth)
+      && !self.tc.node_limit_reached(self.nodes.local())
+    {
+      // Helper threads skip some of the iterations, to spread the threads
+      // out over different depths.
+      if let Some(shared) = &self.shared {
+        if shared.should_skip(self.id, self.depth) {
+          self.depth += 1;
+          continue;
+        }
+      }
+
+      if let Some(shared) = &self.shared {
+        shared.start(self.depth);
+      }
+
       pv.clear();
       self.history.clear_all_killers();
 
@@ -128,8 +201,11 @@ impl<'a> SearchRunner<'a> {
       //
       ////////////////////////////////////////////////////////////////////
 
-      let score =
-        self.aspiration_search(&mut pos, latest_report.score, &mut pv);
+      let score = self.aspiration_search(pos, latest_report.score, &mut pv);
+
+      if let Some(shared) = &self.shared {
+        shared.finish(self.depth);
+      }
 
       // If we got interrupted in the search, don't store the
       // half-completed search state. Just break and return the previous
@@ -140,6 +216,17 @@ impl<'a> SearchRunner<'a> {
 
       latest_report = SearchReport::new(&self, score, &pv);
 
+      if let Some(shared) = &self.shared {
+        shared.publish(self.depth, pv.pv_move());
+      }
+
+      // When playing with a handicap, cap the number of nodes. We only do
+      // this once the first iteration has completed, to make sure we always
+      // have a move to play.
+      if let Some(handicap) = self.handicap {
+        self.tc.limit_nodes(handicap.search_nodes());
+      }
+
       ////////////////////////////////////////////////////////////////////
       //
       // Update the time controller with gathered search statistics
@@ -155,6 +242,16 @@ impl<'a> SearchRunner<'a> {
         }
         prev_best_move = Some(pv.pv_move());
 
+        // If one of the helpers got further than we did, and disagrees on the
+        // best move, don't trust our best move just yet.
+        let deepest = self.shared.as_ref().and_then(|shared| shared.deepest());
+
+        if let Some((depth, best_move)) = deepest {
+          if depth > self.depth && best_move != pv.pv_move() {
+            best_move_stability = 0;
+          }
+        }
+
         if score >= previous_score - 10 && score <= previous_score + 10 {
           score_stability += 1;
         } else {
@@ -174,37 +271,31 @@ impl<'a> SearchRunner<'a> {
 
       ////////////////////////////////////////////////////////////////////
       //
-      // Print search output
+      // Report search progress
       //
       ////////////////////////////////////////////////////////////////////
 
-      if DEBUG && self.id == 0 {
-        let wdl_params = WDL_MODEL.params(&pos.board);
-        let info = SearchInfo::from(&latest_report);
-
-        // When the output is a terminal, we pretty-print the output
-        // and include WDL stats.
-        if std::io::stdout().is_terminal() {
-          println!("{}", info.to_pretty(&pos.board, wdl_params));
-        }
-        // If we're talking to another process, _and we're not in wdl
-        // mode_, we print UCI compliant output, but with the eval
-        // rescaled according to the WDL model.
-        else if !cfg!(feature = "wdl") {
-          println!("info {}", info.to_uci(wdl_params));
-        }
-        // If we're talking to a process, _and_ we're in WDL mode, we
-        // output the score in internal, unscaled, values.
-        else {
-          println!("info {info}");
-        }
-      }
+      self.notify(|| SearchEvent::Iteration {
+        board: pos.board.with_sliders(),
+        report: latest_report.clone(),
+      });
 
       self.depth += 1;
     }
 
+    // Make sure all our nodes are accounted for in the global count
+    self.nodes.flush();
+
     latest_report
   }
+
+  /// Report an event to the observer, if this runner is reporting on its
+  /// progress. The event is only constructed when it's actually needed.
+  pub(crate) fn notify(&mut self, event: impl FnOnce() -> SearchEvent) {
+    if self.reporting {
+      self.observer.notify(event());
+    }
+  }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -281,6 +372,7 @@ impl From<&SearchReport> for SearchInfo {
       nps: Some(nps),
       currmove: None,
       currmovenumber: None,
+      wdl: None,
     }
   }
 }
@@ -357,6 +449,12 @@ impl<'a> NodeCounter<'a> {
     }
   }
 
+  /// Add any nodes that haven't been reported yet to the global count
+  pub fn flush(&mut self) {
+    self.global.fetch_add(self.buffer, Ordering::Relaxed);
+    self.buffer = 0;
+  }
+
   pub fn clear_global(&self) {
     self.global.store(0, Ordering::Relaxed);
   }
@@ -14,6 +14,7 @@
 //! The hope, as always in these things, is that the score is stable enough that
 //! re-searches are minimal, and the time we save in the best-case scenario
 //! more than compensates for the odd re-search.
+use super::observer::SearchEvent;
 use super::SearchRunner;
 use crate::evaluate::tuner::NullTracer;
 use crate::evaluate::Eval;
@@ -22,12 +23,14 @@ use crate::evaluate::ScoreExt;
 use crate::history_tables::pv::PVTable;
 use crate::position::Position;
 use crate::search::params::*;
+use crate::transpositions::NodeType;
+use chess::movegen::sliders::Sliders;
 
 impl<'a> SearchRunner<'a> {
   /// Perform an alpha-beta search with aspiration window centered on `guess`.
   pub fn aspiration_search(
     &mut self,
-    pos: &mut Position,
+    pos: &mut Position<impl Sliders>,
     guess: Score,
     pv: &mut PVTable,
   ) -> Score {
@@ -54,6 +57,22 @@ impl<'a> SearchRunner<'a> {
         false,
       );
 
+      // Let the observer know we failed low or high
+      if !self.aborted && (score <= alpha || score >= beta) {
+        let bound = if score <= alpha {
+          NodeType::Upper
+        } else {
+          NodeType::Lower
+        };
+
+        let depth = self.depth;
+        self.notify(|| SearchEvent::Bound {
+          depth,
+          score,
+          bound,
+        });
+      }
+
       // If we fail low or high, grow the bounds upward/downward
       if score <= alpha {
         alpha -= width;
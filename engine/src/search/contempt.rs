@@ -0,0 +1,122 @@
+//! Contempt settings
+//!
+//! Contempt is the amount by which the engine considers a draw to be worse
+//! than an equal position. A positive contempt makes the engine avoid draws
+//! and play on, a negative contempt makes it happy to settle for a draw.
+//!
+//! Rather than playing with a fixed contempt, we can also adjust the contempt
+//! to the strength of our opponent (if the GUI tells us about it through
+//! `UCI_Opponent`): against a much weaker opponent, we don't want to let them
+//! off the hook with a draw, while against a much stronger opponent, a draw is
+//! a perfectly fine result.
+
+use crate::evaluate::Score;
+
+/// The default contempt, in centipawns.
+pub const DEFAULT_CONTEMPT: Score = 50;
+
+/// An estimate of our own playing strength, used to compute the rating
+/// difference with the opponent when using dynamic contempt.
+pub const ENGINE_ELO: u32 = 3200;
+
+/// The maximum amount (in centipawns) by which dynamic contempt can raise or
+/// lower the base contempt.
+const DYNAMIC_CONTEMPT_RANGE: f64 = 100.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Contempt {
+  /// The base contempt, in centipawns, as set through the `Contempt` option
+  pub base: Score,
+
+  /// Whether to scale the contempt according to the opponent's rating
+  pub dynamic: bool,
+
+  /// When analysing, we want an objective evaluation, so we disable contempt
+  /// altogether.
+  pub analysis: bool,
+
+  /// The opponent's rating, if known.
+  pub opponent_elo: Option<u32>,
+
+  /// Our own rating. This is lower than `ENGINE_ELO` when we're limiting
+  /// our strength.
+  pub engine_elo: u32,
+}
+
+impl Contempt {
+  /// Return the effective contempt (in centipawns), taking into account the
+  /// analysis mode and the opponent's strength.
+  pub fn value(&self) -> Score {
+    if self.analysis {
+      return 0;
+    }
+
+    let Some(opponent_elo) = self.opponent_elo.filter(|_| self.dynamic) else {
+      return self.base;
+    };
+
+    // Scale the contempt by our expected score against the opponent, mapped
+    // onto the [-1, 1] range.
+    let elo_diff = self.engine_elo as f64 - opponent_elo as f64;
+    let expected = 1.0 / (1.0 + f64::powf(10.0, -elo_diff / 400.0));
+    let adjustment = DYNAMIC_CONTEMPT_RANGE * (2.0 * expected - 1.0);
+
+    self.base + adjustment.round() as Score
+  }
+}
+
+impl Default for Contempt {
+  fn default() -> Self {
+    Self {
+      base: DEFAULT_CONTEMPT,
+      dynamic: false,
+      analysis: false,
+      opponent_elo: None,
+      engine_elo: ENGINE_ELO,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn analysis_mode_disables_contempt() {
+    let contempt = Contempt {
+      analysis: true,
+      dynamic: true,
+      opponent_elo: Some(1500),
+      ..Contempt::default()
+    };
+
+    assert_eq!(contempt.value(), 0);
+  }
+
+  #[test]
+  fn dynamic_contempt_scales_with_opponent() {
+    let against = |elo| {
+      Contempt {
+        dynamic: true,
+        opponent_elo: Some(elo),
+        ..Contempt::default()
+      }
+      .value()
+    };
+
+    assert!(against(1500) > DEFAULT_CONTEMPT);
+    assert_eq!(against(ENGINE_ELO), DEFAULT_CONTEMPT);
+    assert!(against(3600) < DEFAULT_CONTEMPT);
+    assert!(against(1500) <= DEFAULT_CONTEMPT + 100);
+  }
+
+  #[test]
+  fn static_contempt_ignores_opponent() {
+    let contempt = Contempt {
+      opponent_elo: Some(1500),
+      ..Contempt::default()
+    };
+
+    assert_eq!(contempt.value(), DEFAULT_CONTEMPT);
+  }
+}
@@ -6,13 +6,16 @@ use crate::history_tables::history::HistoryScore;
 use crate::history_tables::pv::PVTable;
 use crate::move_picker::MovePicker;
 use crate::move_picker::Stage;
+use crate::position::ChildPosition;
 use crate::position::Position;
 use crate::transpositions::NodeType;
 use crate::transpositions::TTEntry;
 use chess::movegen::legal_moves::MoveList;
 use chess::movegen::moves::Move;
 use chess::movegen::moves::MoveType;
+use chess::movegen::sliders::Sliders;
 
+use super::observer::SearchEvent;
 use super::params::lmr_reduction;
 use super::params::MAX_DEPTH;
 use super::params::*;
@@ -24,10 +27,10 @@ impl<'a> SearchRunner<'a> {
   /// The main negamax function of the search routine.
   pub fn negamax<const PV: bool>(
     &mut self,
-    pos: &Position,
+    pos: &mut Position<impl Sliders>,
     ply: usize,
     mut depth: usize,
-    alpha: Score,
+    mut alpha: Score,
     beta: Score,
     pv: &mut PVTable,
     mut eval_state: Eval,
@@ -70,7 +73,7 @@ impl<'a> SearchRunner<'a> {
     ////////////////////////////////////////////////////////////////////////
 
     if depth == 0 || ply >= MAX_DEPTH {
-      return self.quiescence_search::<PV>(&pos, ply, alpha, beta, eval_state);
+      return self.quiescence_search::<PV>(pos, ply, alpha, beta, eval_state);
     }
 
     ////////////////////////////////////////////////////////////////////////
@@ -89,7 +92,23 @@ impl<'a> SearchRunner<'a> {
     // Don't return early when in the root node, because we won't have a PV
     // move to play.
     if !in_root && (pos.board.is_rule_draw() || pos.is_repetition()) {
-      return eval_state.draw_score(ply, self.nodes.local());
+      return eval_state.draw_score(self.contempt, ply, self.nodes.local());
+    }
+
+    // Upcoming repetition?
+    // If we can force a repetition, the position is worth at least a draw
+    // to us, so we can raise alpha to the draw score.
+    if !in_root {
+      let draw_score =
+        eval_state.draw_score(self.contempt, ply, self.nodes.local());
+
+      if alpha < draw_score && pos.has_upcoming_repetition(ply) {
+        alpha = draw_score;
+
+        if alpha >= beta {
+          return alpha;
+        }
+      }
     }
 
     ////////////////////////////////////////////////////////////////////////
@@ -104,7 +123,7 @@ impl<'a> SearchRunner<'a> {
     ////////////////////////////////////////////////////////////////////////
 
     let tt_entry = if excluded.is_none() {
-      self.tt.probe(pos.hash)
+      self.tt.probe(pos.hash())
     } else {
       None
     };
@@ -140,7 +159,7 @@ impl<'a> SearchRunner<'a> {
       let eval = eval_state.total(&pos.board, &mut NullTracer);
 
       self.tt.insert(TTEntry::new(
-        pos.hash,
+        pos.hash(),
         Move::NULL,
         Score::NO_SCORE,
         eval,
@@ -157,7 +176,7 @@ impl<'a> SearchRunner<'a> {
     let static_eval = if excluded.is_some() {
       self.stack[ply].eval
     } else {
-      raw_eval + self.history.eval_correction(pos, ply)
+      raw_eval + self.history.eval_correction(pos, ply) + self.eval_noise(pos)
     };
 
     // Store the eval in the search stack
@@ -250,9 +269,10 @@ impl<'a> SearchRunner<'a> {
       reduction = reduction.min(depth);
 
       self.history.push_null_mv();
+      let mut next_position = ChildPosition::null(pos, self.make_unmake);
 
       let score = -self.zero_window(
-        &pos.play_null_move(),
+        &mut next_position,
         ply + 1,
         depth - reduction,
         -beta + 1,
@@ -262,6 +282,7 @@ impl<'a> SearchRunner<'a> {
         !cutnode,
       );
 
+      drop(next_position);
       self.history.pop_mv();
 
       if score >= beta {
@@ -290,7 +311,7 @@ impl<'a> SearchRunner<'a> {
     //
     ////////////////////////////////////////////////////////////////////////
 
-    let mut legal_moves = MovePicker::new::<ALL_MOVES>(&pos, tt_move, ply);
+    let mut legal_moves = MovePicker::new::<ALL_MOVES>(tt_move, ply);
 
     ////////////////////////////////////////////////////////////////////////
     //
@@ -329,10 +350,9 @@ impl<'a> SearchRunner<'a> {
     let mut best_move = tt_move;
     let mut best_score = Score::MINUS_INF;
     let mut node_type = NodeType::Upper;
-    let mut alpha = alpha;
     let mut local_pv = PVTable::new();
 
-    while let Some(mv) = legal_moves.next(&self.history) {
+    while let Some(mv) = legal_moves.next(pos, &self.history) {
       if Some(mv) == excluded {
         continue;
       }
@@ -478,7 +498,7 @@ impl<'a> SearchRunner<'a> {
         // Do a verification search with the candidate move excluded.
         self.stack[ply].excluded = se_candidate;
         let value = self.zero_window(
-          &pos,
+          pos,
           ply,
           se_depth,
           se_beta,
@@ -553,6 +573,24 @@ impl<'a> SearchRunner<'a> {
         }
       }
 
+      ////////////////////////////////////////////////////////////////////
+      //
+      // Report the current root move
+      //
+      // Let the observer know which root move we're about to search, so
+      // GUIs can show the search's progress.
+      //
+      ////////////////////////////////////////////////////////////////////
+
+      if in_root {
+        let depth = self.depth;
+        self.notify(|| SearchEvent::CurrMove {
+          depth,
+          mv,
+          number: move_count + 1,
+        });
+      }
+
       ////////////////////////////////////////////////////////////////////
       //
       // Late move reductions
@@ -571,7 +609,7 @@ impl<'a> SearchRunner<'a> {
       // time
       self.tt.prefetch(pos.approx_hash_after(mv));
 
-      let next_position = pos.play_move(mv);
+      let mut next_position = ChildPosition::new(pos, mv, self.make_unmake);
 
       let next_eval = eval_state.play_move(
         self.history.indices[ply],
@@ -583,7 +621,7 @@ impl<'a> SearchRunner<'a> {
       // PV Move
       if move_count == 0 {
         score = -self.negamax::<PV>(
-          &next_position,
+          &mut next_position,
           ply + 1,
           (depth as i16 + extension - 1) as usize,
           -beta,
@@ -653,7 +691,7 @@ impl<'a> SearchRunner<'a> {
 
         // Search with zero-window at reduced depth
         score = -self.zero_window(
-          &next_position,
+          &mut next_position,
           ply + 1,
           reduced,
           -alpha,
@@ -678,7 +716,7 @@ impl<'a> SearchRunner<'a> {
           }
 
           score = -self.zero_window(
-            &next_position,
+            &mut next_position,
             ply + 1,
             new_depth.max(0) as usize,
             -alpha,
@@ -695,7 +733,8 @@ impl<'a> SearchRunner<'a> {
               HistoryScore::bonus(new_depth as usize)
             };
 
-            self.history.add_hist_bonus(mv, &pos, bonus);
+            next_position
+              .with_parent(|pos| self.history.add_hist_bonus(mv, pos, bonus));
           }
         }
 
@@ -703,7 +742,7 @@ impl<'a> SearchRunner<'a> {
         // full-window
         if score > alpha && score < beta {
           score = -self.negamax::<PV>(
-            &next_position,
+            &mut next_position,
             ply + 1,
             new_depth.max(0) as usize,
             -beta,
@@ -716,6 +755,7 @@ impl<'a> SearchRunner<'a> {
         }
       }
 
+      drop(next_position);
       self.history.pop_mv();
       move_count += 1;
 
@@ -774,7 +814,7 @@ impl<'a> SearchRunner<'a> {
 
     // Stalemate?
     if move_count == 0 && !in_check {
-      return eval_state.draw_score(ply, self.nodes.local());
+      return eval_state.draw_score(self.contempt, ply, self.nodes.local());
     }
 
     ////////////////////////////////////////////////////////////////////////
@@ -863,7 +903,7 @@ impl<'a> SearchRunner<'a> {
       ///////////////////////////////////////////////////////////////////
 
       self.tt.insert(TTEntry::new(
-        pos.hash,
+        pos.hash(),
         best_move.unwrap_or(Move::NULL),
         best_score,
         raw_eval,
@@ -0,0 +1,194 @@
+//! Reporting on the progress of a search
+//!
+//! Rather than printing its progress directly, the search emits a stream of
+//! `SearchEvent`s to a `SearchObserver`. It's up to the observer to decide
+//! what to do with them: print them as UCI `info` messages, pretty-print them
+//! to the terminal, forward them over a channel, or ignore them altogether.
+//!
+//! Only the main search thread reports on its progress.
+
+use super::SearchReport;
+use crate::evaluate::Score;
+use crate::transpositions::NodeType;
+use chess::board::Board;
+use chess::movegen::moves::Move;
+use std::io::IsTerminal;
+use std::sync::mpsc::Sender;
+use uci::search_info::SearchInfo;
+use uci::wdl::WDL_MODEL;
+
+/// The events a search reports to its observer
+#[derive(Debug, Clone)]
+pub enum SearchEvent {
+  /// An iteration of the iterative deepening loop completed
+  Iteration { board: Board, report: SearchReport },
+
+  /// The aspiration window search failed high (`NodeType::Lower`) or low
+  /// (`NodeType::Upper`), and will be re-searched with a wider window.
+  Bound {
+    depth: usize,
+    score: Score,
+    bound: NodeType,
+  },
+
+  /// Started searching the `number`-th move in the root node
+  CurrMove {
+    depth: usize,
+    mv: Move,
+    number: usize,
+  },
+
+  /// The search finished, and this is the move it came up with
+  BestMove { board: Board, report: SearchReport },
+}
+
+/// Anything that wants to be kept up to date on the progress of a search
+pub trait SearchObserver: Send {
+  fn notify(&mut self, event: SearchEvent);
+}
+
+/// Forward all events over a channel, e.g., to a different thread
+impl SearchObserver for Sender<SearchEvent> {
+  fn notify(&mut self, event: SearchEvent) {
+    // If the receiver hung up, nobody's listening anymore, and that's fine.
+    let _ = self.send(event);
+  }
+}
+
+/// The observer used unless told otherwise: pretty-print the search progress
+/// when writing to a terminal, and print UCI `info` messages otherwise.
+pub fn default_observer(show_wdl: bool) -> Box<dyn SearchObserver> {
+  if std::io::stdout().is_terminal() {
+    Box::new(PrettyObserver)
+  } else {
+    Box::new(UciObserver { show_wdl })
+  }
+}
+
+/// Ignore all events
+pub struct SilentObserver;
+
+impl SearchObserver for SilentObserver {
+  fn notify(&mut self, _: SearchEvent) {}
+}
+
+/// Print completed iterations as UCI `info` messages
+pub struct UciObserver {
+  /// Whether to include WDL stats, as requested through `UCI_ShowWDL`
+  pub show_wdl: bool,
+}
+
+impl SearchObserver for UciObserver {
+  fn notify(&mut self, event: SearchEvent) {
+    let SearchEvent::Iteration { board, report } = event else {
+      return;
+    };
+
+    let wdl_params = WDL_MODEL.params(&board);
+    let mut info = SearchInfo::from(&report);
+
+    if self.show_wdl {
+      info = info.with_wdl(wdl_params);
+    }
+
+    // Unless we're in wdl mode, we print the eval rescaled according to the
+    // WDL model. In wdl mode, we output the score in internal, unscaled,
+    // values.
+    if !cfg!(feature = "wdl") {
+      println!("info {}", info.to_uci(wdl_params));
+    } else {
+      println!("info {info}");
+    }
+  }
+}
+
+/// Pretty-print completed iterations, including WDL stats, for humans
+/// watching the search in a terminal
+pub struct PrettyObserver;
+
+impl SearchObserver for PrettyObserver {
+  fn notify(&mut self, event: SearchEvent) {
+    let SearchEvent::Iteration { board, report } = event else {
+      return;
+    };
+
+    let wdl_params = WDL_MODEL.params(&board);
+    let info = SearchInfo::from(&report);
+    println!("{}", info.to_pretty(&board, wdl_params));
+  }
+}
+
+/// Print completed iterations as XBoard thinking output
+pub struct XBoardObserver;
+
+impl SearchObserver for XBoardObserver {
+  fn notify(&mut self, event: SearchEvent) {
+    let SearchEvent::Iteration { board, report } = event else {
+      return;
+    };
+
+    let wdl_params = WDL_MODEL.params(&board);
+    let info = SearchInfo::from(&report);
+    println!("{}", info.to_xboard(wdl_params));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::position::Position;
+  use crate::search::NodeCounter;
+  use crate::search::SearchRunner;
+  use crate::time_control::TimeController;
+  use crate::transpositions::TTable;
+  use std::sync::atomic::AtomicU32;
+  use uci::time_control::TimeControl;
+
+  #[test]
+  fn events_are_sent_over_channel() {
+    let board = Board::default();
+    let tt = TTable::with_capacity(4);
+    let global_nodes = AtomicU32::new(0);
+    let mut runner = SearchRunner::new(0, &tt, NodeCounter::new(&global_nodes));
+    let (tx, rx) = std::sync::mpsc::channel();
+    runner.observer = Box::new(tx);
+
+    let (tc, _) = TimeController::new(TimeControl::Depth(4), board.current);
+    let report = runner.search::<true>(Position::new(board), tc);
+    drop(runner);
+
+    let events = rx.iter().collect::<Vec<_>>();
+
+    let iterations = events
+      .iter()
+      .filter(|event| matches!(event, SearchEvent::Iteration { .. }))
+      .count();
+
+    assert_eq!(iterations, 4);
+    assert!(events
+      .iter()
+      .any(|event| matches!(event, SearchEvent::CurrMove { number: 1, .. })));
+
+    let Some(SearchEvent::BestMove { report: best, .. }) = events.last() else {
+      panic!("The last event should be the best move");
+    };
+
+    assert_eq!(best.pv, report.pv);
+  }
+
+  #[test]
+  fn nothing_is_reported_without_debug() {
+    let board = Board::default();
+    let tt = TTable::with_capacity(4);
+    let global_nodes = AtomicU32::new(0);
+    let mut runner = SearchRunner::new(0, &tt, NodeCounter::new(&global_nodes));
+    let (tx, rx) = std::sync::mpsc::channel();
+    runner.observer = Box::new(tx);
+
+    let (tc, _) = TimeController::new(TimeControl::Depth(2), board.current);
+    runner.search::<false>(Position::new(board), tc);
+    drop(runner);
+
+    assert_eq!(rx.iter().count(), 0);
+  }
+}
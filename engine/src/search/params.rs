@@ -313,6 +313,7 @@ pub mod tunable_params {
 }
 
 pub const DEFAULT_TT_SIZE: usize = 64;
+pub const MAX_TT_SIZE: usize = 1 << 20;
 pub const MAX_DEPTH: usize = 128;
 pub const MAX_KILLERS: usize = 2;
 
@@ -1,5 +1,6 @@
 use chess::movegen::legal_moves::All;
 use chess::movegen::moves::Move;
+use chess::movegen::sliders::Sliders;
 
 use super::params::*;
 use super::SearchRunner;
@@ -8,6 +9,7 @@ use crate::evaluate::Eval;
 use crate::evaluate::Score;
 use crate::evaluate::ScoreExt;
 use crate::move_picker::MovePicker;
+use crate::position::ChildPosition;
 use crate::position::Position;
 use crate::transpositions::NodeType;
 use crate::transpositions::TTEntry;
@@ -25,7 +27,7 @@ impl<'a> SearchRunner<'a> {
   /// we perform less pruning and hacks.
   pub fn quiescence_search<const PV: bool>(
     &mut self,
-    pos: &Position,
+    pos: &mut Position<impl Sliders>,
     ply: usize,
     mut alpha: Score,
     beta: Score,
@@ -40,11 +42,11 @@ impl<'a> SearchRunner<'a> {
     self.seldepth = self.seldepth.max(ply);
 
     if pos.board.is_rule_draw() || pos.is_repetition() {
-      return eval_state.draw_score(ply, self.nodes.local());
+      return eval_state.draw_score(self.contempt, ply, self.nodes.local());
     }
 
     let in_check = pos.board.in_check();
-    let tt_entry = self.tt.probe(pos.hash);
+    let tt_entry = self.tt.probe(pos.hash());
     let ttpv = PV || tt_entry.is_some_and(|entry| entry.get_ttpv());
 
     ////////////////////////////////////////////////////////////////////////
@@ -65,7 +67,7 @@ impl<'a> SearchRunner<'a> {
       let eval = eval_state.total(&pos.board, &mut NullTracer);
 
       self.tt.insert(TTEntry::new(
-        pos.hash,
+        pos.hash(),
         Move::NULL,
         Score::NO_SCORE,
         eval,
@@ -82,7 +84,7 @@ impl<'a> SearchRunner<'a> {
     let static_eval = if in_check {
       -Score::MATE + ply as Score
     } else {
-      raw_eval + self.history.eval_correction(pos, ply)
+      raw_eval + self.history.eval_correction(pos, ply) + self.eval_noise(pos)
     };
 
     if ply >= MAX_DEPTH {
@@ -121,14 +123,14 @@ impl<'a> SearchRunner<'a> {
 
     let tt_move = tt_entry.and_then(|entry| entry.get_move());
 
-    let mut tacticals = MovePicker::new::<TACTICALS>(&pos, tt_move, ply);
+    let mut tacticals = MovePicker::new::<TACTICALS>(tt_move, ply);
 
     let mut best_move = tt_move;
     let mut best_score = static_eval;
     let mut node_type = NodeType::Upper;
     let mut move_count = 0;
 
-    while let Some(mv) = tacticals.next(&self.history) {
+    while let Some(mv) = tacticals.next(pos, &self.history) {
       // Late move pruning
       if !in_check && move_count >= 4 {
         break;
@@ -137,7 +139,7 @@ impl<'a> SearchRunner<'a> {
       self.history.push_mv(mv, &pos.board);
       self.tt.prefetch(pos.approx_hash_after(mv));
 
-      let next_position = pos.play_move(mv);
+      let mut next_position = ChildPosition::new(pos, mv, self.make_unmake);
 
       let next_eval = eval_state.play_move(
         self.history.indices[ply],
@@ -147,13 +149,14 @@ impl<'a> SearchRunner<'a> {
       );
 
       let score = -self.quiescence_search::<PV>(
-        &next_position,
+        &mut next_position,
         ply + 1,
         -beta,
         -alpha,
         next_eval,
       );
 
+      drop(next_position);
       self.history.pop_mv();
       move_count += 1;
 
@@ -196,7 +199,7 @@ impl<'a> SearchRunner<'a> {
 
     // Store in the TT
     self.tt.insert(TTEntry::new(
-      pos.hash,
+      pos.hash(),
       best_move.unwrap_or(Move::NULL),
       best_score,
       raw_eval,
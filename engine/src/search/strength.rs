@@ -0,0 +1,511 @@
+//! Strength limiting
+//!
+//! At full strength, Simbelmyne is a pretty miserable sparring partner for
+//! most humans. In order to play at a lower level, we handicap the engine in
+//! three ways:
+//!
+//! 1. We cap the number of nodes it's allowed to search per move.
+//! 2. We add noise to the static evaluation, so the engine misjudges
+//!    positions every now and then.
+//! 3. Rather than always playing the best move, we pick a move among the
+//!    near-best root moves, with a probability that depends on how much worse
+//!    it is than the best move, and on a "temperature".
+//!
+//! The strength can be set either through a `Skill Level` (0 - 20) or as an
+//! Elo rating (through `UCI_LimitStrength` and `UCI_Elo`). The Elo ratings are
+//! mapped onto skill levels through a table of anchors that hasn't been
+//! calibrated yet, so `UCI_Elo` is only a rough indication of how strong the
+//! engine plays, not a rating it's been measured at.
+//!
+//! All of the randomness is derived from a user-provided seed, so a game
+//! played at a given strength is fully reproducible (given the same seed,
+//! positions and node budgets). That makes it possible to calibrate the Elo
+//! anchors below by running gauntlets against rated opponents, and get the
+//! same games out when re-running them.
+
+use super::SearchReport;
+use super::SearchRunner;
+use crate::evaluate::tuner::NullTracer;
+use crate::evaluate::Eval;
+use crate::evaluate::Score;
+use crate::evaluate::ScoreExt;
+use crate::history_tables::pv::PVTable;
+use crate::position::ChildPosition;
+use crate::position::Position;
+use crate::search::contempt::ENGINE_ELO;
+use crate::zobrist::ZHash;
+use chess::movegen::legal_moves::All;
+use chess::movegen::moves::Move;
+use chess::movegen::sliders::Sliders;
+
+/// The lowest rating we advertise through `UCI_Elo`
+pub const MIN_ELO: u32 = 1320;
+
+/// The highest rating we advertise through `UCI_Elo`. This corresponds to
+/// playing at full strength.
+pub const MAX_ELO: u32 = ENGINE_ELO;
+
+/// The default rating when limiting strength
+pub const DEFAULT_ELO: u32 = 1500;
+
+/// The skill level that corresponds to playing at full strength
+pub const MAX_SKILL_LEVEL: u8 = 20;
+
+/// Anchors that map a rating onto a (fractional) skill level. Ratings in
+/// between two anchors are interpolated linearly.
+///
+/// Only the top anchor is backed by anything: the rating lists' estimate of
+/// the engine at full strength. The others are guesses that haven't been
+/// checked against opponents of known strength, so the ratings they advertise
+/// could be off by hundreds of points. Once they've been measured with seeded
+/// gauntlets, the method, opponents and results belong here, next to the
+/// table.
+const ELO_ANCHORS: [(u32, f64); 6] = [
+  (MIN_ELO, 0.0),
+  (1600, 3.0),
+  (2000, 7.0),
+  (2400, 11.0),
+  (2800, 15.5),
+  (MAX_ELO, 20.0),
+];
+
+/// The maximum depth at which we score the root moves when picking a
+/// handicapped move.
+const SCORING_DEPTH: usize = 4;
+
+/// The share (1/N) of the node budget that is set aside for scoring the root
+/// moves when picking a handicapped move.
+const SCORING_SHARE: u32 = 4;
+
+/// The strength settings, as configured through the UCI options.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Strength {
+  /// Whether to limit the strength according to `elo`
+  pub limit_strength: bool,
+
+  /// The requested rating, when limiting strength
+  pub elo: u32,
+
+  /// The requested skill level, between 0 and 20. Ignored when
+  /// `limit_strength` is set.
+  pub skill_level: u8,
+
+  /// The seed used for all random decisions
+  pub seed: u64,
+}
+
+impl Strength {
+  /// Return the (fractional) skill level between 0 and 20
+  fn skill(&self) -> f64 {
+    if !self.limit_strength {
+      return self.skill_level.min(MAX_SKILL_LEVEL) as f64;
+    }
+
+    let elo = self.elo.clamp(MIN_ELO, MAX_ELO);
+
+    ELO_ANCHORS
+      .windows(2)
+      .find(|anchors| elo <= anchors[1].0)
+      .map(|anchors| {
+        let (lo_elo, lo_skill) = anchors[0];
+        let (hi_elo, hi_skill) = anchors[1];
+        let t = (elo - lo_elo) as f64 / (hi_elo - lo_elo) as f64;
+        lo_skill + t * (hi_skill - lo_skill)
+      })
+      .unwrap_or(MAX_SKILL_LEVEL as f64)
+  }
+
+  /// Return the rating that corresponds to the configured strength, according
+  /// to the (uncalibrated) anchors.
+  pub fn elo(&self) -> u32 {
+    let skill = self.skill();
+
+    ELO_ANCHORS
+      .windows(2)
+      .find(|anchors| skill <= anchors[1].1)
+      .map(|anchors| {
+        let (lo_elo, lo_skill) = anchors[0];
+        let (hi_elo, hi_skill) = anchors[1];
+        let t = (skill - lo_skill) / (hi_skill - lo_skill);
+        lo_elo + (t * (hi_elo - lo_elo) as f64).round() as u32
+      })
+      .unwrap_or(MAX_ELO)
+  }
+
+  /// Return the handicap to apply to the search, if any
+  pub fn handicap(&self) -> Option<Handicap> {
+    let skill = self.skill();
+
+    if skill >= MAX_SKILL_LEVEL as f64 {
+      return None;
+    }
+
+    // How far below full strength we are, as a fraction between 0 and 1.
+    let weakness = 1.0 - skill / MAX_SKILL_LEVEL as f64;
+
+    Some(Handicap {
+      nodes: (64.0 * f64::powf(2.0, 0.75 * skill)) as u32,
+      noise: (200.0 * weakness * weakness) as Score,
+      temperature: 100.0 * weakness,
+      margin: (300.0 * weakness) as Score,
+      seed: self.seed,
+    })
+  }
+}
+
+impl Default for Strength {
+  fn default() -> Self {
+    Self {
+      limit_strength: false,
+      elo: DEFAULT_ELO,
+      skill_level: MAX_SKILL_LEVEL,
+      seed: 0,
+    }
+  }
+}
+
+/// The concrete handicaps applied to the search
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Handicap {
+  /// The maximum number of nodes to search per move
+  pub nodes: u32,
+
+  /// The maximum amount of noise (in centipawns) to add to the static eval
+  pub noise: Score,
+
+  /// The temperature (in centipawns) used when picking a root move. Higher
+  /// temperatures make worse moves more likely.
+  pub temperature: f64,
+
+  /// Only moves that score within this margin of the best move are
+  /// considered.
+  pub margin: Score,
+
+  /// The seed used for all random decisions
+  pub seed: u64,
+}
+
+impl Handicap {
+  /// The number of nodes the regular search is allowed to use. The rest of
+  /// the node budget is used for scoring the root moves.
+  pub fn search_nodes(&self) -> u32 {
+    self.nodes - self.nodes / SCORING_SHARE
+  }
+
+  /// Return the eval noise for a given position.
+  ///
+  /// The noise is derived from the position's hash, so the same position
+  /// always gets the same noise. Otherwise, we'd get wildly inconsistent
+  /// search results for transpositions.
+  pub fn eval_noise(&self, hash: ZHash) -> Score {
+    if self.noise == 0 {
+      return 0;
+    }
+
+    let range = 2 * self.noise as u64 + 1;
+    (Rng::new(hash.0 ^ self.seed).next_u64() % range) as Score - self.noise
+  }
+
+  /// Pick a move among the scored candidates, favoring moves with higher
+  /// scores.
+  pub fn pick_move(
+    &self,
+    candidates: &[(Move, Score)],
+    rng: &mut Rng,
+  ) -> usize {
+    let best = candidates
+      .iter()
+      .map(|&(_, score)| score)
+      .max()
+      .unwrap_or_default();
+
+    let weights = candidates
+      .iter()
+      .map(|&(_, score)| {
+        if score + self.margin < best {
+          0.0
+        } else {
+          f64::exp((score - best) as f64 / self.temperature.max(1.0))
+        }
+      })
+      .collect::<Vec<_>>();
+
+    let total: f64 = weights.iter().sum();
+    let mut choice = rng.next_f64() * total;
+
+    for (idx, weight) in weights.iter().enumerate() {
+      if choice < *weight {
+        return idx;
+      }
+
+      choice -= weight;
+    }
+
+    // Fall back to the best candidate when rounding errors made us run off
+    // the end
+    weights
+      .iter()
+      .enumerate()
+      .max_by(|(_, w1), (_, w2)| w1.total_cmp(w2))
+      .map(|(idx, _)| idx)
+      .unwrap_or_default()
+  }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Handicapped move selection
+//
+////////////////////////////////////////////////////////////////////////////////
+
+impl<'a> SearchRunner<'a> {
+  /// Return the eval noise for the position, if we're playing with a
+  /// handicap.
+  pub fn eval_noise(&self, pos: &Position<impl Sliders>) -> Score {
+    self
+      .handicap
+      .map_or(0, |handicap| handicap.eval_noise(pos.hash()))
+  }
+
+  /// Score all of the root moves with a shallow, full-window search and
+  /// pick one of the near-best moves according to the handicap.
+  ///
+  /// The scoring searches run under the same time controller as the regular
+  /// search, so they respect the time limit and `stop`, and their nodes count
+  /// towards the handicap's node budget. If we run out before all of the
+  /// moves are scored, we pick among the moves that were scored.
+  ///
+  /// Returns an updated search report with the chosen move and its score.
+  pub fn pick_handicapped_move(
+    &mut self,
+    pos: &mut Position<impl Sliders>,
+    report: &SearchReport,
+    handicap: Handicap,
+  ) -> SearchReport {
+    let Some(&best_move) = report.pv.first() else {
+      return report.clone();
+    };
+
+    // Don't throw away forced mates
+    if report.score.is_mate() {
+      return report.clone();
+    }
+
+    // The regular search stopped short of the full node budget, to leave
+    // some nodes for scoring the moves.
+    self.tc.limit_nodes(handicap.nodes);
+    self.aborted = false;
+
+    let depth = (report.depth as usize).clamp(1, SCORING_DEPTH);
+    let eval_state = Eval::new(&pos.board, &mut NullTracer);
+    let mut candidates = Vec::new();
+    let mut pvs = Vec::new();
+
+    // Score the best move first, so it's scored even if we don't get to the
+    // other moves. All the moves are scored at the same depth, so their
+    // scores can be compared.
+    let mut moves = pos.board.legal_moves::<All>();
+    moves.sort_by_key(|&mv| mv != best_move);
+
+    for mv in moves {
+      let mut pv = PVTable::new();
+      self.history.push_mv(mv, &pos.board);
+      let mut next_position = ChildPosition::new(pos, mv, self.make_unmake);
+      let next_eval = eval_state.play_move(
+        self.history.indices[0],
+        &next_position.board,
+        next_position.kp_hash,
+        &mut self.kp_cache,
+      );
+
+      let score = -self.negamax::<true>(
+        &mut next_position,
+        1,
+        depth - 1,
+        Score::MINUS_INF,
+        Score::PLUS_INF,
+        &mut pv,
+        next_eval,
+        false,
+        false,
+      );
+
+      drop(next_position);
+      self.history.pop_mv();
+
+      // An aborted search doesn't produce a usable score
+      if self.aborted {
+        break;
+      }
+
+      let mut line = vec![mv];
+      line.extend_from_slice(pv.moves());
+      candidates.push((mv, score));
+      pvs.push(line);
+    }
+
+    if candidates.is_empty() {
+      return report.clone();
+    }
+
+    let mut rng = Rng::new(handicap.seed ^ pos.hash().0);
+    let choice = handicap.pick_move(&candidates, &mut rng);
+
+    // Keep the full-depth report when we end up playing the best move anyway
+    if candidates[choice].0 == best_move {
+      return report.clone();
+    }
+
+    SearchReport {
+      score: candidates[choice].1,
+      pv: pvs.swap_remove(choice),
+      ..report.clone()
+    }
+  }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Random number generation
+//
+////////////////////////////////////////////////////////////////////////////////
+
+/// A small, seedable, pseudo-random number generator (SplitMix64)
+#[derive(Debug, Copy, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+  pub fn new(seed: u64) -> Self {
+    Self(seed)
+  }
+
+  /// Return the next pseudo-random u64
+  pub fn next_u64(&mut self) -> u64 {
+    self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = self.0;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+  }
+
+  /// Return the next pseudo-random float in the range [0, 1)
+  pub fn next_f64(&mut self) -> f64 {
+    (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn full_strength_has_no_handicap() {
+    assert!(Strength::default().handicap().is_none());
+
+    let strength = Strength {
+      limit_strength: true,
+      elo: MAX_ELO,
+      ..Strength::default()
+    };
+
+    assert!(strength.handicap().is_none());
+  }
+
+  #[test]
+  fn lower_elo_means_bigger_handicap() {
+    let handicap = |elo| {
+      Strength {
+        limit_strength: true,
+        elo,
+        ..Strength::default()
+      }
+      .handicap()
+      .unwrap()
+    };
+
+    let weak = handicap(1400);
+    let strong = handicap(2600);
+
+    assert!(weak.nodes < strong.nodes);
+    assert!(weak.noise > strong.noise);
+    assert!(weak.temperature > strong.temperature);
+  }
+
+  #[test]
+  fn elo_round_trips() {
+    for elo in [MIN_ELO, 1500, 2000, 2345, 3000] {
+      let strength = Strength {
+        limit_strength: true,
+        elo,
+        ..Strength::default()
+      };
+
+      assert!(strength.elo().abs_diff(elo) <= 1);
+    }
+  }
+
+  #[test]
+  fn handicapped_search_respects_node_budget() {
+    use crate::search::NodeCounter;
+    use crate::time_control::TimeController;
+    use crate::transpositions::TTable;
+    use chess::board::Board;
+    use std::sync::atomic::AtomicU32;
+    use uci::time_control::TimeControl;
+
+    let board = Board::default();
+    let tt = TTable::with_capacity(4);
+    let global_nodes = AtomicU32::new(0);
+    let nodes = NodeCounter::new(&global_nodes);
+    let mut runner = SearchRunner::new(0, &tt, nodes);
+    let (tc, _) = TimeController::new(TimeControl::Infinite, board.current);
+
+    let handicap = Strength {
+      skill_level: 10,
+      ..Strength::default()
+    }
+    .handicap()
+    .unwrap();
+
+    runner.handicap = Some(handicap);
+    runner.search::<false>(Position::new(board), tc);
+
+    // Allow a single node of overshoot, since the node that hits the limit
+    // is counted before the search bails out.
+    assert!(runner.nodes.local() <= handicap.nodes + 1);
+  }
+
+  #[test]
+  fn handicapped_search_is_reproducible() {
+    use crate::search::NodeCounter;
+    use crate::time_control::TimeController;
+    use crate::transpositions::TTable;
+    use chess::board::Board;
+    use std::sync::atomic::AtomicU32;
+    use uci::time_control::TimeControl;
+
+    let board: Board =
+      "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3"
+        .parse()
+        .unwrap();
+
+    let search = || {
+      let tt = TTable::with_capacity(4);
+      let global_nodes = AtomicU32::new(0);
+      let nodes = NodeCounter::new(&global_nodes);
+      let mut runner = SearchRunner::new(0, &tt, nodes);
+      let (tc, _) = TimeController::new(TimeControl::Infinite, board.current);
+
+      runner.handicap = Strength {
+        skill_level: 3,
+        seed: 1234,
+        ..Strength::default()
+      }
+      .handicap();
+
+      runner.search::<false>(Position::new(board), tc).pv[0]
+    };
+
+    assert_eq!(search(), search());
+  }
+}
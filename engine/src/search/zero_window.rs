@@ -3,11 +3,12 @@ use crate::evaluate::Eval;
 use crate::evaluate::Score;
 use crate::history_tables::pv::PVTable;
 use crate::position::Position;
+use chess::movegen::sliders::Sliders;
 
 impl<'a> SearchRunner<'a> {
   pub fn zero_window(
     &mut self,
-    pos: &Position,
+    pos: &mut Position<impl Sliders>,
     ply: usize,
     depth: usize,
     value: Score,
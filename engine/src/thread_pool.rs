@@ -0,0 +1,482 @@
+//! A pool of search threads, for Lazy SMP
+//!
+//! Lazy SMP is about the simplest way of parallelizing a search: every thread
+//! searches the same position, and they only cooperate through the shared
+//! transposition table. Because the threads keep racing each other to fill
+//! the table, they end up exploring slightly different parts of the tree, and
+//! the combined result is better than what any single thread would find.
+//!
+//! The helper threads are spawned once, and parked on a channel in between
+//! searches, so that starting a search doesn't pay for spawning threads, and
+//! the helpers get to keep their history tables between moves.
+//!
+//! Left to their own devices, all threads would iterate through the same
+//! depths in lockstep. To spread them out, each helper skips a different
+//! pattern of depths. The threads also share which depths they're currently
+//! searching, and the deepest iteration completed so far, so helpers don't
+//! pile onto iterations another thread is already working on, or has already
+//! finished.
+//!
+//! Once the search is over, the final move is decided by a vote: every thread
+//! votes for its best move, weighted by how deep it got and how good it
+//! thought the move was. That way, a helper that happened to complete a
+//! deeper iteration than the main thread can still have its say.
+
+use crate::evaluate::Score;
+use crate::evaluate::ScoreExt;
+use crate::position::Position;
+use crate::search::observer::SearchEvent;
+use crate::search::observer::SearchObserver;
+use crate::search::observer::SilentObserver;
+use crate::search::params::MAX_DEPTH;
+use crate::search::strength::Handicap;
+use crate::search::NodeCounter;
+use crate::search::SearchReport;
+use crate::search::SearchRunner;
+use crate::time_control::TimeController;
+use crate::transpositions::TTable;
+use chess::movegen::moves::Move;
+use chess::movegen::sliders::Magics;
+use chess::movegen::sliders::Sliders;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::Scope;
+
+/// The sizes of the blocks of depths that helper threads alternate between
+/// searching and skipping, indexed by helper.
+const SKIP_SIZE: [usize; 20] =
+  [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+
+/// The offsets of the skip blocks, so helpers with the same block size don't
+/// all skip the same depths.
+const SKIP_PHASE: [usize; 20] =
+  [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+
+/// Search progress that's shared between the threads of a search
+#[derive(Debug)]
+pub struct SharedSearch {
+  /// The deepest iteration any thread has completed, packed together with the
+  /// best move it found, as `depth << 16 | move`.
+  deepest: AtomicU32,
+
+  /// The number of threads currently searching each depth
+  searching: [AtomicU32; MAX_DEPTH + 1],
+}
+
+impl Default for SharedSearch {
+  fn default() -> Self {
+    Self {
+      deepest: AtomicU32::new(0),
+      searching: std::array::from_fn(|_| AtomicU32::new(0)),
+    }
+  }
+}
+
+impl SharedSearch {
+  /// Forget about the previous search
+  pub fn clear(&self) {
+    self.deepest.store(0, Ordering::Relaxed);
+
+    for searching in &self.searching {
+      searching.store(0, Ordering::Relaxed);
+    }
+  }
+
+  /// Let the other threads know we started searching an iteration
+  pub fn start(&self, depth: usize) {
+    self.searching[depth].fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// Let the other threads know we're done with an iteration, whether it was
+  /// completed or aborted.
+  pub fn finish(&self, depth: usize) {
+    self.searching[depth].fetch_sub(1, Ordering::Relaxed);
+  }
+
+  /// Share the best move of an iteration that was just completed
+  pub fn publish(&self, depth: usize, best_move: Move) {
+    let packed = (depth as u32) << 16 | best_move.to_u16() as u32;
+    self.deepest.fetch_max(packed, Ordering::Relaxed);
+  }
+
+  /// The deepest iteration completed by any of the threads, and its best
+  /// move, if any.
+  pub fn deepest(&self) -> Option<(usize, Move)> {
+    let packed = self.deepest.load(Ordering::Relaxed);
+    let depth = (packed >> 16) as usize;
+    let best_move = Move::from_u16(packed as u16);
+
+    (depth > 0).then_some((depth, best_move))
+  }
+
+  /// Whether the helper with the given id should skip an iteration, either
+  /// because someone else already completed it or is working on it, or
+  /// because it falls in one of the helper's skip blocks.
+  ///
+  /// The main thread never skips, and neither does anyone skip the first
+  /// iteration, so every thread has a move to vote for.
+  pub fn should_skip(&self, id: usize, depth: usize) -> bool {
+    if id == 0 || depth <= 1 {
+      return false;
+    }
+
+    if self.deepest().is_some_and(|(deepest, _)| depth <= deepest) {
+      return true;
+    }
+
+    if self.searching[depth].load(Ordering::Relaxed) > 0 {
+      return true;
+    }
+
+    let idx = (id - 1) % SKIP_SIZE.len();
+    (depth + SKIP_PHASE[idx]) / SKIP_SIZE[idx] % 2 == 1
+  }
+}
+
+/// A search to be run by one of the helper threads
+struct SearchJob<S: Sliders> {
+  position: Position<S>,
+  tc: TimeController,
+  contempt: Score,
+  handicap: Option<Handicap>,
+}
+
+/// The search threads working on a shared transposition table
+///
+/// The main runner searches on the thread that owns the pool, and is the only
+/// one that reports on its progress. The helpers live on their own threads
+/// for as long as the pool is alive, and stop once it's dropped.
+///
+/// The pool only searches positions that use the slider backend it was
+/// created for, so the threads can be spun up ahead of time.
+pub struct ThreadPool<'a, S: Sliders = Magics> {
+  /// The runner that reports on the search, and decides when to stop
+  main: SearchRunner<'a>,
+
+  /// Channels for handing out work to the helper threads
+  helpers: Vec<Sender<SearchJob<S>>>,
+
+  /// The channel on which the helpers hand in their reports
+  reports: Receiver<SearchReport>,
+
+  /// The search progress shared between all threads
+  shared: Arc<SharedSearch>,
+}
+
+impl<'a, S: Sliders> ThreadPool<'a, S> {
+  /// Spin up a pool with `num_threads` search threads (including the main
+  /// thread) inside the provided scope.
+  pub fn new<'scope>(
+    scope: &'scope Scope<'scope, '_>,
+    num_threads: usize,
+    tt: &'a TTable,
+    global_nodes: &'a AtomicU32,
+    observer: Box<dyn SearchObserver>,
+  ) -> Self
+  where
+    'a: 'scope,
+  {
+    let shared = Arc::new(SharedSearch::default());
+    let mut main = SearchRunner::new(0, tt, NodeCounter::new(global_nodes));
+    main.observer = observer;
+    main.shared = Some(shared.clone());
+
+    let (report_tx, reports) = std::sync::mpsc::channel();
+
+    let helpers = (1..num_threads)
+      .map(|id| {
+        let (job_tx, jobs) = std::sync::mpsc::channel::<SearchJob<S>>();
+        let report_tx = report_tx.clone();
+        let shared = shared.clone();
+
+        scope.spawn(move || {
+          let nodes = NodeCounter::new(global_nodes);
+          let mut runner = SearchRunner::new(id, tt, nodes);
+          runner.shared = Some(shared);
+
+          for mut job in jobs.iter() {
+            runner.contempt = job.contempt;
+            runner.handicap = job.handicap;
+
+            let report =
+              runner.iterative_deepening::<false>(&mut job.position, job.tc);
+
+            // The pool only hangs up when it's being torn down
+            let _ = report_tx.send(report);
+          }
+        });
+
+        job_tx
+      })
+      .collect();
+
+    Self {
+      main,
+      helpers,
+      reports,
+      shared,
+    }
+  }
+
+  /// Replace the observer that gets notified of the search progress
+  pub fn set_observer(&mut self, observer: Box<dyn SearchObserver>) {
+    self.main.observer = observer;
+  }
+
+  /// Take back the observer, so it can be handed to a new pool
+  pub fn take_observer(&mut self) -> Box<dyn SearchObserver> {
+    std::mem::replace(&mut self.main.observer, Box::new(SilentObserver))
+  }
+
+  /// Search the position on all threads, and return the report of the thread
+  /// that won the vote.
+  ///
+  /// The search ends as soon as the main thread is done: the helpers are
+  /// stopped, and their last completed iterations are taken into account.
+  pub fn search(
+    &mut self,
+    mut position: Position<S>,
+    tc: TimeController,
+    contempt: Score,
+    handicap: Option<Handicap>,
+  ) -> SearchReport {
+    self.main.nodes.clear_global();
+    self.shared.clear();
+
+    for helper in &self.helpers {
+      let _ = helper.send(SearchJob {
+        position: position.clone(),
+        tc: tc.clone(),
+        contempt,
+        handicap,
+      });
+    }
+
+    self.main.contempt = contempt;
+    self.main.handicap = handicap;
+
+    let mut pos = position.clone();
+    let mut main_report = self.main.iterative_deepening::<true>(&mut pos, tc);
+
+    // When playing with a handicap, we don't want the helpers to make up for
+    // the handicap, so just go by the main thread. The moves are scored
+    // before stopping the helpers, because they share the main thread's stop
+    // flag.
+    if let Some(handicap) = handicap {
+      main_report =
+        self
+          .main
+          .pick_handicapped_move(&mut position, &main_report, handicap);
+    }
+
+    // Stop the helpers, and collect their results
+    self.main.tc.stop();
+    let mut reports = vec![main_report];
+    reports.extend(self.reports.iter().take(self.helpers.len()));
+
+    let (best, mut report) = match handicap {
+      Some(_) => (0, reports.swap_remove(0)),
+      None => {
+        let best = vote(&reports);
+        (best, reports.swap_remove(best))
+      }
+    };
+
+    // The node count should include everyone's nodes, not just the ones that
+    // were searched by the time the winning iteration completed.
+    report.nodes = self.main.nodes.global();
+
+    // If a helper's result won, let the observer know which line we're
+    // actually going with.
+    if best != 0 {
+      self.main.notify(|| SearchEvent::Iteration {
+        board: position.board.with_sliders(),
+        report: report.clone(),
+      });
+    }
+
+    self.main.notify(|| SearchEvent::BestMove {
+      board: position.board.with_sliders(),
+      report: report.clone(),
+    });
+
+    report
+  }
+}
+
+/// Pick the thread whose best move should be played, and return its index.
+///
+/// Every thread votes for its best move, weighted by the depth it completed
+/// and by how its score compares to that of the other threads. The thread
+/// that found the most popular move gets to report its PV, except when mates
+/// are involved: a thread that found a quicker mate always wins, and a thread
+/// that thinks it's getting mated is never chosen over one that doesn't.
+pub fn vote(reports: &[SearchReport]) -> usize {
+  // Threads that didn't complete a single iteration don't get a vote
+  let candidates = reports
+    .iter()
+    .enumerate()
+    .filter(|(_, report)| !report.pv.is_empty())
+    .collect::<Vec<_>>();
+
+  let Some(min_score) = candidates.iter().map(|(_, r)| r.score).min() else {
+    return 0;
+  };
+
+  let mut votes: Vec<(Move, i64)> = Vec::new();
+
+  for (_, report) in &candidates {
+    let weight = (report.score - min_score + 14) as i64 * report.depth as i64;
+
+    match votes.iter_mut().find(|(mv, _)| *mv == report.pv[0]) {
+      Some((_, count)) => *count += weight,
+      None => votes.push((report.pv[0], weight)),
+    }
+  }
+
+  let votes_for = |report: &SearchReport| {
+    votes
+      .iter()
+      .find(|(mv, _)| *mv == report.pv[0])
+      .map_or(0, |&(_, count)| count)
+  };
+
+  let mut best = candidates[0].0;
+
+  for &(idx, report) in &candidates[1..] {
+    let best_report = &reports[best];
+    let winning = |score: Score| score.is_mate() && score > 0;
+    let losing = |score: Score| score.is_mate() && score < 0;
+
+    let better = if winning(best_report.score) || winning(report.score) {
+      // Prefer the quickest mate
+      report.score > best_report.score
+    } else {
+      !losing(report.score) && votes_for(report) > votes_for(best_report)
+    };
+
+    if better {
+      best = idx;
+    }
+  }
+
+  best
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chess::board::Board;
+  use chess::movegen::legal_moves::All;
+  use std::time::Duration;
+
+  fn report(mv: Move, depth: u8, score: Score) -> SearchReport {
+    SearchReport {
+      depth,
+      seldepth: depth,
+      nodes: 0,
+      duration: Duration::ZERO,
+      score,
+      pv: vec![mv],
+      hashfull: 0,
+    }
+  }
+
+  fn moves() -> (Move, Move) {
+    let moves = Board::default().legal_moves::<All>();
+    (moves[0], moves[1])
+  }
+
+  #[test]
+  fn majority_wins() {
+    let (a, b) = moves();
+    let reports = [report(a, 10, 30), report(b, 10, 25), report(b, 10, 25)];
+
+    assert_eq!(reports[vote(&reports)].pv[0], b);
+  }
+
+  #[test]
+  fn deeper_searches_weigh_more() {
+    let (a, b) = moves();
+    let reports = [report(a, 10, 20), report(b, 14, 20)];
+
+    assert_eq!(vote(&reports), 1);
+  }
+
+  #[test]
+  fn quickest_mate_wins() {
+    let (a, b) = moves();
+    let reports = [
+      report(a, 20, Score::MATE - 5),
+      report(b, 8, Score::MATE - 3),
+      report(b, 8, Score::MATE - 3),
+    ];
+
+    assert_eq!(vote(&reports), 1);
+  }
+
+  #[test]
+  fn helpers_skip_completed_depths() {
+    let (a, _) = moves();
+    let shared = SharedSearch::default();
+    shared.publish(6, a);
+
+    assert_eq!(shared.deepest(), Some((6, a)));
+    assert!(shared.should_skip(1, 6));
+    assert!(!shared.should_skip(0, 6));
+
+    // Helpers skip different depths from each other
+    let skipped = |id| {
+      (7..15)
+        .filter(|&depth| shared.should_skip(id, depth))
+        .collect::<Vec<_>>()
+    };
+
+    assert_ne!(skipped(1), skipped(2));
+    assert_ne!(skipped(3), skipped(4));
+    assert!(skipped(0).is_empty());
+  }
+
+  #[test]
+  fn helpers_never_skip_the_first_iteration() {
+    let shared = SharedSearch::default();
+    shared.start(1);
+
+    for id in 0..SKIP_SIZE.len() + 1 {
+      assert!(!shared.should_skip(id, 1));
+    }
+  }
+
+  #[test]
+  fn helpers_skip_depths_in_progress() {
+    let shared = SharedSearch::default();
+
+    // Find a depth helper 1 would otherwise search
+    let depth = (2..10)
+      .find(|&depth| !shared.should_skip(1, depth))
+      .unwrap();
+
+    shared.start(depth);
+    assert!(shared.should_skip(1, depth));
+    assert!(!shared.should_skip(0, depth));
+
+    // Once the other thread is done (e.g., because it got aborted), the depth
+    // is up for grabs again.
+    shared.finish(depth);
+    assert!(!shared.should_skip(1, depth));
+  }
+
+  #[test]
+  fn empty_reports_are_ignored() {
+    let (a, _) = moves();
+    let mut empty = report(a, 30, 500);
+    empty.pv.clear();
+
+    let reports = [empty, report(a, 5, 10)];
+
+    assert_eq!(vote(&reports), 1);
+  }
+}
@@ -71,6 +71,14 @@ pub struct TimeController {
   /// iteration. (E.g, when the position is forced)
   stop_early: bool,
 
+  /// A hard cap on the number of nodes we're allowed to search, on top of
+  /// the requested time control. (E.g., when limiting the strength)
+  max_nodes: u32,
+
+  /// A hard cap on the search depth, on top of the requested time control.
+  /// (E.g., when the GUI sets both a clock and a maximum depth)
+  max_depth: usize,
+
   /// Correction factor to the soft_time derived from how stable the best
   /// move was across iterations
   bm_stability_factor: f64,
@@ -108,6 +116,8 @@ impl TimeController {
       stop: stop.clone(),
       next_checkup: CHECKUP_WINDOW,
       stop_early: false,
+      max_nodes: u32::MAX,
+      max_depth: usize::MAX,
       bm_stability_factor: 1.0,
       node_frac_factor: 1.0,
       score_stability_factor: 1.0,
@@ -159,6 +169,12 @@ impl TimeController {
   /// we're due for a "checkup" (that is, if we've exceeded the "checkup node
   /// count".)
   pub fn should_continue(&mut self, nodes: u32) -> bool {
+    // The node limit is checked on every call, since it may be much smaller
+    // than the checkup window.
+    if self.node_limit_reached(nodes) {
+      return false;
+    }
+
     // If we're not due for a checkup, simply return
     if nodes < self.next_checkup {
       return true;
@@ -191,8 +207,8 @@ impl TimeController {
       return true;
     }
 
-    // Always respect the global stop flag
-    if self.stopped() {
+    // Always respect the global stop flag and depth cap
+    if self.stopped() || depth > self.max_depth {
       return false;
     }
 
@@ -241,8 +257,10 @@ impl TimeController {
       / 100.0;
   }
 
+  /// Abort the search, along with any other searches sharing this time
+  /// controller.
   pub fn stop(&self) {
-    self.stop.store(false, Ordering::SeqCst);
+    self.stop.store(true, Ordering::SeqCst);
   }
 
   /// Check whether the search has been aborted.
@@ -260,6 +278,21 @@ impl TimeController {
   pub fn stop_early(&mut self) {
     self.stop_early = true;
   }
+
+  /// Cap the number of nodes the search is allowed to search
+  pub fn limit_nodes(&mut self, max_nodes: u32) {
+    self.max_nodes = max_nodes;
+  }
+
+  /// Check whether we've exceeded the node cap
+  pub fn node_limit_reached(&self, nodes: u32) -> bool {
+    nodes >= self.max_nodes
+  }
+
+  /// Cap the depth up to which the search is allowed to search
+  pub fn limit_depth(&mut self, max_depth: usize) {
+    self.max_depth = max_depth;
+  }
 }
 
 /// A wrapper for easily aborting a search, even on a different thread.
@@ -274,3 +307,23 @@ impl TimeControlHandle {
     self.stop.store(true, Ordering::SeqCst);
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn stop_aborts_every_clone() {
+    let (tc, _) = TimeController::new(TimeControl::Infinite, Color::White);
+    let mut helper = tc.clone();
+
+    assert!(!helper.stopped());
+    assert!(helper.should_continue(CHECKUP_WINDOW));
+
+    tc.stop();
+
+    assert!(tc.stopped());
+    assert!(helper.stopped());
+    assert!(!helper.should_continue(2 * CHECKUP_WINDOW));
+  }
+}
@@ -19,20 +19,31 @@
 //! Instead, we truncate the Zobrist hash to however many bits we need to
 //! accomodate for the requested table size. Reducing the key size means we'll
 //! get _many_ more collisions (called Type-2 Collisions). Because these are
-//! much more frequent, we store the full hash along with the the rest of the
-//! values, so that when we read the entry from the table, we can check our
-//! board's position with the full hash, to make sure we (probably) didn't get
-//! a false positive.
+//! much more frequent, we store part of the hash along with the the rest of
+//! the values, so that when we read the entry from the table, we can check
+//! our board's position against it, to make sure we (probably) didn't get a
+//! false positive.
+//!
+//! 3. When two positions map to the same index, one of them has to give way.
+//! To soften the blow, the table is made up of buckets that each hold a
+//! handful of entries, and fit snugly in a cache line. On every insert, we
+//! pick the least valuable entry in the bucket (the shallowest and oldest) to
+//! be replaced.
 
 use crate::evaluate::Score;
 use crate::evaluate::ScoreExt;
 use crate::zobrist::ZHash;
 use chess::movegen::moves::Move;
 use std::mem::size_of;
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::sync::atomic::AtomicU16;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::AtomicU8;
 use std::sync::atomic::Ordering;
 
+mod persist;
+
 /// A flag that stores whether the entry corresponds to a PV, fail-high or
 /// fail-low node. Or, equivalently, whether the score saved in the entry is
 /// exact, and upper bound, or a lower bound.
@@ -235,24 +246,42 @@ impl TTEntry {
 
 ////////////////////////////////////////////////////////////////////////////////
 //
-// Packed TT Entry
+// TT Buckets
 //
 ////////////////////////////////////////////////////////////////////////////////
 
+/// The number of entries that share a single bucket
+const BUCKET_SIZE: usize = 3;
+
+/// A bucket of entries that share the same index into the table.
+///
+/// Each entry takes up 10 bytes: a 16-bit verification key, and 8 bytes of
+/// packed data. All of it is stored in atomics, so that the table can be
+/// shared between threads without any locking. Entries that get mangled by
+/// two threads writing at the same time are rare, and caught by the search
+/// checking whether the stored move is legal.
 #[derive(Default)]
-struct PackedTTEntry {
-  hash: AtomicU64,
-  data: AtomicU64,
+#[repr(C, align(32))]
+struct PackedBucket {
+  keys: [AtomicU16; BUCKET_SIZE],
+  data: [AtomicU64; BUCKET_SIZE],
 }
 
 type Layout = (Move, i16, i16, u8, TTInfo);
 
-impl PackedTTEntry {
-  fn store(&self, entry: &TTEntry) {
-    let hash = entry.hash.0;
+impl PackedBucket {
+  /// Derive the key used to tell apart positions that share a bucket.
+  ///
+  /// The index into the table is derived from the high bits of the hash, so
+  /// we use the low bits here.
+  fn key(hash: ZHash) -> u16 {
+    hash.0 as u16
+  }
 
+  /// Pack everything but the hash of an entry into a single u64
+  fn pack(entry: &TTEntry) -> u64 {
     // SAFETY: The sizes of the Layout type and u64 match.
-    let data = unsafe {
+    unsafe {
       std::mem::transmute::<Layout, u64>((
         entry.best_move,
         entry.score,
@@ -260,22 +289,17 @@ impl PackedTTEntry {
         entry.depth,
         entry.info,
       ))
-    };
-
-    self.hash.store(hash, Ordering::Relaxed);
-    self.data.store(data, Ordering::Relaxed);
+    }
   }
 
-  fn load(&self) -> TTEntry {
-    let hash = self.hash.load(Ordering::Relaxed);
-    let data = self.data.load(Ordering::Relaxed);
-
+  /// Unpack the data stored by `pack`, and tag it with the provided hash
+  fn unpack(hash: ZHash, data: u64) -> TTEntry {
     // SAFETY: The sizes of the Layout type and u64 match.
     let (best_move, score, eval, depth, info) =
       unsafe { std::mem::transmute::<_, Layout>(data) };
 
     TTEntry {
-      hash: ZHash(hash),
+      hash,
       best_move,
       score,
       eval,
@@ -283,6 +307,32 @@ impl PackedTTEntry {
       info,
     }
   }
+
+  fn store(&self, idx: usize, entry: &TTEntry) {
+    self.keys[idx].store(Self::key(entry.hash), Ordering::Relaxed);
+    self.data[idx].store(Self::pack(entry), Ordering::Relaxed);
+  }
+
+  /// Load the entry in the requested slot, if it's occupied.
+  ///
+  /// Since we only store part of the hash, the entry is tagged with the hash
+  /// that was passed in if the keys match, and with a null hash otherwise.
+  fn load(&self, idx: usize, hash: ZHash) -> Option<TTEntry> {
+    let key = self.keys[idx].load(Ordering::Relaxed);
+    let data = self.data[idx].load(Ordering::Relaxed);
+
+    if data == 0 {
+      return None;
+    }
+
+    let hash = if key == Self::key(hash) {
+      hash
+    } else {
+      ZHash::NULL
+    };
+
+    Some(Self::unpack(hash, data))
+  }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -293,12 +343,12 @@ impl PackedTTEntry {
 
 /// A transposition table that stores previously searched results
 pub struct TTable {
-  /// A collection of entries. Stored on the heap because we need to be able
+  /// A collection of buckets. Stored on the heap because we need to be able
   /// to dynamically resize it. We only instantiate it once at the start of
   /// the search though, so this isn't a big deal.
-  table: Vec<PackedTTEntry>,
+  table: BucketArray,
 
-  /// The number of entries in the TT
+  /// The number of buckets in the TT
   size: usize,
 
   /// The age of the transposition table, incremented every time a new search
@@ -308,85 +358,156 @@ pub struct TTable {
 }
 
 impl TTable {
-  /// Resize table to the size requested in MiB
-  pub fn resize(&mut self, mb_size: usize) {
-    let size = (mb_size << 20) / size_of::<TTEntry>();
-    self.table.resize_with(size, PackedTTEntry::default);
+  /// The number of buckets sampled to estimate the occupancy
+  const OCCUPANCY_SAMPLE: usize = 1000;
+
+  /// How many plies of depth a single search's worth of age is worth, when
+  /// deciding which entry to replace
+  const AGE_WEIGHT: i32 = 8;
+
+  /// Resize table to the size requested in MiB, using the requested number of
+  /// threads to initialize it. Any existing entries are discarded.
+  pub fn resize(&mut self, mb_size: usize, threads: usize) {
+    let size = (mb_size << 20) / size_of::<PackedBucket>();
+    self.allocate(size, threads);
+  }
+
+  /// Replace the table with an empty table of `size` buckets
+  fn allocate(&mut self, size: usize, threads: usize) {
+    // Free up the old table first, so we don't need to hold on to both
+    self.table = BucketArray::default();
+    self.table = BucketArray::new(size, threads);
+    self.size = size;
   }
 
   /// Create a new table with the requested capacity in megabytes
   pub fn with_capacity(mb_size: usize) -> TTable {
-    // The number of enties in the TT
-    let size = (mb_size << 20) / size_of::<TTEntry>();
+    // The number of buckets in the TT
+    let size = (mb_size << 20) / size_of::<PackedBucket>();
 
     let mut table: TTable = TTable {
-      table: Vec::new(),
+      table: BucketArray::default(),
       size,
       age: AtomicU8::new(0),
     };
 
-    table.resize(mb_size);
+    table.resize(mb_size, 1);
     table
   }
 
+  /// Wipe all entries from the table, splitting the work over the requested
+  /// number of threads.
+  ///
+  /// Should not be called while a search is using the table.
+  pub fn clear(&self, threads: usize) {
+    self.table.zero(threads);
+    self.age.store(0, Ordering::Relaxed);
+  }
+
   /// Insert an entry into the transposition table
   ///
-  /// If there's already an entry present, replace it if:
+  /// If the position is already stored in the bucket, replace it if:
   /// 1. The existing entry's age is less than the current age
   /// 2. The existing entry's depth is less than the current entry's
+  ///
+  /// Otherwise, take the place of an empty slot, or that of the least
+  /// valuable entry in the bucket.
   pub fn insert(&self, entry: TTEntry) {
-    use NodeType::*;
     let key: ZKey = ZKey::from_hash(entry.hash, self.size);
-    let existing: TTEntry = self.table[key.0].load();
-
-    if existing.is_empty()
-      || existing.get_move().is_none()
-      || existing.get_age() != self.get_age()
-      || existing.depth <= entry.depth + 2 * entry.get_ttpv() as u8
-      || existing.hash != entry.hash
-      || entry.get_type() == Exact && existing.get_type() != Exact
-    {
-      self.table[key.0].store(&entry);
+    self.insert_into(&self.table[key.0], entry);
+  }
+
+  /// Insert an entry into the provided bucket, following the replacement
+  /// scheme described in `insert`.
+  fn insert_into(&self, bucket: &PackedBucket, entry: TTEntry) {
+    use NodeType::*;
+    let mut slot = 0;
+    let mut lowest_value = i32::MAX;
+
+    for idx in 0..BUCKET_SIZE {
+      let Some(existing) = bucket.load(idx, entry.hash) else {
+        bucket.store(idx, &entry);
+        return;
+      };
+
+      if existing.hash == entry.hash {
+        if existing.get_move().is_none()
+          || existing.get_age() != self.get_age()
+          || existing.depth <= entry.depth + 2 * entry.get_ttpv() as u8
+          || entry.get_type() == Exact && existing.get_type() != Exact
+        {
+          bucket.store(idx, &entry);
+        }
+
+        return;
+      }
+
+      let value = self.replacement_value(&existing);
+
+      if value < lowest_value {
+        lowest_value = value;
+        slot = idx;
+      }
     }
+
+    bucket.store(slot, &entry);
+  }
+
+  /// How much we'd prefer keeping an entry around, compared to the other
+  /// entries in its bucket. Deeper entries represent more work, and entries
+  /// from previous searches are less likely to still be relevant.
+  fn replacement_value(&self, entry: &TTEntry) -> i32 {
+    let relative_age =
+      (TTInfo::MAX_AGE + self.get_age() - entry.get_age()) % TTInfo::MAX_AGE;
+
+    entry.depth as i32 - Self::AGE_WEIGHT * relative_age as i32
   }
 
   // Check whether the hash appears in the transposition table, and return it
   // if so.
   pub fn probe(&self, hash: ZHash) -> Option<TTEntry> {
     let key = ZKey::from_hash(hash, self.size);
+    let bucket = self.table.get(key.0)?;
 
-    self
-      .table
-      .get(key.0)
-      .map(|packed| packed.load())
-      .filter(|entry| entry.hash == hash)
+    (0..BUCKET_SIZE)
+      .filter_map(|idx| bucket.load(idx, hash))
+      .find(|entry| entry.hash == hash)
   }
 
-  /// Instruct the CPU to read the requested TT entry into the CPU cache ahead
-  /// of time.
+  /// Instruct the CPU to read the requested TT bucket into the CPU cache
+  /// ahead of time.
   pub fn prefetch(&self, hash: ZHash) {
-    // get a reference to the entry in the table:
+    // get a reference to the bucket in the table:
     let key = ZKey::from_hash(hash, self.size);
-    let entry = &self.table[key.0];
+    let bucket = &self.table[key.0];
 
-    // prefetch the entry:
+    // prefetch the bucket:
     #[cfg(target_arch = "x86_64")]
     unsafe {
       use std::arch::x86_64::_mm_prefetch;
       use std::arch::x86_64::_MM_HINT_T0;
-      _mm_prefetch((entry as *const PackedTTEntry).cast::<i8>(), _MM_HINT_T0);
+      _mm_prefetch((bucket as *const PackedBucket).cast::<i8>(), _MM_HINT_T0);
     }
   }
 
   /// Return the occupancy as a fractional number (0 - 1)
+  ///
+  /// Only entries written during the current search are counted, so this
+  /// reflects how much of the table is being put to use, rather than how much
+  /// of it is still filled with stale entries.
   pub fn occupancy(&self) -> f32 {
-    let occupancy = self.table[0..1000]
+    let age = self.get_age();
+    let sample = &self.table[..Self::OCCUPANCY_SAMPLE.min(self.size)];
+
+    let occupancy = sample
       .iter()
-      .map(|packed| packed.hash.load(Ordering::Relaxed))
-      .filter(|&hash| ZHash(hash) != ZHash::NULL)
+      .flat_map(|bucket| {
+        (0..BUCKET_SIZE).filter_map(|idx| bucket.load(idx, ZHash::NULL))
+      })
+      .filter(|entry| entry.get_age() == age)
       .count();
 
-    occupancy as f32 / 1000.0
+    occupancy as f32 / (sample.len() * BUCKET_SIZE).max(1) as f32
   }
 
   /// Get the current age of the transposition table
@@ -404,6 +525,127 @@ impl TTable {
   }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+//
+// Table memory
+//
+////////////////////////////////////////////////////////////////////////////////
+
+/// A zero-initialized slice of buckets on the heap
+///
+/// The table can get very big (up to hundreds of GB), so rather than going
+/// through a `Vec`, we manage the memory ourselves. This lets us align the
+/// table to huge pages and ask the OS to back it with them, which saves a lot
+/// of TLB misses when probing all over the table. It also lets us initialize
+/// the table on several threads, rather than having a single thread zero out
+/// the entire thing.
+struct BucketArray {
+  ptr: NonNull<PackedBucket>,
+  len: usize,
+}
+
+// SAFETY: The buckets are only made up of atomics, so they can be shared and
+// sent between threads freely.
+unsafe impl Send for BucketArray {}
+unsafe impl Sync for BucketArray {}
+
+impl BucketArray {
+  /// Align the table to the size of a (2MB) huge page.
+  const ALIGN: usize = 2 << 20;
+
+  /// Allocate an array of `len` buckets, and zero it out using the requested
+  /// number of threads.
+  fn new(len: usize, threads: usize) -> Self {
+    if len == 0 {
+      return Self::default();
+    }
+
+    let layout = Self::layout(len);
+
+    // SAFETY: The layout has a non-zero size, and we only hand out
+    // references to the memory after it has been zeroed, which is a valid
+    // bit pattern for a bucket.
+    let ptr = unsafe { std::alloc::alloc(layout) };
+
+    let Some(ptr) = NonNull::new(ptr.cast::<PackedBucket>()) else {
+      std::alloc::handle_alloc_error(layout);
+    };
+
+    // Transparent huge pages are usually only used for memory the program
+    // explicitly asks for. It's only a hint, so we don't mind if it fails.
+    #[cfg(target_os = "linux")]
+    unsafe {
+      libc::madvise(ptr.as_ptr().cast(), layout.size(), libc::MADV_HUGEPAGE);
+    }
+
+    let array = Self { ptr, len };
+    array.zero(threads);
+    array
+  }
+
+  fn layout(len: usize) -> std::alloc::Layout {
+    std::alloc::Layout::from_size_align(
+      len * size_of::<PackedBucket>(),
+      Self::ALIGN,
+    )
+    .expect("Transposition table too large")
+  }
+
+  /// Zero out the entire array, splitting the work over the requested number
+  /// of threads. Should not be called while other threads are accessing the
+  /// array.
+  fn zero(&self, threads: usize) {
+    let chunk_size = self.len.div_ceil(threads.max(1)).max(1);
+
+    // Raw pointers aren't `Send`, so pass the address instead
+    let base = self.ptr.as_ptr() as usize;
+
+    std::thread::scope(|s| {
+      for start in (0..self.len).step_by(chunk_size) {
+        let count = chunk_size.min(self.len - start);
+
+        s.spawn(move || {
+          let ptr = (base as *mut PackedBucket).wrapping_add(start);
+
+          // SAFETY: The chunks don't overlap, and stay within the bounds of
+          // the allocation. All zeroes is a valid (empty) bucket.
+          unsafe { std::ptr::write_bytes(ptr, 0, count) };
+        });
+      }
+    });
+  }
+}
+
+impl Default for BucketArray {
+  fn default() -> Self {
+    Self {
+      ptr: NonNull::dangling(),
+      len: 0,
+    }
+  }
+}
+
+impl Deref for BucketArray {
+  type Target = [PackedBucket];
+
+  fn deref(&self) -> &Self::Target {
+    // SAFETY: The pointer is either dangling with a length of zero, or points
+    // to `len` initialized buckets.
+    unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+  }
+}
+
+impl Drop for BucketArray {
+  fn drop(&mut self) {
+    if self.len > 0 {
+      // SAFETY: The memory was allocated in `new`, with the same layout.
+      unsafe {
+        std::alloc::dealloc(self.ptr.as_ptr().cast(), Self::layout(self.len))
+      };
+    }
+  }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 //
 // Zobrist keys
@@ -434,3 +676,131 @@ impl Default for TTEntry {
     TTEntry::NULL
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chess::board::Board;
+  use chess::movegen::legal_moves::All;
+
+  fn entry(hash: ZHash, depth: usize, age: u8) -> TTEntry {
+    let best_move = Board::default().legal_moves::<All>()[0];
+    TTEntry::new(
+      hash,
+      best_move,
+      50,
+      20,
+      depth,
+      NodeType::Exact,
+      age,
+      false,
+      0,
+    )
+  }
+
+  #[test]
+  fn buckets_fit_a_cache_line() {
+    assert_eq!(size_of::<PackedBucket>(), 32);
+  }
+
+  #[test]
+  fn resize_updates_the_size() {
+    let mut tt = TTable::with_capacity(2);
+    tt.resize(1, 1);
+    assert_eq!(tt.size, (1 << 20) / size_of::<PackedBucket>());
+
+    // Every hash should land in the shrunk table
+    for i in 0..1000u64 {
+      let hash = ZHash(i.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+      tt.insert(entry(hash, 3, 0));
+      assert!(tt.probe(hash).is_some());
+    }
+  }
+
+  #[test]
+  fn store_and_probe() {
+    let tt = TTable::with_capacity(1);
+    let hash = ZHash(0x1234_5678_9abc_def0);
+    tt.insert(entry(hash, 7, 0));
+
+    let found = tt.probe(hash).unwrap();
+    assert_eq!(found.get_depth(), 7);
+    assert_eq!(found.get_score(), 50);
+    assert_eq!(found.get_eval(), 20);
+
+    // Same bucket, different verification key
+    assert!(tt.probe(ZHash(0x1234_5678_9abc_0000)).is_none());
+  }
+
+  #[test]
+  fn colliding_entries_share_a_bucket() {
+    let tt = TTable::with_capacity(1);
+    let hashes = [1, 2, 3].map(|i| ZHash(0xabcd_0000_0000_0000 | i));
+
+    for (i, &hash) in hashes.iter().enumerate() {
+      tt.insert(entry(hash, 4 + i, 0));
+    }
+
+    assert!(hashes.iter().all(|&hash| tt.probe(hash).is_some()));
+
+    // A fourth entry evicts the shallowest one
+    let hash = ZHash(0xabcd_0000_0000_0004);
+    tt.insert(entry(hash, 10, 0));
+    assert!(tt.probe(hash).is_some());
+    assert!(tt.probe(hashes[0]).is_none());
+    assert!(tt.probe(hashes[2]).is_some());
+  }
+
+  #[test]
+  fn old_entries_are_replaced_first() {
+    let tt = TTable::with_capacity(1);
+    let hashes = [1, 2, 3].map(|i| ZHash(0xabcd_0000_0000_0000 | i));
+
+    tt.insert(entry(hashes[0], 10, 0));
+    tt.increment_age();
+    tt.insert(entry(hashes[1], 5, 1));
+    tt.insert(entry(hashes[2], 6, 1));
+
+    let hash = ZHash(0xabcd_0000_0000_0004);
+    tt.insert(entry(hash, 4, 1));
+    assert!(tt.probe(hash).is_some());
+    assert!(tt.probe(hashes[0]).is_none());
+    assert!(tt.probe(hashes[1]).is_some());
+  }
+
+  #[test]
+  fn table_is_aligned_to_huge_pages() {
+    let tt = TTable::with_capacity(4);
+    assert_eq!(tt.table.as_ptr() as usize % BucketArray::ALIGN, 0);
+    assert_eq!(tt.table.len() * size_of::<PackedBucket>(), 4 << 20);
+  }
+
+  #[test]
+  fn clear_on_multiple_threads() {
+    let mut tt = TTable::with_capacity(1);
+    tt.resize(2, 3);
+
+    let hashes = (0..1000u64)
+      .map(|i| ZHash(i.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1))
+      .collect::<Vec<_>>();
+
+    for &hash in &hashes {
+      tt.insert(entry(hash, 5, 0));
+    }
+
+    assert!(hashes.iter().any(|&hash| tt.probe(hash).is_some()));
+
+    tt.clear(3);
+    assert!(hashes.iter().all(|&hash| tt.probe(hash).is_none()));
+  }
+
+  #[test]
+  fn occupancy_only_counts_current_entries() {
+    let tt = TTable::with_capacity(1);
+    tt.insert(entry(ZHash(0x0100_0000_0000_0001), 3, 0));
+    assert!(tt.occupancy() > 0.0);
+
+    tt.increment_age();
+    assert_eq!(tt.occupancy(), 0.0);
+  }
+}
@@ -0,0 +1,441 @@
+//! Saving the transposition table to disk, and loading it back in
+//!
+//! Long analyses can run for days, so it's useful to be able to stop the
+//! engine and pick up where we left off, without having to fill up the table
+//! from scratch.
+//!
+//! The file starts with a header, followed by the raw contents of the table.
+//! All integers are stored in little-endian order.
+//!
+//! +---------+---------+-------------+--------+-----+---------+-------------+
+//! |  Magic  | Version | Bucket size | Layout | Age | Buckets |   Entries   |
+//! | 8 bytes |   u32   |     u32     |  u64   | u8  |   u64   |  (u16, u64) |
+//! +---------+---------+-------------+--------+-----+---------+-------------+
+//!
+//! The layout field holds a reference entry, packed the way the running build
+//! packs its entries. If the way entries are packed ever changes, the field
+//! won't match, and we refuse to load the table rather than reading garbage.
+
+use super::NodeType;
+use super::PackedBucket;
+use super::TTEntry;
+use super::TTInfo;
+use super::TTable;
+use super::BUCKET_SIZE;
+use crate::search::params::MAX_TT_SIZE;
+use crate::zobrist::ZHash;
+use anyhow::anyhow;
+use anyhow::Context;
+use chess::movegen::moves::Move;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Read;
+use std::io::Write;
+use std::mem::size_of;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+
+/// Identifies the file as a Simbelmyne transposition table
+const MAGIC: [u8; 8] = *b"SIMBTT\0\0";
+
+/// The version of the file format. Bump this whenever the format changes.
+const VERSION: u32 = 1;
+
+/// The size of the header, in bytes
+const HEADER_SIZE: u64 = 8 + 4 + 4 + 8 + 1 + 8;
+
+/// The size of a single saved entry, in bytes
+const ENTRY_SIZE: u64 = (size_of::<u16>() + size_of::<u64>()) as u64;
+
+/// The header at the start of a saved table
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Header {
+  version: u32,
+  bucket_size: u32,
+  layout: u64,
+  age: u8,
+  buckets: u64,
+}
+
+impl Header {
+  /// The header for a table saved by this build
+  fn new(tt: &TTable) -> Self {
+    Self {
+      version: VERSION,
+      bucket_size: BUCKET_SIZE as u32,
+      layout: layout_fingerprint(),
+      age: tt.get_age(),
+      buckets: tt.size as u64,
+    }
+  }
+
+  fn write(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&self.version.to_le_bytes())?;
+    writer.write_all(&self.bucket_size.to_le_bytes())?;
+    writer.write_all(&self.layout.to_le_bytes())?;
+    writer.write_all(&[self.age])?;
+    writer.write_all(&self.buckets.to_le_bytes())?;
+    Ok(())
+  }
+
+  /// Read a header, and check whether the table it describes can be used by
+  /// this build.
+  fn read(reader: &mut impl Read) -> anyhow::Result<Self> {
+    if read_bytes::<8>(reader)? != MAGIC {
+      return Err(anyhow!("Not a transposition table file"));
+    }
+
+    let header = Self {
+      version: u32::from_le_bytes(read_bytes(reader)?),
+      bucket_size: u32::from_le_bytes(read_bytes(reader)?),
+      layout: u64::from_le_bytes(read_bytes(reader)?),
+      age: u8::from_le_bytes(read_bytes(reader)?),
+      buckets: u64::from_le_bytes(read_bytes(reader)?),
+    };
+
+    if header.version != VERSION {
+      return Err(anyhow!(
+        "Unsupported file version {} (expected {VERSION})",
+        header.version
+      ));
+    }
+
+    if header.bucket_size != BUCKET_SIZE as u32
+      || header.layout != layout_fingerprint()
+    {
+      return Err(anyhow!(
+        "The table was saved by a build with a different entry layout"
+      ));
+    }
+
+    if header.age >= TTInfo::MAX_AGE {
+      return Err(anyhow!("Invalid table age {}", header.age));
+    }
+
+    Ok(header)
+  }
+}
+
+impl TTable {
+  /// Save the table to a file, so it can be restored with `load`.
+  pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let file = File::create(path)
+      .with_context(|| format!("Failed to create {}", path.display()))?;
+
+    let mut writer = BufWriter::new(file);
+    self.write_to(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+  }
+
+  /// Replace the table with one saved by `save`, including its size and age,
+  /// using the requested number of threads to allocate it.
+  ///
+  /// If the file turns out to be corrupt after we've started reading entries,
+  /// the table is left empty, at its original size.
+  pub fn load(
+    &mut self,
+    path: impl AsRef<Path>,
+    threads: usize,
+  ) -> anyhow::Result<()> {
+    let mut reader = open(path.as_ref())?;
+    let len = reader.get_ref().metadata()?.len();
+    self.read_from(&mut reader, len, threads)
+  }
+
+  /// Merge a table saved by `save` into this one. The saved entries are
+  /// inserted as if they'd been found by a search, so they only replace the
+  /// current entries when they're more valuable.
+  ///
+  /// Since entries only store part of their hash, the saved table needs to
+  /// have the same size as this one.
+  pub fn merge(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let mut reader = open(path.as_ref())?;
+    self.merge_from(&mut reader)
+  }
+
+  fn write_to(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+    Header::new(self).write(writer)?;
+
+    for bucket in self.table.iter() {
+      for idx in 0..BUCKET_SIZE {
+        let key = bucket.keys[idx].load(Ordering::Relaxed);
+        let data = bucket.data[idx].load(Ordering::Relaxed);
+        writer.write_all(&key.to_le_bytes())?;
+        writer.write_all(&data.to_le_bytes())?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Read a table of `len` bytes (including the header), using the requested
+  /// number of threads to allocate it.
+  fn read_from(
+    &mut self,
+    reader: &mut impl Read,
+    len: u64,
+    threads: usize,
+  ) -> anyhow::Result<()> {
+    let header = Header::read(reader)?;
+    let size = usize::try_from(header.buckets)?;
+
+    if size == 0 || size > (MAX_TT_SIZE << 20) / size_of::<PackedBucket>() {
+      return Err(anyhow!("Invalid table size of {size} buckets"));
+    }
+
+    // Check the header against the file before allocating anything, so a
+    // corrupt header can't make us allocate a huge table.
+    let expected_len = header
+      .buckets
+      .checked_mul(BUCKET_SIZE as u64 * ENTRY_SIZE)
+      .and_then(|entries| entries.checked_add(HEADER_SIZE));
+
+    if expected_len != Some(len) {
+      return Err(anyhow!(
+        "The file is {len} bytes long, which doesn't match a table of \
+         {size} buckets"
+      ));
+    }
+
+    // Don't leave a half-loaded table behind if reading fails, but go back
+    // to an empty table of the original size.
+    let previous_size = self.size;
+
+    let result = (|| {
+      self.allocate(size, threads);
+
+      for bucket in self.table.iter() {
+        for idx in 0..BUCKET_SIZE {
+          let (key, data) = read_entry(reader)?;
+          bucket.keys[idx].store(key, Ordering::Relaxed);
+          bucket.data[idx].store(data, Ordering::Relaxed);
+        }
+      }
+
+      Ok(())
+    })();
+
+    match result {
+      Ok(()) => {
+        self.age.store(header.age, Ordering::Relaxed);
+        Ok(())
+      }
+
+      Err(err) => {
+        self.allocate(previous_size, threads);
+        self.age.store(0, Ordering::Relaxed);
+        Err(err)
+      }
+    }
+  }
+
+  fn merge_from(&self, reader: &mut impl Read) -> anyhow::Result<()> {
+    let header = Header::read(reader)?;
+
+    if header.buckets != self.size as u64 {
+      return Err(anyhow!(
+        "Can't merge a table of {} buckets into one of {} buckets",
+        header.buckets,
+        self.size
+      ));
+    }
+
+    for bucket in self.table.iter() {
+      for _ in 0..BUCKET_SIZE {
+        let (key, data) = read_entry(reader)?;
+
+        if data == 0 {
+          continue;
+        }
+
+        // Any hash with the right key will do, since the bucket is fixed.
+        let mut entry = PackedBucket::unpack(ZHash(key as u64), data);
+
+        // Keep the entry's age relative to the saved table's age, so old
+        // entries stay old.
+        let relative_age =
+          (TTInfo::MAX_AGE + header.age - entry.get_age()) % TTInfo::MAX_AGE;
+        let age =
+          (TTInfo::MAX_AGE + self.get_age() - relative_age) % TTInfo::MAX_AGE;
+        entry.info = TTInfo::new(age, entry.get_type(), entry.get_ttpv());
+
+        self.insert_into(bucket, entry);
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// A reference entry, packed with this build's layout
+fn layout_fingerprint() -> u64 {
+  PackedBucket::pack(&TTEntry {
+    hash: ZHash::NULL,
+    best_move: Move::from_u16(0x1234),
+    score: -5678,
+    eval: 910,
+    depth: 11,
+    info: TTInfo::new(12, NodeType::Lower, true),
+  })
+}
+
+fn open(path: &Path) -> anyhow::Result<BufReader<File>> {
+  let file = File::open(path)
+    .with_context(|| format!("Failed to open {}", path.display()))?;
+
+  Ok(BufReader::new(file))
+}
+
+fn read_bytes<const N: usize>(
+  reader: &mut impl Read,
+) -> anyhow::Result<[u8; N]> {
+  let mut bytes = [0; N];
+  reader
+    .read_exact(&mut bytes)
+    .context("Unexpected end of file")?;
+
+  Ok(bytes)
+}
+
+fn read_entry(reader: &mut impl Read) -> anyhow::Result<(u16, u64)> {
+  let key = u16::from_le_bytes(read_bytes(reader)?);
+  let data = u64::from_le_bytes(read_bytes(reader)?);
+  Ok((key, data))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chess::board::Board;
+  use chess::movegen::legal_moves::All;
+
+  fn filled_table(mb_size: usize, hashes: &[ZHash], depth: usize) -> TTable {
+    let tt = TTable::with_capacity(mb_size);
+    let best_move = Board::default().legal_moves::<All>()[0];
+
+    for &hash in hashes {
+      tt.insert(TTEntry::new(
+        hash,
+        best_move,
+        30,
+        10,
+        depth,
+        NodeType::Exact,
+        tt.get_age(),
+        false,
+        0,
+      ));
+    }
+
+    tt
+  }
+
+  fn hashes(seed: u64) -> Vec<ZHash> {
+    (1..500u64)
+      .map(|i| ZHash((i * seed).wrapping_mul(0x9e37_79b9_7f4a_7c15)))
+      .collect()
+  }
+
+  #[test]
+  fn save_and_load() {
+    let hashes = hashes(1);
+    let tt = filled_table(1, &hashes, 6);
+    tt.increment_age();
+
+    let mut file = Vec::new();
+    tt.write_to(&mut file).unwrap();
+
+    let mut loaded = TTable::with_capacity(2);
+    let len = file.len() as u64;
+    loaded.read_from(&mut file.as_slice(), len, 1).unwrap();
+
+    assert_eq!(loaded.size, tt.size);
+    assert_eq!(loaded.get_age(), tt.get_age());
+
+    for &hash in &hashes {
+      assert_eq!(loaded.probe(hash), tt.probe(hash));
+    }
+  }
+
+  #[test]
+  fn reject_incompatible_files() {
+    let tt = filled_table(1, &hashes(1), 6);
+    let mut file = Vec::new();
+    tt.write_to(&mut file).unwrap();
+
+    let len = file.len() as u64;
+
+    // Wrong version
+    let mut wrong_version = file.clone();
+    wrong_version[8] = 99;
+    let mut loaded = TTable::with_capacity(1);
+    assert!(loaded
+      .read_from(&mut wrong_version.as_slice(), len, 1)
+      .is_err());
+
+    // Wrong layout
+    let mut wrong_layout = file.clone();
+    wrong_layout[16] ^= 0xff;
+    assert!(loaded
+      .read_from(&mut wrong_layout.as_slice(), len, 1)
+      .is_err());
+
+    // Truncated
+    let truncated = &file[..file.len() / 2];
+    let truncated_len = truncated.len() as u64;
+    assert!(loaded
+      .read_from(&mut &truncated[..], truncated_len, 1)
+      .is_err());
+
+    // Different size
+    let other = TTable::with_capacity(2);
+    assert!(other.merge_from(&mut file.as_slice()).is_err());
+  }
+
+  #[test]
+  fn failed_loads_keep_the_original_size() {
+    let tt = filled_table(1, &hashes(1), 6);
+    let mut file = Vec::new();
+    tt.write_to(&mut file).unwrap();
+
+    let mut loaded = TTable::with_capacity(2);
+    let original_size = loaded.size;
+
+    // A corrupt header that claims a huge table is rejected before
+    // allocating anything
+    let mut huge = file.clone();
+    let buckets = (MAX_TT_SIZE << 20) / size_of::<PackedBucket>();
+    huge[25..33].copy_from_slice(&(buckets as u64).to_le_bytes());
+    let len = huge.len() as u64;
+    assert!(loaded.read_from(&mut huge.as_slice(), len, 1).is_err());
+    assert_eq!(loaded.size, original_size);
+
+    // A file that ends early, even though it claims to be complete, leaves an
+    // empty table of the original size behind
+    let truncated = &file[..file.len() / 2];
+    let len = file.len() as u64;
+    assert!(loaded.read_from(&mut &truncated[..], len, 1).is_err());
+    assert_eq!(loaded.size, original_size);
+    assert_eq!(loaded.occupancy(), 0.0);
+  }
+
+  #[test]
+  fn merge_tables() {
+    let ours = hashes(1);
+    let theirs = hashes(3);
+    let tt = filled_table(1, &ours, 6);
+    let saved = filled_table(1, &theirs, 8);
+
+    let mut file = Vec::new();
+    saved.write_to(&mut file).unwrap();
+    tt.merge_from(&mut file.as_slice()).unwrap();
+
+    // Deeper entries replace shallower ones, so all of theirs made it in
+    assert!(theirs.iter().all(|&hash| tt.probe(hash).is_some()));
+    assert!(ours.iter().any(|&hash| tt.probe(hash).is_some()));
+  }
+}
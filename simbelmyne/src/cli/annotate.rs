@@ -0,0 +1,240 @@
+//! Annotate games with the engine's evaluations.
+//!
+//! Every position in the game is searched, and the moves are annotated with
+//! the evaluation of the resulting position (as `[%eval]` and `[%wdl]`
+//! comments). Moves that lose more than a given amount of centipawns are
+//! marked as inaccuracies (`?!`), mistakes (`?`) or blunders (`??`), and get
+//! the line the engine preferred as a variation.
+
+use crate::uci::NAME;
+use crate::uci::VERSION;
+use anyhow::Context;
+use chess::movegen::legal_moves::All;
+use chess::movegen::moves::Move;
+use chess::pgn::Annotation;
+use chess::pgn::Pgn;
+use engine::position::Position;
+use engine::search::NodeCounter;
+use engine::search::SearchRunner;
+use engine::time_control::TimeController;
+use engine::transpositions::TTable;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU32;
+use std::time::Duration;
+use uci::search_info::Score;
+use uci::search_info::SearchInfo;
+use uci::time_control::TimeControl;
+use uci::wdl::WDL_MODEL;
+
+/// The size of the engine's transposition table, in MB
+const TT_SIZE: usize = 64;
+
+/// The search depth to use when neither a depth nor a time is provided
+const DEFAULT_DEPTH: usize = 12;
+
+/// Evaluations are capped to this value (in normalized centipawns) before
+/// comparing them, so moves in completely won or lost positions, or moves
+/// that merely delay a mate, don't get flagged.
+const EVAL_CAP: i32 = 1000;
+
+/// NAGs for the move classifications
+const INACCURACY: u8 = 6;
+const MISTAKE: u8 = 2;
+const BLUNDER: u8 = 4;
+
+/// The settings for an annotation run, as passed on the command line
+pub struct AnnotateConfig {
+  pub file: PathBuf,
+  pub output: Option<PathBuf>,
+  pub depth: Option<usize>,
+  pub movetime: Option<f64>,
+  pub inaccuracy: i32,
+  pub mistake: i32,
+  pub blunder: i32,
+}
+
+/// The engine's verdict on a single position
+struct Analysis {
+  /// The score of the position, from the side to move's perspective, with
+  /// centipawn scores normalized by the WDL model.
+  score: Score,
+
+  /// The win/draw/loss probabilities, from the side to move's perspective
+  wdl: (u64, u64, u64),
+
+  /// The line the engine prefers
+  pv: Vec<Move>,
+
+  /// Whether the game is over (checkmate or stalemate)
+  game_over: bool,
+}
+
+pub fn run_annotate(config: AnnotateConfig) -> anyhow::Result<()> {
+  let input = std::fs::read_to_string(&config.file)?;
+  let mut games = Pgn::parse_all(&input)?;
+
+  let tc = match (config.depth, config.movetime) {
+    (None, Some(secs)) => TimeControl::FixedTime(
+      Duration::try_from_secs_f64(secs).context("Invalid movetime")?,
+    ),
+    (depth, _) => TimeControl::Depth(depth.unwrap_or(DEFAULT_DEPTH)),
+  };
+
+  let tt = TTable::with_capacity(TT_SIZE);
+  let global_nodes = AtomicU32::new(0);
+  let mut runner = SearchRunner::new(0, &tt, NodeCounter::new(&global_nodes));
+
+  let total = games.len();
+
+  for (i, game) in games.iter_mut().enumerate() {
+    eprintln!("Annotating game {}/{total}", i + 1);
+    annotate(game, &mut runner, &tc, &config);
+  }
+
+  let output = games
+    .iter()
+    .map(|game| game.to_string())
+    .collect::<Vec<_>>()
+    .join("\n");
+
+  match config.output {
+    Some(path) => std::fs::write(path, output)?,
+    None => print!("{output}"),
+  }
+
+  Ok(())
+}
+
+/// Search every position in the game, and annotate its moves
+fn annotate(
+  game: &mut Pgn,
+  runner: &mut SearchRunner,
+  tc: &TimeControl,
+  config: &AnnotateConfig,
+) {
+  let mut position = Position::new(game.board);
+  let mut analyses = vec![analyze(&position, runner, tc)];
+
+  for &mv in game.moves.iter() {
+    position = position.play_move(mv);
+    analyses.push(analyze(&position, runner, tc));
+  }
+
+  game.annotations = analyses
+    .windows(2)
+    .enumerate()
+    .map(|(ply, pair)| {
+      let [before, after] = pair else {
+        unreachable!()
+      };
+      let mut annotation = Annotation::default();
+
+      // The evaluation of the resulting position, from White's perspective
+      let white_to_move = game.board.current.is_white() != (ply % 2 == 0);
+
+      if !after.game_over {
+        let (score, wdl) = if white_to_move {
+          (after.score, after.wdl)
+        } else {
+          (negate(after.score), (after.wdl.2, after.wdl.1, after.wdl.0))
+        };
+
+        let (w, d, l) = wdl;
+        annotation.comment =
+          Some(format!("[%eval {}] [%wdl {w} {d} {l}]", format_eval(score)));
+      }
+
+      // Flag the move if it lost too much compared to the best move
+      let loss = capped(before.score) + capped(after.score);
+
+      let nag = if loss >= config.blunder {
+        Some(BLUNDER)
+      } else if loss >= config.mistake {
+        Some(MISTAKE)
+      } else if loss >= config.inaccuracy {
+        Some(INACCURACY)
+      } else {
+        None
+      };
+
+      if let Some(nag) = nag {
+        annotation.nags.push(nag);
+        annotation.variation = before.pv.clone();
+      }
+
+      annotation
+    })
+    .collect();
+
+  game.set_tag("Annotator", &format!("{NAME} {VERSION}"));
+}
+
+/// Search a position, or score it directly if the game is over
+fn analyze(
+  position: &Position,
+  runner: &mut SearchRunner,
+  tc: &TimeControl,
+) -> Analysis {
+  let board = position.board;
+
+  if board.legal_moves::<All>().is_empty() {
+    let (score, wdl) = if board.in_check() {
+      (Score::Mate(0), (0, 0, 1000))
+    } else {
+      (Score::Cp(0), (0, 1000, 0))
+    };
+
+    return Analysis {
+      score,
+      wdl,
+      pv: Vec::new(),
+      game_over: true,
+    };
+  }
+
+  let (tc, _) = TimeController::new(*tc, board.current);
+  runner.tt.increment_age();
+  runner.nodes.clear_global();
+  let report = runner.search::<false>(position.clone(), tc);
+
+  let wdl_params = WDL_MODEL.params(&board);
+  let info = SearchInfo::from(&report).with_wdl(wdl_params);
+
+  let score = match info.score.unwrap_or(Score::Cp(0)) {
+    Score::Cp(cp) => Score::Cp(wdl_params.wdl_normalized(cp)),
+    mate => mate,
+  };
+
+  Analysis {
+    score,
+    wdl: info.wdl.unwrap_or((0, 1000, 0)),
+    pv: report.pv,
+    game_over: false,
+  }
+}
+
+/// Flip a score to the other side's perspective
+fn negate(score: Score) -> Score {
+  match score {
+    Score::Cp(cp) => Score::Cp(-cp),
+    Score::Mate(n) => Score::Mate(-n),
+  }
+}
+
+/// Cap a score to `EVAL_CAP`, treating mates as the capped value
+fn capped(score: Score) -> i32 {
+  match score {
+    Score::Cp(cp) => cp.clamp(-EVAL_CAP, EVAL_CAP),
+    Score::Mate(n) if n > 0 => EVAL_CAP,
+    Score::Mate(_) => -EVAL_CAP,
+  }
+}
+
+/// Format a score the way `[%eval]` comments expect it: in pawns, or as the
+/// number of moves to mate.
+fn format_eval(score: Score) -> String {
+  match score {
+    Score::Cp(cp) => format!("{:.2}", cp as f64 / 100.0),
+    Score::Mate(n) => format!("#{n}"),
+  }
+}
@@ -1,16 +1,41 @@
-use std::sync::atomic::AtomicU32;
-use std::time::Duration;
-
-use uci::time_control::TimeControl;
+//! Benchmark the search on a fixed set of positions
+//!
+//! By default, this runs the built-in positions to depth 13 on a single
+//! thread, and prints the total number of nodes and the nps, in the format
+//! OpenBench expects. The node count doubles as a signature for the search:
+//! any functional change to the search is bound to change it.
+//!
+//! The depth, hash size, number of threads and set of positions can all be
+//! configured, and the suite can be repeated a number of times to get a feel
+//! for how noisy the nps measurements are. The per-position results and
+//! statistics are printed to stderr, so stdout only ever holds the OpenBench
+//! line, or a JSON report that can be diffed between builds.
 
+use anyhow::anyhow;
+use anyhow::Context;
+use chess::board::Board;
+use chess::movegen::sliders;
+use chess::movegen::sliders::Sliders;
+use chess::movegen::sliders::WithSliders;
 use engine::position::Position;
+use engine::search::contempt::DEFAULT_CONTEMPT;
+use engine::search::observer::SilentObserver;
 use engine::search::NodeCounter;
+use engine::search::SearchReport;
 use engine::search::SearchRunner;
+use engine::thread_pool::ThreadPool;
 use engine::time_control::TimeController;
 use engine::transpositions::TTable;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU32;
+use std::time::Duration;
+use uci::search_info::SearchInfo;
+use uci::time_control::TimeControl;
 
 const NO_DEBUG: bool = false;
-const DEPTH: usize = 13;
+pub const DEPTH: usize = 13;
+pub const HASH: usize = 16;
 
 const POSITIONS: [&'static str; 50] = [
   "r3k2r/2pb1ppp/2pp1q2/p7/1nP1B3/1P2P3/P2N1PPP/R2QK2R w KQkq a6 0 14",
@@ -65,41 +90,355 @@ const POSITIONS: [&'static str; 50] = [
   "2r2b2/5p2/5k2/p1r1pP2/P2pB3/1P3P2/K1P3R1/7R w - - 23 93",
 ];
 
+/// The settings for a bench run, as passed on the command line
+pub struct BenchConfig {
+  pub depth: usize,
+  pub hash: usize,
+  pub threads: usize,
+  pub file: Option<PathBuf>,
+  pub repeat: usize,
+  pub json: bool,
+}
+
+impl Default for BenchConfig {
+  fn default() -> Self {
+    Self {
+      depth: DEPTH,
+      hash: HASH,
+      threads: 1,
+      file: None,
+      repeat: 1,
+      json: false,
+    }
+  }
+}
+
+/// The result of searching a single position
 pub struct BenchResult {
-  nodes: u64,
-  duration: Duration,
+  pub nodes: u64,
+  pub duration: Duration,
+  pub report: SearchReport,
+}
+
+/// The full results of a bench run, as output in JSON
+#[derive(Debug, Serialize)]
+pub struct BenchReport {
+  pub backend: String,
+  pub depth: usize,
+  pub hash: usize,
+  pub threads: usize,
+  pub positions: Vec<PositionReport>,
+  pub runs: Vec<RunReport>,
+  pub nodes: u64,
+  pub nps: NpsStats,
+}
+
+/// The results for a single position. The node count, best move and score are
+/// those of the first run, the time is the median over all runs.
+#[derive(Debug, Serialize)]
+pub struct PositionReport {
+  pub fen: String,
+  pub nodes: u64,
+  pub time_ms: f64,
+  pub best_move: String,
+  pub score: String,
 }
 
-pub fn run_bench() {
-  let mut total_nodes = 0;
-  let mut total_time = Duration::default();
+/// The totals for a single run through all the positions
+#[derive(Debug, Serialize)]
+pub struct RunReport {
+  pub nodes: u64,
+  pub time_ms: f64,
+  pub nps: u64,
+}
+
+/// Statistics on the nps, over all runs
+#[derive(Debug, Serialize)]
+pub struct NpsStats {
+  pub mean: f64,
+  pub median: f64,
+  pub stddev: f64,
+}
 
-  for fen in POSITIONS {
-    let BenchResult { nodes, duration } = run_single(fen, DEPTH);
-    total_nodes += nodes;
-    total_time += duration;
+pub fn run_bench(config: BenchConfig) -> anyhow::Result<()> {
+  let report = bench(&config)?;
+
+  if config.json {
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    return Ok(());
   }
 
-  println!(
-    "{} nodes {} nps",
-    total_nodes,
-    1_000_000_000 * total_nodes / total_time.as_nanos() as u64,
-  );
+  print_report(&report);
+
+  // Keep this line as is, since it's what OpenBench looks for.
+  let run = &report.runs[0];
+  println!("{} nodes {} nps", run.nodes, run.nps);
+
+  Ok(())
 }
 
-pub fn run_single(fen: &str, depth: usize) -> BenchResult {
-  let board = fen.parse().unwrap();
-  let position = Position::new(board);
-  let tt = TTable::with_capacity(16);
-  let (tc, _) = TimeController::new(TimeControl::Depth(depth), board.current);
-  let global_nodes = AtomicU32::new(0);
-  let nodes = NodeCounter::new(&global_nodes);
-  let mut search_thread = SearchRunner::new(0, &tt, nodes);
+/// Run the bench suite with the provided settings, and collect the results
+pub fn bench(config: &BenchConfig) -> anyhow::Result<BenchReport> {
+  let fens = match &config.file {
+    Some(path) => read_positions(path)?,
+    None => POSITIONS.iter().map(|fen| fen.to_string()).collect(),
+  };
+
+  let boards = fens
+    .iter()
+    .map(|fen| {
+      fen
+        .parse::<Board>()
+        .with_context(|| format!("Invalid FEN: {fen}"))
+    })
+    .collect::<anyhow::Result<Vec<_>>>()?;
+
+  let repeat = config.repeat.max(1);
+  let mut results: Vec<Vec<BenchResult>> = Vec::new();
+
+  for _ in 0..repeat {
+    let run = boards
+      .iter()
+      .map(|&board| {
+        run_single(board, config.depth, config.hash, config.threads)
+      })
+      .collect();
+
+    results.push(run);
+  }
+
+  let positions = fens
+    .into_iter()
+    .enumerate()
+    .map(|(i, fen)| {
+      let first = &results[0][i];
+      let mut times = results
+        .iter()
+        .map(|run| run[i].duration.as_secs_f64() * 1000.0)
+        .collect::<Vec<_>>();
+
+      PositionReport {
+        fen,
+        nodes: first.nodes,
+        time_ms: median(&mut times),
+        best_move: first
+          .report
+          .pv
+          .first()
+          .map_or(String::from("none"), |mv| mv.to_string()),
+        score: SearchInfo::from(&first.report)
+          .score
+          .map_or(String::new(), |score| score.to_string()),
+      }
+    })
+    .collect();
+
+  let runs = results
+    .iter()
+    .map(|run| {
+      let nodes = run.iter().map(|result| result.nodes).sum::<u64>();
+      let time = run.iter().map(|result| result.duration).sum::<Duration>();
+
+      RunReport {
+        nodes,
+        time_ms: time.as_secs_f64() * 1000.0,
+        nps: (1_000_000_000 * nodes as u128 / time.as_nanos().max(1)) as u64,
+      }
+    })
+    .collect::<Vec<_>>();
 
-  let report = search_thread.search::<NO_DEBUG>(position, tc);
+  let mut nps = runs.iter().map(|run| run.nps as f64).collect::<Vec<_>>();
+
+  Ok(BenchReport {
+    backend: sliders::backend().to_string(),
+    depth: config.depth,
+    hash: config.hash,
+    threads: config.threads,
+    positions,
+    nodes: runs[0].nodes,
+    nps: NpsStats {
+      mean: mean(&nps),
+      stddev: variance(&nps).sqrt(),
+      median: median(&mut nps),
+    },
+    runs,
+  })
+}
+
+/// Search a single position to the requested depth, on a fresh
+/// transposition table, so every position is searched independently of the
+/// others.
+pub fn run_single(
+  board: Board,
+  depth: usize,
+  hash: usize,
+  threads: usize,
+) -> BenchResult {
+  let report = sliders::dispatch(SingleSearch {
+    board,
+    depth,
+    hash,
+    threads,
+  });
 
   BenchResult {
     nodes: report.nodes as u64,
     duration: report.duration,
+    report,
+  }
+}
+
+/// The search run by `run_single`, compiled for each of the slider backends
+struct SingleSearch {
+  board: Board,
+  depth: usize,
+  hash: usize,
+  threads: usize,
+}
+
+impl WithSliders for SingleSearch {
+  type Output = SearchReport;
+
+  fn run<S: Sliders>(self) -> SearchReport {
+    let position = Position::new(self.board.with_sliders::<S>());
+    let tt = TTable::with_capacity(self.hash);
+    let tc = TimeControl::Depth(self.depth);
+    let (tc, _) = TimeController::new(tc, self.board.current);
+    let global_nodes = AtomicU32::new(0);
+
+    if self.threads > 1 {
+      std::thread::scope(|s| {
+        let observer = Box::new(SilentObserver);
+        let mut pool =
+          ThreadPool::new(s, self.threads, &tt, &global_nodes, observer);
+        pool.search(position, tc, DEFAULT_CONTEMPT, None)
+      })
+    } else {
+      let nodes = NodeCounter::new(&global_nodes);
+      let mut search_thread = SearchRunner::new(0, &tt, nodes);
+      search_thread.search::<NO_DEBUG>(position, tc)
+    }
+  }
+}
+
+/// Read a file of positions, one FEN per line. Empty lines and lines starting
+/// with a '#' are skipped.
+fn read_positions(path: &PathBuf) -> anyhow::Result<Vec<String>> {
+  let contents = std::fs::read_to_string(path)
+    .with_context(|| format!("Failed to read {}", path.display()))?;
+
+  let mut fens = Vec::new();
+
+  for (i, line) in contents.lines().enumerate() {
+    let line = line.trim();
+
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+
+    line
+      .parse::<Board>()
+      .with_context(|| format!("Invalid FEN on line {}: {line}", i + 1))?;
+
+    fens.push(String::from(line));
+  }
+
+  if fens.is_empty() {
+    return Err(anyhow!("No positions found in {}", path.display()));
+  }
+
+  Ok(fens)
+}
+
+/// Print the per-position results and the nps statistics to stderr
+fn print_report(report: &BenchReport) {
+  eprintln!(
+    "{:>4} {:>10} {:>10} {:>8} {:>10}",
+    "#", "nodes", "time (ms)", "move", "score"
+  );
+
+  for (i, position) in report.positions.iter().enumerate() {
+    eprintln!(
+      "{:>4} {:>10} {:>10.1} {:>8} {:>10}",
+      i + 1,
+      position.nodes,
+      position.time_ms,
+      position.best_move,
+      position.score
+    );
+  }
+
+  let total_time = report.positions.iter().map(|pos| pos.time_ms).sum::<f64>();
+  eprintln!();
+  eprintln!(
+    "depth {} hash {} threads {} ({}): {:.0} ms to depth",
+    report.depth, report.hash, report.threads, report.backend, total_time
+  );
+
+  if report.runs.len() > 1 {
+    eprintln!(
+      "nps over {} runs: mean {:.0} median {:.0} stddev {:.0}",
+      report.runs.len(),
+      report.nps.mean,
+      report.nps.median,
+      report.nps.stddev
+    );
+
+    if report.runs.iter().any(|run| run.nodes != report.nodes) {
+      eprintln!("warning: node counts differ between runs");
+    }
+  }
+}
+
+pub fn mean(values: &[f64]) -> f64 {
+  values.iter().sum::<f64>() / values.len().max(1) as f64
+}
+
+/// The sample variance of the values
+pub fn variance(values: &[f64]) -> f64 {
+  if values.len() < 2 {
+    return 0.0;
+  }
+
+  let mean = mean(values);
+  let sum_sq = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>();
+  sum_sq / (values.len() - 1) as f64
+}
+
+fn median(values: &mut [f64]) -> f64 {
+  if values.is_empty() {
+    return 0.0;
+  }
+
+  values.sort_by(|a, b| a.total_cmp(b));
+  let mid = values.len() / 2;
+
+  if values.len().is_multiple_of(2) {
+    (values[mid - 1] + values[mid]) / 2.0
+  } else {
+    values[mid]
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn mean_and_variance() {
+    let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+
+    assert_eq!(mean(&values), 5.0);
+    assert!((variance(&values) - 32.0 / 7.0).abs() < 1e-12);
+
+    assert_eq!(mean(&[]), 0.0);
+    assert_eq!(variance(&[3.0]), 0.0);
+  }
+
+  #[test]
+  fn median_of_odd_and_even_lengths() {
+    assert_eq!(median(&mut [5.0, 1.0, 3.0]), 3.0);
+    assert_eq!(median(&mut [4.0, 1.0, 3.0, 2.0]), 2.5);
+    assert_eq!(median(&mut []), 0.0);
   }
 }
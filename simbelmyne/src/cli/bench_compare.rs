@@ -0,0 +1,197 @@
+//! Compare the speed of this build against another binary
+//!
+//! Runs the default bench suite for both builds, alternating between them
+//! over a number of rounds, so that any drift in the machine's performance
+//! (thermal throttling, other processes, ...) affects both sides equally.
+//!
+//! Since a non-functional change shouldn't alter the search, both builds are
+//! expected to search the exact same number of nodes. If they don't, we bail
+//! out, since comparing their speed is meaningless.
+
+use super::bench::bench;
+use super::bench::mean;
+use super::bench::variance;
+use super::bench::BenchConfig;
+use anyhow::anyhow;
+use anyhow::Context;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The nodes and nps reported by a single bench run
+#[derive(Debug, Copy, Clone)]
+struct BenchRun {
+  nodes: u64,
+  nps: u64,
+}
+
+pub fn run_bench_compare(other: PathBuf, rounds: usize) -> anyhow::Result<()> {
+  if rounds < 2 {
+    return Err(anyhow!(
+      "Need at least 2 rounds to estimate the spread of the speedup"
+    ));
+  }
+
+  let mut speedups = Vec::new();
+
+  println!(
+    "{:>6} {:>12} {:>12} {:>9}",
+    "round", "local nps", "other nps", "speedup"
+  );
+
+  for round in 0..rounds {
+    // Alternate which binary goes first, so neither side consistently gets
+    // the benefit of a cooler machine.
+    let (local, other) = if round % 2 == 0 {
+      let local = run_local()?;
+      (local, run_other(&other)?)
+    } else {
+      let other = run_other(&other)?;
+      (run_local()?, other)
+    };
+
+    if local.nodes != other.nodes {
+      return Err(anyhow!(
+        "Node counts differ ({} local, {} other): the builds aren't \
+         functionally equivalent",
+        local.nodes,
+        other.nodes
+      ));
+    }
+
+    let speedup = local.nps as f64 / other.nps as f64;
+    speedups.push(speedup);
+
+    println!(
+      "{:>6} {:>12} {:>12} {:>8.2}%",
+      round + 1,
+      local.nps,
+      other.nps,
+      100.0 * (speedup - 1.0)
+    );
+  }
+
+  let mean = mean(&speedups);
+  let margin = t_value(speedups.len() - 1)
+    * (variance(&speedups) / speedups.len() as f64).sqrt();
+
+  println!();
+  println!("nodes: identical over {rounds} rounds");
+  println!(
+    "speedup: {:+.2}% ± {:.2}% (95% confidence)",
+    100.0 * (mean - 1.0),
+    100.0 * margin
+  );
+
+  Ok(())
+}
+
+/// Run the bench suite in this process
+fn run_local() -> anyhow::Result<BenchRun> {
+  let report = bench(&BenchConfig::default())?;
+  let run = &report.runs[0];
+
+  Ok(BenchRun {
+    nodes: run.nodes,
+    nps: run.nps,
+  })
+}
+
+/// Run the other binary's bench, and parse the OpenBench-style
+/// "<nodes> nodes <nps> nps" line it prints.
+fn run_other(binary: &PathBuf) -> anyhow::Result<BenchRun> {
+  let output = Command::new(binary)
+    .arg("bench")
+    .output()
+    .with_context(|| format!("Failed to run {}", binary.display()))?;
+
+  if !output.status.success() {
+    return Err(anyhow!(
+      "{} bench exited with {}",
+      binary.display(),
+      output.status
+    ));
+  }
+
+  let stdout = String::from_utf8_lossy(&output.stdout);
+
+  parse_bench_output(&stdout).ok_or_else(|| {
+    anyhow!("No bench result in the output of {}", binary.display())
+  })
+}
+
+/// Find the last bench result in a binary's output, skipping over whatever
+/// else it prints.
+fn parse_bench_output(output: &str) -> Option<BenchRun> {
+  output.lines().rev().find_map(parse_bench_line)
+}
+
+fn parse_bench_line(line: &str) -> Option<BenchRun> {
+  let mut parts = line.split_whitespace();
+  let nodes = parts.next()?.parse().ok()?;
+  (parts.next()? == "nodes").then_some(())?;
+  let nps = parts.next()?.parse().ok()?;
+  (parts.next()? == "nps").then_some(())?;
+
+  Some(BenchRun { nodes, nps })
+}
+
+/// The two-sided 95% critical value of Student's t-distribution, for the
+/// given degrees of freedom.
+fn t_value(df: usize) -> f64 {
+  const T_VALUES: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+    2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086,
+    2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+  ];
+
+  match df {
+    0 => f64::INFINITY,
+    1..=30 => T_VALUES[df - 1],
+    _ => 1.96,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_bench_lines() {
+    let run = parse_bench_line("3575808 nodes 1284182 nps").unwrap();
+    assert_eq!(run.nodes, 3575808);
+    assert_eq!(run.nps, 1284182);
+
+    assert!(parse_bench_line("3575808 nodes").is_none());
+    assert!(parse_bench_line("3575808 nps 1284182 nodes").is_none());
+    assert!(parse_bench_line("nodes 3575808 nps 1284182").is_none());
+  }
+
+  #[test]
+  fn parse_bench_output_with_noise() {
+    let output = "\
+      info string using pext\n\
+      depth 13 hash 16 threads 1 (pext): 2785 ms to depth\n\
+      3575808 nodes 1284182 nps\n\
+      warning: node counts differ between runs\n";
+
+    let run = parse_bench_output(output).unwrap();
+    assert_eq!(run.nodes, 3575808);
+    assert_eq!(run.nps, 1284182);
+
+    assert!(parse_bench_output("Unknown command: bench\n").is_none());
+  }
+
+  #[test]
+  fn t_values() {
+    assert_eq!(t_value(0), f64::INFINITY);
+    assert_eq!(t_value(1), 12.706);
+    assert_eq!(t_value(9), 2.262);
+    assert_eq!(t_value(30), 2.042);
+    assert_eq!(t_value(1000), 1.96);
+  }
+
+  #[test]
+  fn a_single_round_is_rejected() {
+    assert!(run_bench_compare(PathBuf::from("simbelmyne"), 1).is_err());
+  }
+}
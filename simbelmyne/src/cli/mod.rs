@@ -1,17 +1,31 @@
 use std::path::PathBuf;
 
+use self::annotate::run_annotate;
+use self::annotate::AnnotateConfig;
 use self::bench::run_bench;
+use self::bench::BenchConfig;
+use self::bench_compare::run_bench_compare;
 use self::perft::run_perft;
+use self::perft_debug::run_perft_debug;
+use self::play::run_play;
+use self::play::PlayConfig;
+use self::play::Side;
 use self::tune::run_tune;
+use self::wdl_fit::run_wdl_fit;
 use crate::spsa::run_openbench;
 use crate::spsa::run_weatherfactory;
 use clap::Subcommand;
 use divide::run_divide;
 
+pub mod annotate;
 pub mod bench;
+pub mod bench_compare;
 pub mod divide;
 pub mod perft;
+pub mod perft_debug;
+pub mod play;
 pub mod tune;
+pub mod wdl_fit;
 
 #[derive(Debug, Subcommand)]
 pub enum Command {
@@ -27,6 +41,20 @@ pub enum Command {
 
     #[arg(long)]
     all: bool,
+
+    /// The number of threads to split the root moves over
+    #[arg(short, long, value_name = "THREADS", default_value = "1")]
+    threads: usize,
+
+    /// The size of the perft hash table, in MB. A size of 0 disables the
+    /// table.
+    #[arg(long, value_name = "MB", default_value = "0")]
+    hash: usize,
+
+    /// Play the moves in-place with make/unmake, rather than copying the
+    /// board for every move
+    #[arg(long)]
+    make_unmake: bool,
   },
 
   /// Run the perft test suite
@@ -45,9 +73,68 @@ pub enum Command {
     fen: String,
   },
 
+  /// Compare perft results with a reference engine, and find the position
+  /// where the move generators disagree
+  PerftDebug {
+    /// The UCI engine to compare against
+    #[arg(short, long, value_name = "ENGINE")]
+    reference: PathBuf,
+
+    /// Set the search depth
+    #[arg(short, long, value_name = "DEPTH", default_value = "5")]
+    depth: usize,
+
+    /// The FEN string to start from
+    #[arg(
+      short,
+      long,
+      value_name = "FEN",
+      default_value = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    )]
+    fen: String,
+  },
+
   /// Run the bench suite and report the total number of nodes and average
   /// nps
-  Bench,
+  Bench {
+    /// The depth to search every position to
+    #[arg(short, long, value_name = "DEPTH", default_value_t = bench::DEPTH)]
+    depth: usize,
+
+    /// The size of the transposition table, in MB
+    #[arg(long, value_name = "MB", default_value_t = bench::HASH)]
+    hash: usize,
+
+    /// The number of search threads
+    #[arg(short, long, value_name = "THREADS", default_value = "1")]
+    threads: usize,
+
+    /// A file of positions to use instead of the built-in ones, one FEN per
+    /// line
+    #[arg(short, long, value_name = "FILE")]
+    file: Option<PathBuf>,
+
+    /// The number of times to run through the positions, to measure how
+    /// noisy the nps is
+    #[arg(short, long, value_name = "TIMES", default_value = "1")]
+    repeat: usize,
+
+    /// Output the results as JSON
+    #[arg(long)]
+    json: bool,
+  },
+
+  /// Compare the speed of this build against another binary, checking that
+  /// both search the same number of nodes
+  BenchCompare {
+    /// The binary to compare against
+    #[arg(value_name = "BINARY")]
+    other: PathBuf,
+
+    /// The number of bench runs for each binary (at least 2)
+    #[arg(short, long, value_name = "ROUNDS", default_value = "10")]
+    rounds: usize,
+  },
 
   /// Start a tuning run of all the evaluation weights
   Tune {
@@ -74,6 +161,87 @@ pub enum Command {
     zero: bool,
   },
 
+  /// Fit the WDL model to a set of positions annotated with scores and game
+  /// results, and output the fitted model
+  WdlFit {
+    /// The file of `<fen> | <score> | <result>` lines to fit against
+    #[arg(short, long, value_name = "FILE")]
+    file: PathBuf,
+
+    #[arg(short, long, value_name = "NUMBER")]
+    positions: Option<usize>,
+  },
+
+  /// Play a game against the engine in the terminal
+  Play {
+    /// The position to start the game from
+    #[arg(
+      short,
+      long,
+      value_name = "FEN",
+      default_value = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    )]
+    fen: String,
+
+    /// The side you want to play
+    #[arg(short, long, value_enum, default_value = "white")]
+    side: Side,
+
+    /// The time on each player's clock, in minutes. Without a clock, the
+    /// engine thinks for a fixed amount of time per move.
+    #[arg(short, long, value_name = "MINUTES")]
+    time: Option<f64>,
+
+    /// The increment per move, in seconds
+    #[arg(short, long, value_name = "SECONDS", default_value = "0")]
+    increment: f64,
+
+    /// Limit the engine's search depth
+    #[arg(short, long, value_name = "DEPTH")]
+    depth: Option<usize>,
+
+    /// The engine's thinking time per move, in seconds, when not playing
+    /// with a clock
+    #[arg(short, long, value_name = "SECONDS", default_value = "3")]
+    movetime: f64,
+
+    /// The file to save the game to, in PGN format
+    #[arg(short, long, value_name = "FILE")]
+    pgn: Option<PathBuf>,
+  },
+
+  /// Annotate the games in a PGN file with the engine's evaluations, and flag
+  /// inaccuracies, mistakes and blunders
+  Annotate {
+    /// The PGN file containing the games to annotate
+    #[arg(value_name = "PGN")]
+    file: PathBuf,
+
+    /// The file to write the annotated games to. Defaults to stdout.
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// The depth to search every position to (default: 12)
+    #[arg(short, long, value_name = "DEPTH", conflicts_with = "movetime")]
+    depth: Option<usize>,
+
+    /// The time to search every position for, in seconds
+    #[arg(short, long, value_name = "SECONDS")]
+    movetime: Option<f64>,
+
+    /// The loss, in centipawns, above which a move is an inaccuracy
+    #[arg(long, value_name = "CP", default_value = "50")]
+    inaccuracy: i32,
+
+    /// The loss, in centipawns, above which a move is a mistake
+    #[arg(long, value_name = "CP", default_value = "100")]
+    mistake: i32,
+
+    /// The loss, in centipawns, above which a move is a blunder
+    #[arg(long, value_name = "CP", default_value = "200")]
+    blunder: i32,
+  },
+
   /// Output all tunable UCI options in Openbench's SPSA format
   Openbench,
 
@@ -84,8 +252,20 @@ pub enum Command {
 impl Command {
   pub fn run(self) -> anyhow::Result<()> {
     match self {
-      Command::Perft { depth, fen, all } => run_perft(depth, fen, all)?,
+      Command::Perft {
+        depth,
+        fen,
+        all,
+        threads,
+        hash,
+        make_unmake,
+      } => run_perft(depth, fen, all, threads, hash, make_unmake)?,
       Command::Divide { fen, depth } => run_divide(fen, depth)?,
+      Command::PerftDebug {
+        reference,
+        depth,
+        fen,
+      } => run_perft_debug(reference, fen, depth)?,
       Command::Tune {
         file,
         positions,
@@ -94,7 +274,59 @@ impl Command {
         interval,
         zero,
       } => run_tune(file, positions, epochs, output, interval, zero),
-      Command::Bench => run_bench(),
+      Command::WdlFit { file, positions } => run_wdl_fit(file, positions)?,
+      Command::Play {
+        fen,
+        side,
+        time,
+        increment,
+        depth,
+        movetime,
+        pgn,
+      } => run_play(PlayConfig {
+        fen,
+        side,
+        time,
+        increment,
+        depth,
+        movetime,
+        pgn,
+      })?,
+      Command::Annotate {
+        file,
+        output,
+        depth,
+        movetime,
+        inaccuracy,
+        mistake,
+        blunder,
+      } => run_annotate(AnnotateConfig {
+        file,
+        output,
+        depth,
+        movetime,
+        inaccuracy,
+        mistake,
+        blunder,
+      })?,
+      Command::Bench {
+        depth,
+        hash,
+        threads,
+        file,
+        repeat,
+        json,
+      } => run_bench(BenchConfig {
+        depth,
+        hash,
+        threads,
+        file,
+        repeat,
+        json,
+      })?,
+      Command::BenchCompare { other, rounds } => {
+        run_bench_compare(other, rounds)?
+      }
       Command::Openbench => run_openbench(),
       Command::WeatherFactory => run_weatherfactory(),
     };
@@ -1,5 +1,9 @@
 use anyhow::*;
 use chess::board::Board;
+use chess::movegen::sliders;
+use chess::movegen::sliders::Sliders;
+use chess::movegen::sliders::WithSliders;
+use chess::perft::PerftTable;
 use colored::*;
 use std::time::Instant;
 
@@ -26,12 +30,58 @@ impl PerftResult {
   }
 }
 
+/// How to run the perft counts
+pub struct PerftConfig {
+  /// The number of threads to split the root moves over
+  pub threads: usize,
+
+  /// The table to cache subtree counts in, if any
+  pub table: Option<PerftTable>,
+
+  /// Whether to play the moves in-place, rather than copying the board
+  pub in_place: bool,
+}
+
+impl PerftConfig {
+  /// Count the leaf nodes at the given depth
+  pub fn count(&self, board: Board, depth: usize) -> u64 {
+    sliders::dispatch(PerftCount {
+      config: self,
+      board,
+      depth,
+    })
+  }
+}
+
+/// A perft count, compiled for each of the slider backends
+struct PerftCount<'a> {
+  config: &'a PerftConfig,
+  board: Board,
+  depth: usize,
+}
+
+impl WithSliders for PerftCount<'_> {
+  type Output = u64;
+
+  fn run<S: Sliders>(self) -> u64 {
+    let mut board = self.board.with_sliders::<S>();
+    let config = self.config;
+
+    if config.in_place {
+      board.perft_in_place(self.depth)
+    } else {
+      board.perft_parallel(self.depth, config.threads, config.table.as_ref())
+    }
+  }
+}
+
 pub fn perform_perft<const BULK: bool>(
   board: Board,
   depth: usize,
+  config: &PerftConfig,
 ) -> PerftResult {
   let start = Instant::now();
-  let nodes = board.perft(depth);
+  let nodes = config.count(board, depth);
   let duration = start.elapsed();
 
   return PerftResult {
@@ -46,17 +96,30 @@ pub fn run_perft(
   depth: usize,
   fen: Option<String>,
   all: bool,
+  threads: usize,
+  hash: usize,
+  in_place: bool,
 ) -> anyhow::Result<()> {
+  if in_place && (threads > 1 || hash > 0) {
+    bail!("In-place perft can't be combined with threads or a hash table");
+  }
+
+  let config = PerftConfig {
+    threads,
+    table: (hash > 0).then(|| PerftTable::new(hash)),
+    in_place,
+  };
+
   if all {
-    run_suite();
+    run_suite(&config);
   } else if let Some(fen) = fen {
-    run_fen(fen, depth)?;
+    run_fen(fen, depth, &config)?;
   }
 
   Ok(())
 }
 
-fn run_suite() {
+fn run_suite(config: &PerftConfig) {
   for entry in PERFT_RESULTS {
     let mut parts = entry.split(',');
     let fen = parts.next().unwrap();
@@ -66,7 +129,7 @@ fn run_suite() {
     print!("{:<100} ", fen.blue());
 
     for (i, &expected) in results.iter().enumerate() {
-      let found = board.perft(i + 1);
+      let found = config.count(board, i + 1);
       if found == expected {
         print!("{} ", found.to_string().green());
       } else {
@@ -78,14 +141,18 @@ fn run_suite() {
   }
 }
 
-fn run_fen(fen: String, depth: usize) -> anyhow::Result<()> {
+fn run_fen(
+  fen: String,
+  depth: usize,
+  config: &PerftConfig,
+) -> anyhow::Result<()> {
   let board: Board = fen.parse().unwrap();
 
   println!("{}: {}", "FEN".green(), fen.italic());
   println!("{}:\n\n{board}\n\n", "Board".green());
 
   for depth in 0..=depth {
-    let result = perform_perft::<BULK>(board, depth);
+    let result = perform_perft::<BULK>(board, depth, config);
 
     print!("Depth {}: ", depth.to_string().blue());
 
@@ -0,0 +1,264 @@
+//! Find move generation bugs by comparing perft results with another engine
+//!
+//! We run a perft divide on both engines, and look for the first root move
+//! where the node counts disagree. We then play that move, and repeat the
+//! process one ply deeper. Eventually, we end up in a position where the two
+//! engines disagree on the legal moves themselves, which is the position we
+//! report.
+//!
+//! The reference engine is driven over UCI, and needs to support the
+//! (non-standard, but widespread) `go perft <depth>` command.
+
+use anyhow::anyhow;
+use anyhow::Context;
+use chess::board::Board;
+use chess::movegen::legal_moves::All;
+use chess::movegen::moves::BareMove;
+use chess::movegen::moves::Move;
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Child;
+use std::process::ChildStdin;
+use std::process::ChildStdout;
+use std::process::Command;
+use std::process::Stdio;
+use uci::client::UciClientMessage;
+
+/// The node counts for every root move, keyed by the move in UCI notation
+type Divide = BTreeMap<String, u64>;
+
+pub fn run_perft_debug(
+  reference: PathBuf,
+  fen: String,
+  depth: usize,
+) -> anyhow::Result<()> {
+  let mut board: Board = fen.parse()?;
+  let mut engine = ReferenceEngine::new(&reference)?;
+  let mut moves: Vec<Move> = Vec::new();
+
+  for depth in (1..=depth).rev() {
+    let ours = divide(&board, depth);
+    let theirs = engine.divide(&board, depth)?;
+
+    let missing = diff_keys(&theirs, &ours);
+    let extra = diff_keys(&ours, &theirs);
+
+    // The engines disagree on the legal moves: we found our culprit.
+    if !missing.is_empty() || !extra.is_empty() {
+      print_culprit(&fen, &moves, &board);
+
+      if !missing.is_empty() {
+        println!(
+          "{}: {}",
+          "Only generated by the reference".red(),
+          missing.join(" ")
+        );
+      }
+
+      if !extra.is_empty() {
+        println!("{}: {}", "Only generated by us".red(), extra.join(" "));
+      }
+
+      return Ok(());
+    }
+
+    // Otherwise, descend into the first move with mismatching counts
+    let mismatch = ours.iter().find(|(mv, nodes)| theirs[*mv] != **nodes);
+
+    let Some((mv, nodes)) = mismatch else {
+      if moves.is_empty() {
+        println!("{}", "No differences found".green());
+        return Ok(());
+      }
+
+      // The counts for the individual moves agree, even though they didn't
+      // one ply up. This can only happen if the engines don't agree on the
+      // position itself, e.g., because of a FEN parsing bug.
+      print_culprit(&fen, &moves, &board);
+      println!(
+        "{}",
+        "The divides agree, the engines may disagree on the position".red()
+      );
+      return Ok(());
+    };
+
+    println!(
+      "Depth {depth}: {mv} has {} nodes, reference has {}",
+      nodes.to_string().red(),
+      theirs[mv].to_string().green()
+    );
+
+    let mv = board
+      .legal_moves::<All>()
+      .into_iter()
+      .find(|legal| legal.to_string() == *mv)
+      .expect("Divide only contains legal moves");
+
+    board = board.play_move(mv);
+    moves.push(mv);
+  }
+
+  Ok(())
+}
+
+/// Run a perft divide with our own move generator
+fn divide(board: &Board, depth: usize) -> Divide {
+  board
+    .perft_divide(depth)
+    .into_iter()
+    .map(|(mv, nodes)| (mv.to_string(), nodes))
+    .collect()
+}
+
+/// The keys in `a` that aren't in `b`
+fn diff_keys(a: &Divide, b: &Divide) -> Vec<String> {
+  a.keys()
+    .filter(|mv| !b.contains_key(*mv))
+    .cloned()
+    .collect()
+}
+
+fn print_culprit(fen: &str, moves: &[Move], board: &Board) {
+  let moves = moves.iter().map(Move::to_string).collect::<Vec<_>>();
+
+  println!();
+  println!("{}: {}", "Start".green(), fen.italic());
+  println!("{}: {}", "Moves".green(), moves.join(" "));
+  println!("{}: {}", "FEN".green(), board.to_fen().italic());
+  println!("{}:\n\n{board}\n", "Board".green());
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Reference engine
+//
+////////////////////////////////////////////////////////////////////////////////
+
+/// An engine running in a child process, that we talk to over UCI
+struct ReferenceEngine {
+  process: Child,
+  stdin: ChildStdin,
+  stdout: BufReader<ChildStdout>,
+}
+
+impl ReferenceEngine {
+  /// Start the engine, and wait for it to be ready
+  fn new(path: &PathBuf) -> anyhow::Result<Self> {
+    let mut process = Command::new(path)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::null())
+      .spawn()
+      .with_context(|| format!("Failed to start {}", path.display()))?;
+
+    let stdin = process.stdin.take().expect("stdin is piped");
+    let stdout =
+      BufReader::new(process.stdout.take().expect("stdout is piped"));
+
+    let mut engine = Self {
+      process,
+      stdin,
+      stdout,
+    };
+
+    engine.send(UciClientMessage::Uci)?;
+    engine.read_until("uciok")?;
+
+    Ok(engine)
+  }
+
+  /// Run a perft divide on the reference engine
+  fn divide(&mut self, board: &Board, depth: usize) -> anyhow::Result<Divide> {
+    self.send(UciClientMessage::Position(*board, Vec::new()))?;
+    self.send(UciClientMessage::GoPerft(depth))?;
+
+    // Perft runs synchronously, so the `readyok` only comes in once the
+    // divide has been printed.
+    self.send(UciClientMessage::IsReady)?;
+
+    Ok(parse_divide(&self.read_until("readyok")?))
+  }
+
+  fn send(&mut self, msg: UciClientMessage) -> anyhow::Result<()> {
+    writeln!(self.stdin, "{}", msg.to_string().trim_end())?;
+    Ok(())
+  }
+
+  /// Read lines until we find the message we're looking for, and return all
+  /// the lines that came before it.
+  fn read_until(&mut self, msg: &str) -> anyhow::Result<Vec<String>> {
+    let mut lines = Vec::new();
+
+    loop {
+      let mut line = String::new();
+
+      if self.stdout.read_line(&mut line)? == 0 {
+        return Err(anyhow!("The reference engine exited unexpectedly"));
+      }
+
+      if line.trim() == msg {
+        return Ok(lines);
+      }
+
+      lines.push(line);
+    }
+  }
+}
+
+/// Parse the `<move>: <nodes>` lines of a perft divide. Anything else the
+/// engine prints along with it (e.g., Stockfish's `Nodes searched: <total>`)
+/// is ignored.
+fn parse_divide(lines: &[String]) -> Divide {
+  let mut divide = Divide::new();
+
+  for line in lines {
+    let Some((mv, nodes)) = line.split_once(':') else {
+      continue;
+    };
+
+    let mv = mv.trim();
+
+    if mv.parse::<BareMove>().is_err() {
+      continue;
+    }
+
+    if let Ok(nodes) = nodes.trim().parse() {
+      divide.insert(mv.to_string(), nodes);
+    }
+  }
+
+  divide
+}
+
+impl Drop for ReferenceEngine {
+  fn drop(&mut self) {
+    let _ = self.send(UciClientMessage::Quit);
+    let _ = self.process.wait();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_stockfish_divide() {
+    let output = "info string NNUE evaluation using nn-b1a57edbea57.nnue\n\
+                  a2a3: 380\n\
+                  b1c3: 440\n\
+                  e7e8q: 12\n\
+                  \n\
+                  Nodes searched: 832";
+    let lines = output.lines().map(String::from).collect::<Vec<_>>();
+    let divide = parse_divide(&lines);
+
+    assert_eq!(divide.len(), 3);
+    assert_eq!(divide["a2a3"], 380);
+    assert_eq!(divide["b1c3"], 440);
+    assert_eq!(divide["e7e8q"], 12);
+  }
+}
@@ -0,0 +1,502 @@
+//! Play a game against the engine in the terminal.
+//!
+//! Moves can be entered either in SAN (`Nf3`) or in UCI notation (`g1f3`).
+//! Besides moves, a handful of commands are supported to take back moves,
+//! flip the board, change the engine settings, etc. (see `HELP`).
+
+use crate::uci::NAME;
+use crate::uci::VERSION;
+use anyhow::Context;
+use chess::board::Board;
+use chess::game::Game;
+use chess::game::GameOutcome;
+use chess::movegen::moves::BareMove;
+use chess::movegen::moves::Move;
+use chess::pgn::Pgn;
+use chess::piece::Color;
+use chess::san::ToSan;
+use clap::ValueEnum;
+use colored::Colorize;
+use engine::evaluate::pretty_print::print_eval;
+use engine::position::Position;
+use engine::search::NodeCounter;
+use engine::search::SearchReport;
+use engine::search::SearchRunner;
+use engine::time_control::TimeController;
+use engine::transpositions::TTable;
+use std::fmt::Display;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU32;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+use uci::time_control::TimeControl;
+
+const DEBUG: bool = true;
+
+/// The size of the engine's transposition table, in MB
+const TT_SIZE: usize = 64;
+
+const HELP: &str = "
+  <move>            Play a move, in SAN (Nf3) or UCI (g1f3) notation
+  undo              Take back your last move
+  flip              Flip the board
+  switch            Switch sides with the engine
+  depth <n|off>     Limit the engine's search depth
+  movetime <secs>   Set the engine's thinking time, when not playing on a clock
+  eval              Print the static evaluation of the current position
+  pgn               Print the game so far
+  resign            Resign the game
+  quit              Quit without finishing the game
+  help              Show this message
+";
+
+/// The side the user wants to play
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum Side {
+  White,
+  Black,
+}
+
+impl From<Side> for Color {
+  fn from(side: Side) -> Self {
+    match side {
+      Side::White => Color::White,
+      Side::Black => Color::Black,
+    }
+  }
+}
+
+/// The settings for a game, as passed on the command line
+pub struct PlayConfig {
+  pub fen: String,
+  pub side: Side,
+  pub time: Option<f64>,
+  pub increment: f64,
+  pub depth: Option<usize>,
+  pub movetime: f64,
+  pub pgn: Option<PathBuf>,
+}
+
+/// The reason a game ended: either one of the ways the rules of chess end a
+/// game, or something that happened over the board.
+enum Ending {
+  Outcome(GameOutcome),
+  Timeout(Color),
+  Resignation(Color),
+  Aborted,
+}
+
+impl Ending {
+  /// The game result in PGN notation
+  fn result(&self) -> &'static str {
+    match self {
+      Ending::Outcome(outcome) => outcome.result(),
+
+      Ending::Timeout(loser) | Ending::Resignation(loser) => {
+        if loser.is_white() {
+          "0-1"
+        } else {
+          "1-0"
+        }
+      }
+
+      Ending::Aborted => "*",
+    }
+  }
+}
+
+impl Display for Ending {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let name = |side: &Color| if side.is_white() { "White" } else { "Black" };
+
+    match self {
+      Ending::Outcome(outcome) => write!(f, "{outcome}"),
+      Ending::Timeout(loser) => write!(f, "{} wins on time", name(&!*loser)),
+      Ending::Resignation(loser) => write!(f, "{} resigns", name(loser)),
+      Ending::Aborted => write!(f, "Game aborted"),
+    }
+  }
+}
+
+/// A game against the engine, along with the settings it's played with
+struct Session {
+  /// The game so far
+  game: Game,
+
+  /// The side the user is playing
+  user: Color,
+
+  /// Whether to show the board from Black's side
+  flipped: bool,
+
+  /// The time each side started out with, when playing with a clock
+  base_time: Option<Duration>,
+
+  /// The time left on each side's clock, when playing with a clock
+  clocks: Option<[Duration; Color::COUNT]>,
+
+  /// The increment per move
+  increment: Duration,
+
+  /// The maximum depth the engine is allowed to search to
+  depth: Option<usize>,
+
+  /// The time the engine gets per move when not playing with a clock
+  movetime: Duration,
+}
+
+pub fn run_play(config: PlayConfig) -> anyhow::Result<()> {
+  let board: Board = config.fen.parse()?;
+  let user = Color::from(config.side);
+
+  let base_time = match config.time {
+    Some(minutes) => {
+      Some(Duration::try_from_secs_f64(60.0 * minutes).context("Invalid time")?)
+    }
+    None => None,
+  };
+
+  let mut game = Session {
+    game: Game::new(board),
+    user,
+    flipped: !user.is_white(),
+    base_time,
+    clocks: base_time.map(|time| [time; Color::COUNT]),
+    increment: Duration::try_from_secs_f64(config.increment)
+      .context("Invalid increment")?,
+    depth: config.depth,
+    movetime: Duration::try_from_secs_f64(config.movetime)
+      .context("Invalid movetime")?,
+  };
+
+  let tt = TTable::with_capacity(TT_SIZE);
+  let global_nodes = AtomicU32::new(0);
+  let mut runner = SearchRunner::new(0, &tt, NodeCounter::new(&global_nodes));
+  let mut input = std::io::stdin().lines();
+
+  println!("Type {} for a list of commands.", "help".blue());
+
+  let ending = loop {
+    if let Some(ending) = game.ending() {
+      break ending;
+    }
+
+    let current = game.board().current;
+
+    // The engine's turn
+    if current != game.user {
+      println!("{}", game.board().pretty(game.flipped));
+
+      let report = game.search(&mut runner);
+      let mv = report.pv[0];
+
+      if game.tick(current, report.duration) {
+        break Ending::Timeout(current);
+      }
+
+      println!("{} plays {}", NAME.blue(), game.san(mv).bold());
+      game.play(mv);
+      continue;
+    }
+
+    // The user's turn
+    println!("{}", game.board().pretty(game.flipped));
+    game.print_clocks();
+
+    let start = Instant::now();
+
+    let input = loop {
+      print!("{} ", "Your move:".green());
+      std::io::stdout().flush()?;
+
+      // Treat a closed stdin as a request to quit
+      let Some(line) = input.next() else {
+        break Input::Quit;
+      };
+
+      match game.handle_input(line?.trim()) {
+        Input::None => continue,
+        input => break input,
+      }
+    };
+
+    match input {
+      Input::Move(mv) => {
+        if game.tick(current, start.elapsed()) {
+          break Ending::Timeout(current);
+        }
+
+        game.play(mv);
+      }
+
+      Input::Resign => break Ending::Resignation(game.user),
+      Input::Quit => break Ending::Aborted,
+      Input::Redraw | Input::None => {}
+    }
+  };
+
+  println!("{}", game.board().pretty(game.flipped));
+  println!("{} ({ending})", ending.result().bold());
+
+  let pgn = game.pgn(&ending);
+  println!("\n{pgn}");
+
+  if let Some(path) = config.pgn {
+    std::fs::write(&path, pgn.to_string())?;
+    println!("Saved game to {}", path.display().to_string().blue());
+  }
+
+  Ok(())
+}
+
+/// The outcome of processing a line of user input
+enum Input {
+  /// The user entered a legal move
+  Move(Move),
+
+  /// Something changed, and we should redraw the board
+  Redraw,
+
+  /// The user resigned
+  Resign,
+
+  /// The user wants to quit
+  Quit,
+
+  /// Nothing to do, just ask for more input
+  None,
+}
+
+impl Session {
+  /// The current position
+  fn board(&self) -> &Board {
+    self.game.board()
+  }
+
+  fn san(&self, mv: Move) -> String {
+    mv.to_san(self.board())
+  }
+
+  fn play(&mut self, mv: Move) {
+    self.game.play_move(mv);
+  }
+
+  /// Take back the last move, if any
+  fn undo(&mut self) -> bool {
+    self.game.undo_move().is_some()
+  }
+
+  /// Update the clock for the side that just moved. Returns whether that
+  /// side's flag fell.
+  fn tick(&mut self, side: Color, elapsed: Duration) -> bool {
+    let Some(clocks) = self.clocks.as_mut() else {
+      return false;
+    };
+
+    let clock = &mut clocks[side as usize];
+
+    if elapsed > *clock {
+      *clock = Duration::ZERO;
+      return true;
+    }
+
+    *clock = *clock - elapsed + self.increment;
+    false
+  }
+
+  fn print_clocks(&self) {
+    let Some(clocks) = self.clocks else {
+      return;
+    };
+
+    let format_clock = |clock: Duration| {
+      let secs = clock.as_secs();
+      format!("{}:{:0>2}", secs / 60, secs % 60)
+    };
+
+    println!(
+      "{} {}   {} {}",
+      "White".bright_black(),
+      format_clock(clocks[Color::White as usize]).bold(),
+      "Black".bright_black(),
+      format_clock(clocks[Color::Black as usize]).bold(),
+    );
+  }
+
+  /// Check whether the game is over. Draws that could be claimed are claimed
+  /// right away.
+  fn ending(&self) -> Option<Ending> {
+    self.game.outcome().map(Ending::Outcome)
+  }
+
+  /// Search the current position with the engine's settings
+  fn search(&self, runner: &mut SearchRunner) -> SearchReport {
+    let side = self.board().current;
+
+    let tc = match (self.clocks, self.depth) {
+      (Some(clocks), _) => TimeControl::Clock {
+        wtime: clocks[Color::White as usize],
+        btime: clocks[Color::Black as usize],
+        winc: Some(self.increment),
+        binc: Some(self.increment),
+        movestogo: None,
+      },
+
+      (None, Some(depth)) => TimeControl::Depth(depth),
+      (None, None) => TimeControl::FixedTime(self.movetime),
+    };
+
+    let (mut tc, _) = TimeController::new(tc, side);
+
+    if let Some(depth) = self.depth {
+      tc.limit_depth(depth);
+    }
+
+    runner.tt.increment_age();
+    runner.nodes.clear_global();
+    runner.search::<DEBUG>(Position::from_game(&self.game), tc)
+  }
+
+  /// Handle a line of user input
+  fn handle_input(&mut self, input: &str) -> Input {
+    let (cmd, arg) = input.split_once(' ').unwrap_or((input, ""));
+
+    match cmd {
+      "" => Input::None,
+
+      "help" => {
+        println!("{HELP}");
+        Input::None
+      }
+
+      // Take back moves until it's the user's turn again
+      "undo" | "takeback" => {
+        if !self.undo() {
+          println!("{}", "Nothing to take back".red());
+          return Input::None;
+        }
+
+        if self.board().current != self.user {
+          self.undo();
+        }
+
+        Input::Redraw
+      }
+
+      "flip" => {
+        self.flipped = !self.flipped;
+        Input::Redraw
+      }
+
+      "switch" => {
+        self.user = !self.user;
+        Input::Redraw
+      }
+
+      "depth" => {
+        match arg {
+          "off" => self.depth = None,
+          depth => match depth.parse() {
+            Ok(depth) => self.depth = Some(depth),
+            Err(_) => println!("{}", "Invalid depth".red()),
+          },
+        }
+
+        Input::None
+      }
+
+      "movetime" => {
+        let secs = arg.parse::<f64>().ok().filter(|&secs| secs > 0.0);
+
+        match secs.and_then(|secs| Duration::try_from_secs_f64(secs).ok()) {
+          Some(movetime) => self.movetime = movetime,
+          None => println!("{}", "Invalid movetime".red()),
+        }
+
+        Input::None
+      }
+
+      "eval" => {
+        println!("{}", print_eval(self.board()));
+        Input::None
+      }
+
+      "pgn" => {
+        println!("{}", self.pgn(&Ending::Aborted));
+        Input::None
+      }
+
+      "resign" => Input::Resign,
+      "quit" | "exit" => Input::Quit,
+
+      // Anything else, we try to parse as a move
+      _ => match self.parse_move(input) {
+        Some(mv) => Input::Move(mv),
+        None => {
+          println!("{} {input}", "Illegal move:".red());
+          Input::None
+        }
+      },
+    }
+  }
+
+  /// Parse a move in either SAN or UCI notation
+  fn parse_move(&self, input: &str) -> Option<Move> {
+    let board = self.board();
+
+    Move::from_san(input, board).or_else(|| {
+      let bare: BareMove = input.parse().ok()?;
+      board.find_move(bare)
+    })
+  }
+
+  /// Compile the game so far into a PGN
+  fn pgn(&self, ending: &Ending) -> Pgn {
+    let engine = format!("{NAME} {VERSION}");
+    let (white, black) = if self.user.is_white() {
+      ("Human", engine.as_str())
+    } else {
+      (engine.as_str(), "Human")
+    };
+
+    let mut pgn = Pgn::new(*self.game.initial());
+    pgn.moves = self.game.moves().to_vec();
+    pgn.set_tag("Event", "Casual game");
+    pgn.set_tag("Date", &today());
+    pgn.set_tag("White", white);
+    pgn.set_tag("Black", black);
+    pgn.set_tag("Result", ending.result());
+
+    if let Some(base_time) = self.base_time {
+      let base = base_time.as_secs();
+      let inc = self.increment.as_secs();
+      pgn.set_tag("TimeControl", &format!("{base}+{inc}"));
+    }
+
+    pgn
+  }
+}
+
+/// Today's date, in PGN's `YYYY.MM.DD` format
+fn today() -> String {
+  let Ok(elapsed) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)
+  else {
+    return "????.??.??".to_string();
+  };
+
+  // Convert the number of days since the epoch into a civil date
+  // (See http://howardhinnant.github.io/date_algorithms.html#civil_from_days)
+  let days = (elapsed.as_secs() / 86400) as i64 + 719468;
+  let era = days / 146097;
+  let doe = days - era * 146097;
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = doy - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+  format!("{year}.{month:0>2}.{day:0>2}")
+}
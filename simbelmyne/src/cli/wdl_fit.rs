@@ -0,0 +1,403 @@
+//! Fit the parameters of the WDL model to a set of scored positions.
+//!
+//! The WDL model predicts the win/draw/loss probabilities for a given score
+//! as
+//!
+//! ```text
+//! W = 1 / (1 + exp((a - score) / b))
+//! L = 1 / (1 + exp((a + score) / b))
+//! D = 1 - W - L
+//! ```
+//!
+//! where `a` and `b` are cubic polynomials in the material left on the board.
+//!
+//! The fit proceeds in two steps:
+//!
+//! 1. For every material count, find the `a` and `b` that maximize the
+//!    likelihood of the observed game results.
+//! 2. Fit cubic polynomials through the per-material `a`s and `b`s, weighting
+//!    every material count by the number of positions we have for it.
+//!
+//! The input is expected to contain one position per line, in the format
+//! `<fen> | <score> | <result>`, where both the score (in internal, unscaled,
+//! units) and the result (1.0, 0.5, 0.0) are from White's perspective.
+//!
+//! We don't produce this format ourselves. A build with the `wdl` feature
+//! enabled reports unscaled scores over UCI, so self-play games between such
+//! builds (where the GUI records the scores in the PGN) can be turned into
+//! this format with external tools.
+
+use anyhow::anyhow;
+use chess::board::Board;
+use colored::Colorize;
+use rayon::iter::ParallelBridge;
+use rayon::iter::ParallelIterator;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::path::PathBuf;
+use uci::wdl::WdlModel;
+use uci::wdl::WdlParams;
+use uci::wdl::WDL_MODEL;
+
+/// Scores beyond this value are almost certainly decided, and only add noise
+/// to the fit.
+const MAX_SCORE: i32 = 2000;
+
+/// Material counts with fewer positions than this are left out of the
+/// polynomial fit.
+const MIN_POSITIONS: u64 = 100;
+
+/// The number of gradient steps to take when fitting `a` and `b` for a given
+/// material count.
+const ITERATIONS: usize = 2000;
+
+/// The learning rate for the (Adam) optimizer
+const LRATE: f64 = 1.0;
+
+pub fn run_wdl_fit(
+  file: PathBuf,
+  positions: Option<usize>,
+) -> anyhow::Result<()> {
+  eprintln!("Loading input from {}... ", file.to_str().unwrap().blue());
+
+  let lines = BufReader::new(File::open(&file)?)
+    .lines()
+    .take(positions.unwrap_or(usize::MAX))
+    .map_while(Result::ok)
+    .collect::<Vec<_>>();
+
+  let entries = lines
+    .into_iter()
+    .par_bridge()
+    .filter_map(|line| parse_line(&line).ok())
+    .filter(|&(_, score, _)| score.abs() <= MAX_SCORE)
+    .collect::<Vec<_>>();
+
+  eprintln!("Loaded {} positions", entries.len().to_string().blue());
+
+  // Aggregate the positions by material count, score and outcome, so we
+  // don't need to iterate over every single position in every step of the
+  // fit.
+  let mut buckets: HashMap<u32, Bucket> = HashMap::new();
+
+  for (mat, score, outcome) in entries {
+    buckets.entry(mat).or_default().add(score, outcome);
+  }
+
+  // Fit `a` and `b` for every material count separately
+  let mut fits = buckets
+    .iter()
+    .filter(|(_, bucket)| bucket.total >= MIN_POSITIONS)
+    .map(|(&mat, bucket)| {
+      let init = WDL_MODEL.params_for_material(mat);
+      (mat, bucket.total, bucket.fit(init))
+    })
+    .collect::<Vec<_>>();
+
+  fits.sort_by_key(|&(mat, _, _)| mat);
+
+  if fits.len() < 4 {
+    Err(anyhow!(
+      "Not enough data to fit the model: need at least 4 material counts \
+       with at least {MIN_POSITIONS} positions each"
+    ))?
+  }
+
+  for &(mat, total, params) in fits.iter() {
+    eprintln!(
+      "material {:>2}: a = {:>8.2}, b = {:>8.2} ({} positions)",
+      mat.to_string().blue(),
+      params.a,
+      params.b,
+      total
+    );
+  }
+
+  // Fit the cubic polynomials through the per-material values
+  let xs = fits
+    .iter()
+    .map(|&(mat, _, _)| mat as f64 / WdlModel::NORM_MATERIAL as f64)
+    .collect::<Vec<_>>();
+  let weights = fits
+    .iter()
+    .map(|&(_, total, _)| total as f64)
+    .collect::<Vec<_>>();
+  let a_s = fits.iter().map(|(_, _, p)| p.a).collect::<Vec<_>>();
+  let b_s = fits.iter().map(|(_, _, p)| p.b).collect::<Vec<_>>();
+
+  let model = WdlModel {
+    a: fit_cubic(&xs, &a_s, &weights)?,
+    b: fit_cubic(&xs, &b_s, &weights)?,
+  };
+
+  let normalization = model.a.iter().sum::<f64>().round();
+
+  println!("// NormalizeToPawnValue = {normalization}");
+  println!("pub const WDL_MODEL: WdlModel = WdlModel {{");
+  println!("  a: [{}],", format_coeffs(&model.a));
+  println!("  b: [{}],", format_coeffs(&model.b));
+  println!("}};");
+
+  Ok(())
+}
+
+fn format_coeffs(coeffs: &[f64; 4]) -> String {
+  coeffs
+    .iter()
+    .map(|c| format!("{c:.8}"))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Parsing
+//
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Outcome {
+  Win,
+  Draw,
+  Loss,
+}
+
+/// Parse an input line into a (material, score, outcome) triple
+fn parse_line(line: &str) -> anyhow::Result<(u32, i32, Outcome)> {
+  let mut parts = line.split('|').map(str::trim);
+
+  let fen = parts.next().ok_or(anyhow!("Missing FEN: {line}"))?;
+  let score = parts.next().ok_or(anyhow!("Missing score: {line}"))?;
+  let result = parts.next().ok_or(anyhow!("Missing result: {line}"))?;
+
+  let board: Board = fen.parse()?;
+  let score: i32 = score.parse()?;
+
+  let outcome = match result {
+    "1.0" | "1" | "1-0" | "[1.0]" => Outcome::Win,
+    "0.5" | "1/2-1/2" | "[0.5]" => Outcome::Draw,
+    "0.0" | "0" | "0-1" | "[0.0]" => Outcome::Loss,
+    _ => Err(anyhow!("Invalid result: {line}"))?,
+  };
+
+  Ok((WdlModel::material(&board), score, outcome))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Per-material maximum likelihood fit
+//
+////////////////////////////////////////////////////////////////////////////////
+
+/// The number of wins, draws and losses observed for every score, for a
+/// given material count.
+#[derive(Debug, Default)]
+struct Bucket {
+  counts: HashMap<i32, [u64; 3]>,
+  total: u64,
+}
+
+impl Bucket {
+  fn add(&mut self, score: i32, outcome: Outcome) {
+    self.counts.entry(score).or_default()[outcome as usize] += 1;
+    self.total += 1;
+  }
+
+  /// Return the negative log-likelihood of the observed outcomes, and its
+  /// gradient with respect to `a` and `b`, normalized by the number of
+  /// positions.
+  fn loss(&self, params: WdlParams) -> (f64, f64, f64) {
+    let WdlParams { a, b } = params;
+    let mut loss = 0.0;
+    let mut grad_a = 0.0;
+    let mut grad_b = 0.0;
+
+    for (&score, &[wins, draws, losses]) in self.counts.iter() {
+      let score = score as f64;
+      let win = sigmoid((score - a) / b);
+      let loss_ = sigmoid((-score - a) / b);
+      let draw = (1.0 - win - loss_).max(f64::EPSILON);
+
+      // Derivatives of the win and loss probabilities w.r.t. a and b
+      let dwin_da = -win * (1.0 - win) / b;
+      let dwin_db = -win * (1.0 - win) * (score - a) / (b * b);
+      let dloss_da = -loss_ * (1.0 - loss_) / b;
+      let dloss_db = -loss_ * (1.0 - loss_) * (-score - a) / (b * b);
+
+      let (wins, draws, losses) = (wins as f64, draws as f64, losses as f64);
+
+      loss -= wins * win.ln() + draws * draw.ln() + losses * loss_.ln();
+
+      grad_a -= wins * dwin_da / win + losses * dloss_da / loss_
+        - draws * (dwin_da + dloss_da) / draw;
+
+      grad_b -= wins * dwin_db / win + losses * dloss_db / loss_
+        - draws * (dwin_db + dloss_db) / draw;
+    }
+
+    let total = self.total as f64;
+    (loss / total, grad_a / total, grad_b / total)
+  }
+
+  /// Find the `a` and `b` that maximize the likelihood of the observed
+  /// outcomes, starting from an initial guess, using Adam.
+  fn fit(&self, init: WdlParams) -> WdlParams {
+    const B1: f64 = 0.9;
+    const B2: f64 = 0.999;
+    const EPS: f64 = 1e-8;
+
+    let mut params = WdlParams {
+      a: init.a,
+      b: init.b.max(1.0),
+    };
+
+    let mut momenta = [0.0; 2];
+    let mut velocities = [0.0; 2];
+
+    for t in 1..=ITERATIONS {
+      let (_, grad_a, grad_b) = self.loss(params);
+      let grad = [grad_a, grad_b];
+
+      for i in 0..2 {
+        momenta[i] = B1 * momenta[i] + (1.0 - B1) * grad[i];
+        velocities[i] = B2 * velocities[i] + (1.0 - B2) * grad[i] * grad[i];
+      }
+
+      let step = |i: usize| {
+        let m = momenta[i] / (1.0 - B1.powi(t as i32));
+        let v = velocities[i] / (1.0 - B2.powi(t as i32));
+        LRATE * m / (v.sqrt() + EPS)
+      };
+
+      params.a -= step(0);
+      params.b = (params.b - step(1)).max(1.0);
+    }
+
+    params
+  }
+}
+
+fn sigmoid(x: f64) -> f64 {
+  1.0 / (1.0 + f64::exp(-x))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Polynomial fit
+//
+////////////////////////////////////////////////////////////////////////////////
+
+/// Fit a cubic polynomial through the provided points by weighted least
+/// squares.
+///
+/// The coefficients are returned from the highest order down, to match the
+/// Horner scheme used in `WdlModel::params`.
+fn fit_cubic(
+  xs: &[f64],
+  ys: &[f64],
+  weights: &[f64],
+) -> anyhow::Result<[f64; 4]> {
+  // Set up the normal equations (X^T W X) c = X^T W y
+  let mut lhs = [[0.0; 4]; 4];
+  let mut rhs = [0.0; 4];
+
+  for ((&x, &y), &w) in xs.iter().zip(ys).zip(weights) {
+    let powers = [x * x * x, x * x, x, 1.0];
+
+    for i in 0..4 {
+      for j in 0..4 {
+        lhs[i][j] += w * powers[i] * powers[j];
+      }
+
+      rhs[i] += w * powers[i] * y;
+    }
+  }
+
+  // Solve by Gaussian elimination with partial pivoting
+  for col in 0..4 {
+    let pivot = (col..4)
+      .max_by(|&i, &j| lhs[i][col].abs().total_cmp(&lhs[j][col].abs()))
+      .unwrap();
+
+    if lhs[pivot][col].abs() < f64::EPSILON {
+      Err(anyhow!("Failed to fit polynomial: singular system"))?
+    }
+
+    lhs.swap(col, pivot);
+    rhs.swap(col, pivot);
+
+    for row in col + 1..4 {
+      let factor = lhs[row][col] / lhs[col][col];
+
+      let pivot_row = lhs[col];
+
+      for (entry, pivot) in lhs[row].iter_mut().zip(pivot_row).skip(col) {
+        *entry -= factor * pivot;
+      }
+
+      rhs[row] -= factor * rhs[col];
+    }
+  }
+
+  let mut coeffs = [0.0; 4];
+
+  for row in (0..4).rev() {
+    let known = (row + 1..4).map(|k| lhs[row][k] * coeffs[k]).sum::<f64>();
+    coeffs[row] = (rhs[row] - known) / lhs[row][row];
+  }
+
+  Ok(coeffs)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn max_likelihood_fit_recovers_parameters() {
+    let truth = WdlParams { a: 250.0, b: 70.0 };
+    let mut bucket = Bucket::default();
+
+    // Add the expected number of wins, draws and losses for every score
+    for score in (-800..=800).step_by(10) {
+      let x = score as f64;
+      let win = sigmoid((x - truth.a) / truth.b);
+      let loss = sigmoid((-x - truth.a) / truth.b);
+      let draw = 1.0 - win - loss;
+
+      for (outcome, p) in [
+        (Outcome::Win, win),
+        (Outcome::Draw, draw),
+        (Outcome::Loss, loss),
+      ] {
+        for _ in 0..(1000.0 * p).round() as usize {
+          bucket.add(score, outcome);
+        }
+      }
+    }
+
+    let fit = bucket.fit(WdlParams { a: 150.0, b: 100.0 });
+
+    assert!((fit.a - truth.a).abs() < 2.5, "a = {}", fit.a);
+    assert!((fit.b - truth.b).abs() < 1.0, "b = {}", fit.b);
+  }
+
+  #[test]
+  fn cubic_fit_recovers_coefficients() {
+    let truth = [-12.5, 40.0, 150.0, 80.0];
+    let eval =
+      |x: f64| ((truth[0] * x + truth[1]) * x + truth[2]) * x + truth[3];
+
+    let xs = (0..20).map(|i| i as f64 / 10.0).collect::<Vec<_>>();
+    let ys = xs.iter().map(|&x| eval(x)).collect::<Vec<_>>();
+    let weights = (1..=20).map(|w| w as f64).collect::<Vec<_>>();
+
+    let coeffs = fit_cubic(&xs, &ys, &weights).unwrap();
+
+    for (fit, truth) in coeffs.iter().zip(truth) {
+      assert!((fit - truth).abs() < 1e-6, "{coeffs:?}");
+    }
+  }
+}
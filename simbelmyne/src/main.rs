@@ -1,10 +1,14 @@
+use chess::movegen::sliders;
 use clap::Parser;
 use cli::Command;
+use uci::print_banner;
 use uci::SearchController;
+use xboard::XBoardController;
 
 mod cli;
 mod spsa;
 mod uci;
+mod xboard;
 
 #[derive(Parser)]
 #[command(author = "Sam Roelants", version = "0.1", about = "A simple perft tool.", long_about = None)]
@@ -24,12 +28,26 @@ struct Cli {
 
 fn main() -> anyhow::Result<()> {
   let cli = Cli::parse();
+  sliders::init();
 
   if let Some(command) = cli.command {
     command.run()?;
   } else {
     let board = cli.fen.parse().unwrap();
-    SearchController::new(board).run()?;
+    print_banner();
+
+    // Pick the protocol based on the first command we receive
+    let mut first_line = String::new();
+    std::io::stdin().read_line(&mut first_line)?;
+
+    if first_line.trim() == "xboard" {
+      XBoardController::new(board).run()?;
+    } else {
+      let input = std::iter::once(first_line)
+        .chain(std::io::stdin().lines().map_while(Result::ok));
+
+      SearchController::new(board).run(input)?;
+    }
   }
 
   Ok(())
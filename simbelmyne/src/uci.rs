@@ -1,7 +1,7 @@
 //! Simbelmyne's UCI interface.
 //!
-//! Utilities for creating a UCI "listener" that spins up a search thread
-//! and communicates with it over a channel.
+//! Utilities for creating a UCI "listener" that spins up an `Engine` and
+//! forwards the UCI commands it receives to it.
 //!
 //! Uses the UCI types and definitions defined in `shared::uci`.
 //!
@@ -9,26 +9,29 @@
 //! extra features (hash table size, etc...) just yet.
 
 use chess::board::Board;
+use chess::game::Game;
+use chess::movegen::sliders;
 use colored::Colorize;
+use engine::engine::Engine;
 use engine::evaluate::pretty_print::print_eval;
 use engine::position::Position;
+use engine::search::contempt::DEFAULT_CONTEMPT;
+use engine::search::observer::default_observer;
+use engine::search::observer::SearchEvent;
+use engine::search::observer::SearchObserver;
 use engine::search::params::DEFAULT_TT_SIZE;
-use engine::search::NodeCounter;
-use engine::search::SearchRunner;
-use engine::time_control::TimeControlHandle;
-use engine::time_control::TimeController;
-use engine::transpositions::TTable;
+use engine::search::params::MAX_TT_SIZE;
+use engine::search::strength::DEFAULT_ELO;
+use engine::search::strength::MAX_ELO;
+use engine::search::strength::MAX_SKILL_LEVEL;
+use engine::search::strength::MIN_ELO;
 use std::io::stdout;
-use std::io::BufRead;
 use std::io::Write;
-use std::sync::atomic::AtomicU32;
 use uci::client::UciClientMessage;
 use uci::engine::UciEngineMessage;
 use uci::options::OptionType;
 use uci::options::UciOption;
 
-const DEBUG: bool = true;
-
 const BANNER: &str = r"
  ,-.          .       .                  
 (   ` o       |       |                  
@@ -37,22 +40,26 @@ const BANNER: &str = r"
  `-'  ' ' ' ' `-' `-' ' ' ' ' `-| ' ' `-'
                               `-'        ";
 
-const NAME: &str = "Simbelmyne";
-const VERSION: &str = env!("CARGO_PKG_VERSION");
-const AUTHOR: &str = env!("CARGO_PKG_AUTHORS");
+pub const NAME: &str = "Simbelmyne";
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const AUTHOR: &str = env!("CARGO_PKG_AUTHORS");
 const WEBSITE: &str = "https://www.samroelants.com";
 const REPOSITORY: &str = env!("CARGO_PKG_REPOSITORY");
 
-const UCI_OPTIONS: [UciOption; 2] = [
+const UCI_OPTIONS: [UciOption; 12] = [
   UciOption {
     name: "Hash",
     option_type: OptionType::Spin {
       min: 4,
-      max: 1024,
+      max: MAX_TT_SIZE as i32,
       default: DEFAULT_TT_SIZE as i32,
       step: 1,
     },
   },
+  UciOption {
+    name: "Clear Hash",
+    option_type: OptionType::Button,
+  },
   UciOption {
     name: "Threads",
     option_type: OptionType::Spin {
@@ -62,55 +69,115 @@ const UCI_OPTIONS: [UciOption; 2] = [
       step: 1,
     },
   },
+  UciOption {
+    name: "Contempt",
+    option_type: OptionType::Spin {
+      min: -200,
+      max: 200,
+      default: DEFAULT_CONTEMPT,
+      step: 10,
+    },
+  },
+  UciOption {
+    name: "Dynamic Contempt",
+    option_type: OptionType::Check { default: false },
+  },
+  UciOption {
+    name: "UCI_AnalyseMode",
+    option_type: OptionType::Check { default: false },
+  },
+  UciOption {
+    name: "UCI_Opponent",
+    option_type: OptionType::String {
+      default: String::new(),
+    },
+  },
+  UciOption {
+    name: "UCI_ShowWDL",
+    option_type: OptionType::Check { default: false },
+  },
+  UciOption {
+    name: "UCI_LimitStrength",
+    option_type: OptionType::Check { default: false },
+  },
+  UciOption {
+    name: "UCI_Elo",
+    option_type: OptionType::Spin {
+      min: MIN_ELO as i32,
+      max: MAX_ELO as i32,
+      default: DEFAULT_ELO as i32,
+      step: 1,
+    },
+  },
+  UciOption {
+    name: "Skill Level",
+    option_type: OptionType::Spin {
+      min: 0,
+      max: MAX_SKILL_LEVEL as i32,
+      default: MAX_SKILL_LEVEL as i32,
+      step: 1,
+    },
+  },
+  UciOption {
+    name: "Skill Seed",
+    option_type: OptionType::Spin {
+      min: 0,
+      max: i32::MAX,
+      default: 0,
+      step: 1,
+    },
+  },
 ];
 
-/// A wrapper that spins up a search thread and wires up the stdin/stdout of the
-/// process to the search thread.
+/// Print the engine banner and identifying information to stderr
+pub fn print_banner() {
+  eprintln!("{}", BANNER.blue());
+  eprintln!(
+    "                            {} {}",
+    "Version".blue(),
+    VERSION.blue()
+  );
+  eprintln!("");
+  eprintln!("{}: {NAME} {VERSION}", "Engine".blue());
+  eprintln!("{}: {AUTHOR}", "Author".blue());
+  eprintln!("{}: {WEBSITE}", "Website".blue());
+  eprintln!("{}: {REPOSITORY}", "Source".blue());
+  eprintln!("");
+}
+
+/// A wrapper that spins up an engine and wires up the stdin/stdout of the
+/// process to it.
 pub struct SearchController {
-  position: Position,
   debug: bool,
-  tc_handle: Option<TimeControlHandle>,
-  search_thread: SearchThread,
+  engine: Engine,
 }
 
 impl SearchController {
   // Create a new UCI listener
   pub fn new(board: Board) -> Self {
+    let mut engine = Engine::new();
+    engine.set_position(Position::new(board));
+    engine.set_observer(UciOutput::new(false));
+
     Self {
-      position: Position::new(board),
       debug: false,
-      tc_handle: None,
-      search_thread: SearchThread::new(),
+      engine,
     }
   }
 
-  /// Start listening on stdin and transmit any valid UCI messages to the
-  /// search thread
-  pub fn run(&mut self) -> anyhow::Result<()> {
-    let stdin = std::io::stdin().lock();
-
-    eprintln!("{}", BANNER.blue());
-    eprintln!(
-      "                            {} {}",
-      "Version".blue(),
-      VERSION.blue()
-    );
-    eprintln!("");
-    eprintln!("{}: {NAME} {VERSION}", "Engine".blue());
-    eprintln!("{}: {AUTHOR}", "Author".blue());
-    eprintln!("{}: {WEBSITE}", "Website".blue());
-    eprintln!("{}: {REPOSITORY}", "Source".blue());
-    eprintln!("");
-
-    for input in stdin.lines() {
-      let input = input.unwrap();
-
+  /// Listen for UCI messages on the provided input lines (typically stdin),
+  /// and transmit any valid UCI messages to the search thread
+  pub fn run(
+    &mut self,
+    input: impl Iterator<Item = String>,
+  ) -> anyhow::Result<()> {
+    for input in input {
       match input.trim().parse::<UciClientMessage>() {
         Ok(command) => {
           match command {
             // Print identifying information
             UciClientMessage::Uci => {
-              println!("id name {NAME} {VERSION}");
+              println!("id name {NAME} {VERSION} ({})", sliders::backend());
               println!("id author {AUTHOR}");
 
               for option in UCI_OPTIONS {
@@ -129,49 +196,46 @@ impl SearchController {
             }
 
             UciClientMessage::Show => {
-              println!("{}", self.position.board);
+              println!("{}", self.engine.position().board);
             }
 
             UciClientMessage::Eval => {
-              println!("{}", print_eval(&self.position.board));
+              println!("{}", print_eval(&self.engine.position().board));
             }
 
             // Let the client know we're ready
             UciClientMessage::IsReady => println!("readyok"),
 
             // Reset the search state
-            UciClientMessage::UciNewGame => {
-              self.position = Position::new(Board::default());
-              self.tc_handle = None;
-              self.search_thread.clear_tables();
-            }
+            UciClientMessage::UciNewGame => self.engine.new_game(),
 
             // Print additional debug information
             UciClientMessage::Debug(debug) => self.debug = debug,
 
             // Set up the provided position by applying the moves to
-            // the provided board state.
+            // the provided board state. If any of the moves is illegal,
+            // keep the previous position.
             UciClientMessage::Position(board, moves) => {
-              let mut position = Position::new(board);
+              let mut game = Game::new(board);
 
-              for mv in moves {
-                position = position.play_bare_move(mv);
-              }
+              let played = moves
+                .into_iter()
+                .try_for_each(|mv| game.play_bare_move(mv).map(|_| ()));
 
-              self.position = position;
+              match played {
+                Ok(()) => self.engine.set_position(Position::from_game(&game)),
+                Err(err) => eprintln!("{err}"),
+              }
             }
 
             // Start a search on the current board position, with
             // the requested time control
             UciClientMessage::Go(tc) => {
-              let (tc, tc_handle) =
-                TimeController::new(tc, self.position.board.current);
-              self.tc_handle = Some(tc_handle);
-              self.search_thread.search(self.position.clone(), tc);
+              self.engine.go(tc);
             }
 
             UciClientMessage::GoPerft(d) => {
-              let result = self.position.board.perft_divide(d);
+              let result = self.engine.position().board.perft_divide(d);
               let total: u64 = result.iter().map(|(_, nodes)| nodes).sum();
 
               for (mv, nodes) in result.iter() {
@@ -182,37 +246,20 @@ impl SearchController {
             }
 
             // Abort the currently running search
-            UciClientMessage::Stop => {
-              if let Some(tc_handle) = &self.tc_handle {
-                tc_handle.stop();
-              }
-            }
+            UciClientMessage::Stop => self.engine.stop(),
 
             // Set an option
             UciClientMessage::SetOption(name, value) => {
               match name.as_str() {
-                // Advertized options
-                "Hash" => {
-                  let size = value.parse()?;
-                  self.search_thread.resize_tt(size);
+                // Showing WDL stats only affects our output
+                "UCI_ShowWDL" => {
+                  let show_wdl = value.parse()?;
+                  self.engine.set_observer(UciOutput::new(show_wdl));
                 }
 
-                "Threads" => {
-                  let num_threads = value.parse()?;
-                  self.search_thread.set_threads(num_threads);
-                }
-
-                // Treat any other options as search params
-                // for SPSA purposes.
                 _ => {
-                  if let Ok(_value) = value.parse::<i32>() {
-                    #[cfg(feature = "spsa")]
-                    {
-                      use engine::search::params::set_param;
-                      set_param(&name, _value);
-                    }
-                  } else {
-                    eprintln!("Invalid value {value}");
+                  if let Err(err) = self.engine.set_option(&name, &value) {
+                    eprintln!("{err}");
                   }
                 }
               }
@@ -221,6 +268,25 @@ impl SearchController {
             UciClientMessage::Quit => {
               break;
             }
+
+            // Save and restore the transposition table
+            UciClientMessage::SaveHash(path) => {
+              if let Err(err) = self.engine.save_hash(path) {
+                eprintln!("{err:#}");
+              }
+            }
+
+            UciClientMessage::LoadHash(path) => {
+              if let Err(err) = self.engine.load_hash(path) {
+                eprintln!("{err:#}");
+              }
+            }
+
+            UciClientMessage::MergeHash(path) => {
+              if let Err(err) = self.engine.merge_hash(path) {
+                eprintln!("{err:#}");
+              }
+            }
           }
         }
 
@@ -234,108 +300,28 @@ impl SearchController {
   }
 }
 
-/// A handle to a long-running thread that's in charge of searching for the best
-/// move, given a position and time control.
-struct SearchThread {
-  tx: std::sync::mpsc::Sender<SearchCommand>,
+/// Reports the search progress in UCI format (or pretty-printed, when
+/// writing to a terminal), and prints the best move as a `bestmove` message.
+struct UciOutput {
+  progress: Box<dyn SearchObserver>,
 }
-impl SearchThread {
-  /// Spawn a new search thread, and return a handle to it as a SearchThread
-  /// struct.
-  pub fn new() -> Self {
-    let (tx, rx) = std::sync::mpsc::channel::<SearchCommand>();
-
-    std::thread::spawn(move || {
-      let mut num_threads = 1;
-      let mut tt_size = DEFAULT_TT_SIZE;
-      let mut tt = TTable::with_capacity(tt_size);
-      let global_nodes = AtomicU32::new(0);
-      let nodes = NodeCounter::new(&global_nodes);
-      let mut runners = (0..num_threads)
-        .map(|i| SearchRunner::new(i, &tt, nodes.clone()))
-        .collect::<Vec<_>>();
-
-      for msg in rx.iter() {
-        match msg {
-          SearchCommand::Search(pos, tc) => {
-            tt.increment_age();
-            nodes.clear_global();
-
-            std::thread::scope(|s| {
-              for runner in runners.iter_mut() {
-                s.spawn(|| {
-                  let report = runner.search::<DEBUG>(pos.clone(), tc.clone());
-
-                  if runner.id == 0 {
-                    tc.stop();
-                    println!("{}", UciEngineMessage::BestMove(report.pv[0]));
-                  }
-                });
-              }
-            });
-          }
-
-          SearchCommand::Clear => {
-            tt = TTable::with_capacity(tt_size);
-
-            runners = (0..num_threads)
-              .map(|i| SearchRunner::new(i, &tt, nodes.clone()))
-              .collect::<Vec<_>>();
-          }
-
-          SearchCommand::ResizeTT(size) => {
-            tt_size = size;
-            tt.resize(size);
 
-            runners = (0..num_threads)
-              .map(|i| SearchRunner::new(i, &tt, nodes.clone()))
-              .collect::<Vec<_>>();
-          }
-
-          SearchCommand::SetThreads(n) => {
-            num_threads = n;
-
-            runners = (0..num_threads)
-              .map(|i| SearchRunner::new(i, &tt, nodes.clone()))
-              .collect();
-          }
-        }
-      }
-    });
-
-    Self { tx }
-  }
-
-  /// Initiate a new search on this thread
-  pub fn search(&self, position: Position, tc: TimeController) {
-    self.tx.send(SearchCommand::Search(position, tc)).unwrap();
-  }
-
-  /// Clear the history and transposition tables for this search thread
-  pub fn clear_tables(&self) {
-    self.tx.send(SearchCommand::Clear).unwrap();
+impl UciOutput {
+  fn new(show_wdl: bool) -> Self {
+    Self {
+      progress: default_observer(show_wdl),
+    }
   }
+}
 
-  pub fn resize_tt(&self, size: usize) {
-    self.tx.send(SearchCommand::ResizeTT(size)).unwrap();
-  }
+impl SearchObserver for UciOutput {
+  fn notify(&mut self, event: SearchEvent) {
+    match event {
+      SearchEvent::BestMove { report, .. } => {
+        println!("{}", UciEngineMessage::BestMove(report.pv[0]));
+      }
 
-  pub fn set_threads(&self, num_threads: usize) {
-    self
-      .tx
-      .send(SearchCommand::SetThreads(num_threads))
-      .unwrap();
+      event => self.progress.notify(event),
+    }
   }
-
-  // pub fn set_search_params(&self, search_params: SearchParams) {
-  //     self.tx.send(SearchCommand::SetSearchParams(search_params)).unwrap();
-  // }
-}
-
-/// Commands that can be sent from the UCI listener thread to the SearchThread
-enum SearchCommand {
-  Search(Position, TimeController),
-  Clear,
-  ResizeTT(usize),
-  SetThreads(usize),
 }
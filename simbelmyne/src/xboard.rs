@@ -0,0 +1,410 @@
+//! Simbelmyne's XBoard (CECP) interface.
+//!
+//! Drives the same engine as the UCI interface, but speaks the XBoard
+//! protocol. The main difference with UCI is that the engine, rather than the
+//! GUI, keeps track of the game: the GUI only sends over the opponent's moves,
+//! and the engine decides for itself when it's its turn to move.
+//!
+//! Because the engine needs to know about its own moves as well, both the
+//! lines read from stdin and the moves found by the search are funneled into a
+//! single channel of `Event`s.
+
+use crate::uci::NAME;
+use crate::uci::VERSION;
+use chess::board::Board;
+use chess::movegen::legal_moves::All;
+use chess::movegen::moves::Move;
+use chess::piece::Color;
+use engine::engine::Engine;
+use engine::engine::Limits;
+use engine::position::Position;
+use engine::search::observer::SearchEvent;
+use engine::search::observer::SearchObserver;
+use engine::search::observer::XBoardObserver;
+use std::io::stdout;
+use std::io::Write;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+use uci::time_control::TimeControl;
+use uci::xboard::XBoardCommand;
+
+/// Events the XBoard controller needs to respond to
+enum Event {
+  /// A line of input from the GUI
+  Input(String),
+
+  /// The search thread found a move
+  BestMove(Move),
+}
+
+/// The time control, as set through the `level` command
+#[derive(Debug, Copy, Clone)]
+struct Level {
+  mps: u32,
+  base: Duration,
+  inc: Duration,
+}
+
+impl Default for Level {
+  /// XBoard's default time control: 40 moves in 5 minutes
+  fn default() -> Self {
+    Self {
+      mps: 40,
+      base: Duration::from_secs(300),
+      inc: Duration::ZERO,
+    }
+  }
+}
+
+pub struct XBoardController {
+  /// The current game position
+  position: Position,
+
+  /// The positions before every move played so far, so we can take moves
+  /// back
+  undo_stack: Vec<Position>,
+
+  /// The full move counter at the start of the game, used to figure out how
+  /// many moves are left until the next time control
+  start_move: u16,
+
+  /// The side the engine is playing, or `None` when in force mode
+  engine_side: Option<Color>,
+
+  /// The time control, as set by `level`
+  level: Level,
+
+  /// A fixed time per move, as set by `st`
+  move_time: Option<Duration>,
+
+  /// A maximum search depth, as set by `sd`
+  max_depth: Option<usize>,
+
+  /// The time left on the engine's clock
+  engine_clock: Duration,
+
+  /// The time left on the opponent's clock
+  opponent_clock: Duration,
+
+  /// Whether there is a search running that we're waiting on
+  searching: bool,
+
+  /// The number of aborted searches whose moves we should discard
+  stale_searches: usize,
+
+  engine: Engine,
+  tx: Sender<Event>,
+  rx: Receiver<Event>,
+}
+
+impl XBoardController {
+  pub fn new(board: Board) -> Self {
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    // XBoard expects no thinking output until it sends `post`
+    let engine = Engine::new();
+    engine.set_observer(XBoardOutput::new(false, tx.clone()));
+
+    let level = Level::default();
+
+    Self {
+      position: Position::new(board),
+      undo_stack: Vec::new(),
+      start_move: board.full_moves,
+      engine_side: Some(Color::Black),
+      level,
+      move_time: None,
+      max_depth: None,
+      engine_clock: level.base,
+      opponent_clock: level.base,
+      searching: false,
+      stale_searches: 0,
+      engine,
+      tx,
+      rx,
+    }
+  }
+
+  /// Start listening on stdin, and respond to any XBoard commands and moves
+  /// found by the search thread.
+  pub fn run(&mut self) -> anyhow::Result<()> {
+    let input_tx = self.tx.clone();
+
+    std::thread::spawn(move || {
+      for line in std::io::stdin().lines().map_while(Result::ok) {
+        input_tx.send(Event::Input(line)).unwrap();
+      }
+
+      // Treat a closed stdin as a request to quit
+      input_tx.send(Event::Input("quit".to_string())).unwrap();
+    });
+
+    while let Ok(event) = self.rx.recv() {
+      match event {
+        Event::Input(input) => match input.parse::<XBoardCommand>() {
+          Ok(XBoardCommand::Quit) => {
+            self.abort_search();
+            break;
+          }
+
+          Ok(command) => self.handle_command(command),
+
+          Err(_) => match input.trim().split_once(' ') {
+            Some(("usermove", mv)) => println!("Illegal move: {mv}"),
+            _ => println!("Error (unknown command): {}", input.trim()),
+          },
+        },
+
+        Event::BestMove(mv) => self.handle_bestmove(mv),
+      }
+
+      stdout().flush()?;
+    }
+
+    Ok(())
+  }
+
+  fn handle_command(&mut self, command: XBoardCommand) {
+    use XBoardCommand::*;
+
+    match command {
+      // Announce the protocol features we support
+      Protover(_) => {
+        println!(
+          "feature myname=\"{NAME} {VERSION}\" ping=1 setboard=1 usermove=1 \
+           time=1 draw=0 sigint=0 sigterm=0 reuse=1 analyze=0 colors=0 \
+           memory=1 smp=1 variants=\"normal\" done=1"
+        );
+      }
+
+      // Reset the game, and play Black
+      New => {
+        self.abort_search();
+        self.set_board(Board::default());
+        self.engine_side = Some(Color::Black);
+        self.move_time = None;
+        self.max_depth = None;
+        self.engine_clock = self.level.base;
+        self.opponent_clock = self.level.base;
+        self.engine.new_game();
+      }
+
+      Force => {
+        self.abort_search();
+        self.engine_side = None;
+      }
+
+      // Play the side to move, starting now
+      Go => {
+        self.engine_side = Some(self.position.board.current);
+        self.start_search();
+      }
+
+      UserMove(bare) => {
+        let Some(mv) = self.position.board.find_move(bare) else {
+          println!("Illegal move: {bare}");
+          return;
+        };
+
+        self.play_move(mv);
+
+        if self.engine_side == Some(self.position.board.current) {
+          self.start_search();
+        }
+      }
+
+      Level { mps, base, inc } => {
+        self.level = self::Level { mps, base, inc };
+        self.move_time = None;
+        self.engine_clock = base;
+        self.opponent_clock = base;
+      }
+
+      St(time) => self.move_time = Some(time),
+      Sd(depth) => self.max_depth = Some(depth),
+      Time(time) => self.engine_clock = time,
+      Otim(time) => self.opponent_clock = time,
+
+      Undo => {
+        self.abort_search();
+        self.undo_moves(1);
+      }
+
+      Remove => {
+        self.abort_search();
+        self.undo_moves(2);
+      }
+
+      // The game is over, stop playing until we get a `new`.
+      Result(_) => {
+        self.abort_search();
+        self.engine_side = None;
+      }
+
+      SetBoard(board) => {
+        self.abort_search();
+        self.set_board(board);
+      }
+
+      Post => self.set_post(true),
+      NoPost => self.set_post(false),
+      Ping(n) => println!("pong {n}"),
+
+      MoveNow => self.engine.stop(),
+      Memory(size) => self.set_option("Hash", size),
+      Cores(n) => self.set_option("Threads", n),
+
+      XBoard | Ignored(_) | Quit => {}
+    }
+  }
+
+  /// Play the move found by the search thread, unless the search was
+  /// aborted in the meantime.
+  fn handle_bestmove(&mut self, mv: Move) {
+    if self.stale_searches > 0 {
+      self.stale_searches -= 1;
+      return;
+    }
+
+    self.searching = false;
+    self.play_move(mv);
+    println!("move {mv}");
+
+    // Claim the result if the game is over
+    if self.position.board.legal_moves::<All>().is_empty() {
+      if !self.position.board.in_check() {
+        println!("1/2-1/2 {{Stalemate}}");
+      } else if self.position.board.current.is_white() {
+        println!("0-1 {{Black mates}}");
+      } else {
+        println!("1-0 {{White mates}}");
+      }
+    }
+  }
+
+  /// Start a search for the engine's move, using the current time control
+  fn start_search(&mut self) {
+    // Nothing to search if the game is already over
+    if self.position.board.legal_moves::<All>().is_empty() {
+      return;
+    }
+
+    let limits = Limits {
+      time_control: self.time_control(),
+      depth: self.max_depth,
+    };
+
+    self.searching = true;
+    self.engine.set_position(self.position.clone());
+    self.engine.go(limits);
+  }
+
+  /// Abort the running search, if any, and make sure we don't play the move
+  /// it comes back with.
+  fn abort_search(&mut self) {
+    if self.searching {
+      self.engine.stop();
+      self.searching = false;
+      self.stale_searches += 1;
+    }
+  }
+
+  /// Enable or disable thinking output
+  fn set_post(&self, post: bool) {
+    self
+      .engine
+      .set_observer(XBoardOutput::new(post, self.tx.clone()));
+  }
+
+  fn set_option(&mut self, name: &str, value: impl ToString) {
+    if let Err(err) = self.engine.set_option(name, &value.to_string()) {
+      println!("Error ({err}): {name}");
+    }
+  }
+
+  fn play_move(&mut self, mv: Move) {
+    let next = self.position.play_move(mv);
+    self
+      .undo_stack
+      .push(std::mem::replace(&mut self.position, next));
+  }
+
+  fn undo_moves(&mut self, count: usize) {
+    for _ in 0..count {
+      if let Some(previous) = self.undo_stack.pop() {
+        self.position = previous;
+      }
+    }
+  }
+
+  fn set_board(&mut self, board: Board) {
+    self.position = Position::new(board);
+    self.undo_stack.clear();
+    self.start_move = board.full_moves;
+  }
+
+  /// Translate the XBoard time settings into a time control for the search
+  fn time_control(&self) -> TimeControl {
+    if let Some(time) = self.move_time {
+      return TimeControl::FixedTime(time);
+    }
+
+    let Level { mps, inc, .. } = self.level;
+
+    // In conventional time controls, figure out how many moves are left
+    // until the next time control
+    let movestogo = if mps > 0 {
+      let moves_played = self
+        .position
+        .board
+        .full_moves
+        .saturating_sub(self.start_move);
+      Some(mps - moves_played as u32 % mps)
+    } else {
+      None
+    };
+
+    let (wtime, btime) = if self.position.board.current.is_white() {
+      (self.engine_clock, self.opponent_clock)
+    } else {
+      (self.opponent_clock, self.engine_clock)
+    };
+
+    TimeControl::Clock {
+      wtime,
+      btime,
+      winc: Some(inc),
+      binc: Some(inc),
+      movestogo,
+    }
+  }
+}
+
+/// Passes the search's best move back to the controller, and optionally
+/// prints the thinking output.
+struct XBoardOutput {
+  /// Whether to print the thinking output, as set by `post` and `nopost`
+  post: bool,
+  tx: Sender<Event>,
+}
+
+impl XBoardOutput {
+  fn new(post: bool, tx: Sender<Event>) -> Self {
+    Self { post, tx }
+  }
+}
+
+impl SearchObserver for XBoardOutput {
+  fn notify(&mut self, event: SearchEvent) {
+    match event {
+      SearchEvent::BestMove { report, .. } => {
+        // The controller only goes away when we're quitting anyway
+        let _ = self.tx.send(Event::BestMove(report.pv[0]));
+      }
+
+      event if self.post => XBoardObserver.notify(event),
+      _ => {}
+    }
+  }
+}
@@ -0,0 +1,162 @@
+//! Run `perft-debug` against a few reference engines: the engine itself, which
+//! should never disagree with us, and a stand-in that answers with doctored
+//! divides, to check that we track down and report the differences.
+
+use std::path::Path;
+use std::process::Command;
+
+const KIWIPETE: &str =
+  "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+/// Run `perft-debug` against a reference engine, and return its stdout
+fn perft_debug(reference: &Path, depth: usize) -> String {
+  let output = Command::new(env!("CARGO_BIN_EXE_simbelmyne"))
+    .arg("perft-debug")
+    .arg("--reference")
+    .arg(reference)
+    .args(["--depth", &depth.to_string(), "--fen", KIWIPETE])
+    .env("NO_COLOR", "1")
+    .output()
+    .expect("Failed to run perft-debug");
+
+  let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+  assert!(output.status.success(), "perft-debug failed: {stdout}");
+  stdout
+}
+
+#[test]
+fn perft_debug_against_itself() {
+  let engine = Path::new(env!("CARGO_BIN_EXE_simbelmyne"));
+  let stdout = perft_debug(engine, 3);
+
+  assert!(stdout.contains("No differences found"), "{stdout}");
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Stand-in reference engine
+//
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(unix)]
+mod stand_in {
+  use super::*;
+  use chess::board::Board;
+  use chess::movegen::legal_moves::All;
+  use chess::movegen::moves::Move;
+  use std::os::unix::fs::PermissionsExt;
+  use std::path::PathBuf;
+
+  /// A stand-in for the reference engine: a shell script that answers every
+  /// `go perft` with the next divide in line, in the same `<move>: <nodes>`
+  /// format Stockfish uses.
+  const STAND_IN: &str = r#"#!/bin/sh
+n=0
+while read -r command _; do
+  case "$command" in
+    uci) echo uciok ;;
+    isready) echo readyok ;;
+    go) n=$((n + 1)); cat "$(dirname "$0")/divide-$n" ;;
+    quit) exit ;;
+  esac
+done
+"#;
+
+  /// Write the stand-in engine to a fresh directory, along with the divides it
+  /// should answer with, and return the path to the script.
+  fn stand_in(name: &str, divides: &[Vec<(String, u64)>]) -> PathBuf {
+    let dir = std::env::temp_dir()
+      .join(format!("simbelmyne-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for (i, divide) in divides.iter().enumerate() {
+      let lines = divide
+        .iter()
+        .map(|(mv, nodes)| format!("{mv}: {nodes}\n"))
+        .collect::<String>();
+
+      std::fs::write(dir.join(format!("divide-{}", i + 1)), lines).unwrap();
+    }
+
+    let script = dir.join("reference");
+    std::fs::write(&script, STAND_IN).unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))
+      .unwrap();
+
+    script
+  }
+
+  /// Our own divide, in the same shape as the stand-in's
+  fn divide(board: &Board, depth: usize) -> Vec<(String, u64)> {
+    board
+      .perft_divide(depth)
+      .into_iter()
+      .map(|(mv, nodes)| (mv.to_string(), nodes))
+      .collect()
+  }
+
+  fn find_move(board: &Board, mv: &str) -> Move {
+    board
+      .legal_moves::<All>()
+      .into_iter()
+      .find(|legal| legal.to_string() == mv)
+      .unwrap()
+  }
+
+  #[test]
+  fn perft_debug_finds_a_missing_move() {
+    let root: Board = KIWIPETE.parse().unwrap();
+    let child = root.play_move(find_move(&root, "e5f7"));
+
+    // The reference doesn't generate one of the replies to e5f7, so it also
+    // comes up short on the nodes after e5f7.
+    let mut second = divide(&child, 2);
+    let (missing, missed_nodes) = second.remove(0);
+
+    let mut first = divide(&root, 3);
+    let (_, nodes) = first.iter_mut().find(|(mv, _)| mv == "e5f7").unwrap();
+    let ours = *nodes;
+    *nodes -= missed_nodes;
+
+    let reference = stand_in("perft-missing", &[first, second]);
+    let stdout = perft_debug(&reference, 3);
+    std::fs::remove_dir_all(reference.parent().unwrap()).unwrap();
+
+    let culprit = child.to_fen();
+    let theirs = ours - missed_nodes;
+    let descent =
+      format!("Depth 3: e5f7 has {ours} nodes, reference has {theirs}\n");
+    assert!(stdout.contains(&descent), "{stdout}");
+    assert!(stdout.contains("Moves: e5f7\n"), "{stdout}");
+    assert!(stdout.contains(&format!("FEN: {culprit}\n")), "{stdout}");
+    assert!(
+      stdout.contains(&format!("Only generated by us: {missing}\n")),
+      "{stdout}"
+    );
+    assert!(
+      !stdout.contains("Only generated by the reference"),
+      "{stdout}"
+    );
+  }
+
+  #[test]
+  fn perft_debug_finds_an_extra_move() {
+    let root: Board = KIWIPETE.parse().unwrap();
+
+    // The reference thinks the pawn on a2 can jump straight to a5
+    let mut first = divide(&root, 2);
+    first.push((String::from("a2a5"), 42));
+
+    let reference = stand_in("perft-extra", &[first]);
+    let stdout = perft_debug(&reference, 2);
+    std::fs::remove_dir_all(reference.parent().unwrap()).unwrap();
+
+    assert!(stdout.contains("Moves: \n"), "{stdout}");
+    assert!(stdout.contains(&format!("FEN: {KIWIPETE}\n")), "{stdout}");
+    assert!(
+      stdout.contains("Only generated by the reference: a2a5\n"),
+      "{stdout}"
+    );
+    assert!(!stdout.contains("Only generated by us"), "{stdout}");
+  }
+}
@@ -0,0 +1,53 @@
+//! Drive the engine over UCI, the way a GUI would.
+
+use std::io::Write;
+use std::process::Command;
+use std::process::Stdio;
+
+const KIWIPETE: &str =
+  "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+/// Send the commands to a fresh engine, and return its stdout and stderr
+fn run(commands: &[&str]) -> (String, String) {
+  let mut engine = Command::new(env!("CARGO_BIN_EXE_simbelmyne"))
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .expect("Failed to start the engine");
+
+  let mut stdin = engine.stdin.take().unwrap();
+  for command in commands {
+    writeln!(stdin, "{command}").unwrap();
+  }
+  drop(stdin);
+
+  let output = engine.wait_with_output().unwrap();
+  assert!(output.status.success());
+
+  (
+    String::from_utf8_lossy(&output.stdout).into_owned(),
+    String::from_utf8_lossy(&output.stderr).into_owned(),
+  )
+}
+
+#[test]
+fn illegal_moves_keep_the_previous_position() {
+  let (stdout, stderr) = run(&[
+    &format!("position fen {KIWIPETE}"),
+    "position startpos moves e2e4 e2e5",
+    "go perft 1",
+    "isready",
+    "quit",
+  ]);
+
+  assert!(stderr.contains("Illegal move e2e5"), "{stderr}");
+
+  // Kiwipete has 48 legal moves, the starting position only 20
+  let total = stdout
+    .lines()
+    .rev()
+    .find(|line| !line.is_empty() && line != &"readyok");
+  assert_eq!(total, Some("48"), "{stdout}");
+  assert!(stdout.contains("readyok"));
+}
@@ -3,6 +3,7 @@ use anyhow::anyhow;
 use chess::board::Board;
 use chess::movegen::moves::BareMove;
 use std::fmt::Display;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 /// Messages that can be sent from the client to the engine
@@ -20,6 +21,11 @@ pub enum UciClientMessage {
   GoPerft(usize),
   Stop,
   Quit,
+
+  // Non-standard extensions for saving and restoring the transposition table
+  SaveHash(PathBuf),
+  LoadHash(PathBuf),
+  MergeHash(PathBuf),
 }
 
 impl Display for UciClientMessage {
@@ -34,6 +40,9 @@ impl Display for UciClientMessage {
       Show => writeln!(f, "show"),
       Eval => writeln!(f, "eval"),
       IsReady => writeln!(f, "isready"),
+      SetOption(opt, val) if val.is_empty() => {
+        writeln!(f, "setoption name {opt}")
+      }
       SetOption(opt, val) => {
         writeln!(f, "setoption name {opt} value {val}")
       }
@@ -54,6 +63,9 @@ impl Display for UciClientMessage {
       GoPerft(depth) => writeln!(f, "go perft {depth}"),
       Stop => writeln!(f, "stop"),
       Quit => writeln!(f, "quit"),
+      SaveHash(path) => writeln!(f, "savehash {}", path.display()),
+      LoadHash(path) => writeln!(f, "loadhash {}", path.display()),
+      MergeHash(path) => writeln!(f, "mergehash {}", path.display()),
     }
   }
 }
@@ -88,18 +100,22 @@ impl FromStr for UciClientMessage {
         let mut parts = remainder.split_whitespace();
         assert_eq!(parts.next(), Some("name"), "Invalidly formed UCI command");
 
+        // Option names and values may both contain spaces (e.g.,
+        // `setoption name UCI_Opponent value GM 2800 human Gary Kasparov`)
         let name = parts
           .by_ref()
           .take_while(|&word| word != "value")
-          .collect::<String>();
+          .collect::<Vec<_>>()
+          .join(" ");
 
-        let value = if let Some(value) = parts.next() {
-          value
-        } else {
+        // Buttons don't carry a value, so the value may be empty
+        let value = parts.collect::<Vec<_>>().join(" ");
+
+        if name.is_empty() {
           Err(anyhow!("Invalid UCI message"))?
-        };
+        }
 
-        Ok(SetOption(name.to_string(), value.to_string()))
+        Ok(SetOption(name, value))
       }
 
       "ucinewgame" => Ok(UciNewGame),
@@ -154,6 +170,21 @@ impl FromStr for UciClientMessage {
       "stop" => Ok(Stop),
       "quit" => Ok(Quit),
 
+      // File paths may contain spaces, so take the entire remainder
+      "savehash" | "loadhash" | "mergehash" => {
+        if remainder.is_empty() {
+          Err(anyhow!("Missing file name: {msg}"))?
+        }
+
+        let path = PathBuf::from(remainder);
+
+        match msg {
+          "savehash" => Ok(SaveHash(path)),
+          "loadhash" => Ok(LoadHash(path)),
+          _ => Ok(MergeHash(path)),
+        }
+      }
+
       _ => Err(anyhow!("Invalid UCI message: {msg}")),
     }
   }
@@ -1,9 +1,11 @@
 //! Utilities for serializing and deserializing Simbelmyne data into
-//! UCI messages
+//! UCI (and XBoard) messages
 
 pub mod client;
 pub mod engine;
+pub mod opponent;
 pub mod options;
 pub mod search_info;
 pub mod time_control;
 pub mod wdl;
+pub mod xboard;
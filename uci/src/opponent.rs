@@ -0,0 +1,129 @@
+use anyhow::anyhow;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Information about the opponent, as passed by the GUI through the
+/// `UCI_Opponent` option.
+///
+/// The value is of the form
+/// `<title> <elo> <computer|human> <name>`, where the title and rating may be
+/// `none` when the GUI doesn't know them.
+///
+/// Example: `setoption name UCI_Opponent value GM 2800 human Gary Kasparov`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opponent {
+  /// The opponent's title (GM, IM, FM, ...), if any
+  pub title: Option<String>,
+
+  /// The opponent's rating, if known
+  pub elo: Option<u32>,
+
+  /// Whether the opponent is another engine
+  pub computer: bool,
+
+  /// The opponent's name
+  pub name: String,
+}
+
+impl FromStr for Opponent {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> anyhow::Result<Self> {
+    let mut parts = s.split_whitespace();
+
+    let title = match parts.next() {
+      Some("none") => None,
+      Some(title) => Some(title.to_string()),
+      None => Err(anyhow!("Invalid opponent: {s}"))?,
+    };
+
+    let elo = match parts.next() {
+      Some("none") => None,
+      Some(elo) => Some(elo.parse()?),
+      None => Err(anyhow!("Invalid opponent: {s}"))?,
+    };
+
+    let computer = match parts.next() {
+      Some("computer") => true,
+      Some("human") => false,
+      _ => Err(anyhow!("Invalid opponent: {s}"))?,
+    };
+
+    let name = parts.collect::<Vec<_>>().join(" ");
+
+    Ok(Opponent {
+      title,
+      elo,
+      computer,
+      name,
+    })
+  }
+}
+
+impl Display for Opponent {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let title = self.title.as_deref().unwrap_or("none");
+    let kind = if self.computer { "computer" } else { "human" };
+
+    if let Some(elo) = self.elo {
+      write!(f, "{title} {elo} {kind} {}", self.name)
+    } else {
+      write!(f, "{title} none {kind} {}", self.name)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_titled_human() {
+    let opponent: Opponent = "GM 2800 human Kasparov".parse().unwrap();
+
+    assert_eq!(
+      opponent,
+      Opponent {
+        title: Some(String::from("GM")),
+        elo: Some(2800),
+        computer: false,
+        name: String::from("Kasparov"),
+      }
+    );
+  }
+
+  #[test]
+  fn parse_unknown_computer() {
+    let opponent: Opponent = "none none computer Stockfish".parse().unwrap();
+
+    assert_eq!(
+      opponent,
+      Opponent {
+        title: None,
+        elo: None,
+        computer: true,
+        name: String::from("Stockfish"),
+      }
+    );
+  }
+
+  #[test]
+  fn parse_name_with_spaces() {
+    let opponent: Opponent = "IM 2450 human  Judit   Polgar ".parse().unwrap();
+    assert_eq!(opponent.name, "Judit Polgar");
+
+    // Round-trips through Display
+    let printed: Opponent = opponent.to_string().parse().unwrap();
+    assert_eq!(printed, opponent);
+  }
+
+  #[test]
+  fn reject_malformed_opponents() {
+    assert!("".parse::<Opponent>().is_err());
+    assert!("GM".parse::<Opponent>().is_err());
+    assert!("GM 2800".parse::<Opponent>().is_err());
+    assert!("GM strong human Kasparov".parse::<Opponent>().is_err());
+    assert!("GM -2800 human Kasparov".parse::<Opponent>().is_err());
+    assert!("GM 2800 alien Kasparov".parse::<Opponent>().is_err());
+  }
+}
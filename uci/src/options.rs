@@ -49,6 +49,10 @@ impl Display for OptionType {
         write!(f, "type button")?;
       }
 
+      Self::String { default } if default.is_empty() => {
+        write!(f, "type string default <empty>")?;
+      }
+
       Self::String { default } => {
         write!(f, "type string default {default}")?;
       }
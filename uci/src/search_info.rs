@@ -28,6 +28,9 @@ pub struct SearchInfo {
   /// The highest score we've obtained so far
   pub score: Option<Score>,
 
+  /// The win/draw/loss probabilities (per mille) associated with the score
+  pub wdl: Option<(u64, u64, u64)>,
+
   /// The move we're currently searching
   pub currmove: Option<Move>,
 
@@ -67,6 +70,10 @@ impl Display for SearchInfo {
       write!(f, "score {score} ")?;
     }
 
+    if let Some((w, d, l)) = self.wdl {
+      write!(f, "wdl {w} {d} {l} ")?;
+    }
+
     if let Some(currmove) = self.currmove {
       write!(f, "currmove {currmove} ")?;
     }
@@ -96,6 +103,18 @@ impl Display for SearchInfo {
 }
 
 impl SearchInfo {
+  /// Attach the win/draw/loss probabilities for the current score, as
+  /// predicted by the provided WDL parameters.
+  pub fn with_wdl(mut self, wdl: WdlParams) -> Self {
+    self.wdl = self.score.map(|score| match score {
+      Score::Cp(score) => wdl.get_wdl(score),
+      Score::Mate(n) if n > 0 => (1000, 0, 0),
+      Score::Mate(_) => (0, 0, 1000),
+    });
+
+    self
+  }
+
   /// Format the SearchInfo as a UCI compliant log message, using the provided
   /// WDL parameters to rescale the score such that an advantage of 100cp
   /// corresponds to a 50% chance of winning.
@@ -130,6 +149,10 @@ impl SearchInfo {
       }
     }
 
+    if let Some((w, d, l)) = self.wdl {
+      output.push(format!("wdl {w} {d} {l} "));
+    }
+
     if let Some(currmove) = self.currmove {
       output.push(format!("currmove {currmove} "));
     }
@@ -157,6 +180,34 @@ impl SearchInfo {
     output.join(" ")
   }
 
+  /// Format the SearchInfo as XBoard thinking output
+  /// (`<ply> <score> <time> <nodes> <pv>`), using the provided WDL parameters
+  /// to rescale the score, like we do for UCI output.
+  ///
+  /// Times are reported in centiseconds, and mate scores as 100000 + N for
+  /// mate in N moves.
+  pub fn to_xboard(&self, wdl: WdlParams) -> String {
+    let score = match self.score {
+      Some(Score::Cp(score)) => wdl.wdl_normalized(score),
+      Some(Score::Mate(n)) if n > 0 => 100_000 + n,
+      Some(Score::Mate(n)) => -100_000 + n,
+      None => 0,
+    };
+
+    let mut output = format!(
+      "{} {score} {} {}",
+      self.depth.unwrap_or_default(),
+      self.time.unwrap_or_default() / 10,
+      self.nodes.unwrap_or_default(),
+    );
+
+    for mv in self.pv.iter() {
+      write!(output, " {mv}").unwrap();
+    }
+
+    output
+  }
+
   /// Format the SearchInfo as a pretty-printed log message, using the
   /// provided WDL parameters to rescale the score such that an advantage of
   /// 100cp corresponds to a 50% chance of winning.
@@ -352,13 +403,31 @@ impl FromStr for SearchInfo {
         }
 
         "score" => {
-          // 'score cp x'
-          parts.next(); // Skip the 'cp' part
+          // 'score cp x' or 'score mate x'
+          let score_type = parts.next().ok_or(anyhow!(
+            "Not a valid info string: {s}, failed to parse 'score'"
+          ))?;
+
           let info_value = parts.next().ok_or(anyhow!(
             "Not a valid info string: {s}, failed to parse 'score'"
           ))?;
 
-          info.score = Some(info_value.parse()?);
+          info.score = Some(format!("{score_type} {info_value}").parse()?);
+        }
+
+        "wdl" => {
+          let mut wdl = [0; 3];
+
+          for value in wdl.iter_mut() {
+            *value = parts
+              .next()
+              .ok_or(anyhow!(
+                "Not a valid info string: {s}. Failed to parse 'wdl'."
+              ))?
+              .parse()?;
+          }
+
+          info.wdl = Some((wdl[0], wdl[1], wdl[2]));
         }
 
         "currmove" => {
@@ -453,3 +522,34 @@ impl FromStr for Score {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_wdl() {
+    let info: SearchInfo =
+      "depth 12 seldepth 18 score cp 35 wdl 120 850 30 nodes 123456"
+        .parse()
+        .unwrap();
+
+    assert_eq!(info.depth, Some(12));
+    assert_eq!(info.score, Some(Score::Cp(35)));
+    assert_eq!(info.wdl, Some((120, 850, 30)));
+    assert_eq!(info.nodes, Some(123456));
+  }
+
+  #[test]
+  fn wdl_round_trip() {
+    let info = SearchInfo {
+      depth: Some(5),
+      score: Some(Score::Mate(-3)),
+      wdl: Some((0, 0, 1000)),
+      ..SearchInfo::default()
+    };
+
+    let parsed: SearchInfo = info.to_string().parse().unwrap();
+    assert_eq!(parsed, info);
+  }
+}
@@ -15,12 +15,24 @@ pub struct WdlModel {
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct WdlParams {
-  a: f64,
-  b: f64,
+  pub a: f64,
+  pub b: f64,
 }
 
 impl WdlModel {
-  pub fn params(&self, board: &Board) -> WdlParams {
+  /// The smallest material count the model distinguishes
+  pub const MIN_MATERIAL: u32 = 10;
+
+  /// The largest material count the model distinguishes
+  pub const MAX_MATERIAL: u32 = 78;
+
+  /// The material count the model is normalized to. (That is, the material
+  /// count for which `a` is reported as the `NormalizeToPawnValue`.)
+  pub const NORM_MATERIAL: u32 = 58;
+
+  /// Return the material count for the board, clamped to the range the
+  /// model is defined on.
+  pub fn material(board: &Board) -> u32 {
     use PieceType::*;
 
     let mat = board.piece_bbs[Pawn].count()
@@ -29,7 +41,16 @@ impl WdlModel {
       + 5 * board.piece_bbs[Rook].count()
       + 9 * board.piece_bbs[Queen].count();
 
-    let mat = mat.clamp(10, 78) as f64 / 58.0;
+    mat.clamp(Self::MIN_MATERIAL, Self::MAX_MATERIAL)
+  }
+
+  pub fn params(&self, board: &Board) -> WdlParams {
+    self.params_for_material(Self::material(board))
+  }
+
+  /// Return the WDL parameters for a given material count
+  pub fn params_for_material(&self, mat: u32) -> WdlParams {
+    let mat = mat as f64 / Self::NORM_MATERIAL as f64;
 
     WdlParams {
       a: self.a[0]
@@ -45,6 +66,7 @@ impl WdlModel {
 }
 
 impl WdlParams {
+  /// Return the win/draw/loss probabilities as values per mille
   pub fn get_wdl(&self, eval: i32) -> (u64, u64, u64) {
     let win_rate = 1000.0 / (1.0 + f64::exp((-eval as f64 + self.a) / self.b));
     let loss_rate = 1000.0 / (1.0 + f64::exp((eval as f64 + self.a) / self.b));
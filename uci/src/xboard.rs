@@ -0,0 +1,268 @@
+//! Messages of the XBoard/CECP protocol
+//!
+//! Only the subset of the protocol that's needed to play games through a GUI
+//! or tournament manager is supported. See
+//! https://www.gnu.org/software/xboard/engine-intf.html for the full
+//! specification.
+
+use anyhow::anyhow;
+use chess::board::Board;
+use chess::movegen::moves::BareMove;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Messages that can be sent from the client to the engine
+#[derive(Debug, Clone)]
+pub enum XBoardCommand {
+  /// Switch to XBoard mode
+  XBoard,
+
+  /// Announce the protocol version the client speaks
+  Protover(u32),
+
+  /// Reset the board and the clocks, and have the engine play Black
+  New,
+
+  /// Stop playing either side, and only keep track of the moves played
+  Force,
+
+  /// Play the side to move, and start thinking
+  Go,
+
+  /// A move played by the opponent (or entered in force mode)
+  UserMove(BareMove),
+
+  /// Set a conventional time control: `mps` moves per `base`, with an
+  /// increment of `inc` per move. An `mps` of zero means the whole game is
+  /// played in `base`.
+  Level {
+    mps: u32,
+    base: Duration,
+    inc: Duration,
+  },
+
+  /// Set a fixed time per move
+  St(Duration),
+
+  /// Limit the search depth
+  Sd(usize),
+
+  /// Set the engine's remaining time
+  Time(Duration),
+
+  /// Set the opponent's remaining time
+  Otim(Duration),
+
+  /// Take back the last move
+  Undo,
+
+  /// Take back the last two moves
+  Remove,
+
+  /// The game has ended
+  Result(String),
+
+  /// Set up the provided position
+  SetBoard(Board),
+
+  /// Enable thinking output
+  Post,
+
+  /// Disable thinking output
+  NoPost,
+
+  /// Request a `pong` reply, once all previous commands have been processed
+  Ping(u32),
+
+  /// Move now
+  MoveNow,
+
+  /// Set the size of the transposition table, in MB
+  Memory(usize),
+
+  /// Set the number of search threads
+  Cores(usize),
+
+  /// Commands we accept, but don't act on (e.g., `random`, `hard`, `name`)
+  Ignored(String),
+
+  Quit,
+}
+
+impl Display for XBoardCommand {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    use XBoardCommand::*;
+
+    match self {
+      XBoard => write!(f, "xboard"),
+      Protover(version) => write!(f, "protover {version}"),
+      New => write!(f, "new"),
+      Force => write!(f, "force"),
+      Go => write!(f, "go"),
+      UserMove(mv) => write!(f, "usermove {mv}"),
+      Level { mps, base, inc } => {
+        let minutes = base.as_secs() / 60;
+        let seconds = base.as_secs() % 60;
+        let inc = inc.as_secs_f64();
+
+        if seconds == 0 {
+          write!(f, "level {mps} {minutes} {inc}")
+        } else {
+          write!(f, "level {mps} {minutes}:{seconds:0>2} {inc}")
+        }
+      }
+      St(time) => write!(f, "st {}", time.as_secs()),
+      Sd(depth) => write!(f, "sd {depth}"),
+      Time(time) => write!(f, "time {}", time.as_millis() / 10),
+      Otim(time) => write!(f, "otim {}", time.as_millis() / 10),
+      Undo => write!(f, "undo"),
+      Remove => write!(f, "remove"),
+      Result(result) => write!(f, "result {result}"),
+      SetBoard(board) => write!(f, "setboard {}", board.to_fen()),
+      Post => write!(f, "post"),
+      NoPost => write!(f, "nopost"),
+      Ping(n) => write!(f, "ping {n}"),
+      MoveNow => write!(f, "?"),
+      Memory(size) => write!(f, "memory {size}"),
+      Cores(n) => write!(f, "cores {n}"),
+      Ignored(cmd) => write!(f, "{cmd}"),
+      Quit => write!(f, "quit"),
+    }
+  }
+}
+
+impl FromStr for XBoardCommand {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> anyhow::Result<Self> {
+    use XBoardCommand::*;
+    let s = s.trim();
+    let (cmd, remainder) = s.split_once(" ").unwrap_or((s, ""));
+    let remainder = remainder.trim();
+
+    match cmd {
+      "xboard" => Ok(XBoard),
+      "protover" => Ok(Protover(remainder.parse()?)),
+      "new" => Ok(New),
+      "force" => Ok(Force),
+      "go" => Ok(Go),
+      "usermove" => Ok(UserMove(remainder.parse()?)),
+
+      "level" => {
+        let mut parts = remainder.split_whitespace();
+        let mut next =
+          || parts.next().ok_or(anyhow!("Invalid XBoard command: {s}"));
+
+        let mps = next()?.parse()?;
+
+        // The base time is either given as `<minutes>` or as
+        // `<minutes>:<seconds>`
+        let base = next()?;
+        let (minutes, seconds) = base.split_once(':').unwrap_or((base, "0"));
+        let base = Duration::from_secs(
+          60 * minutes.parse::<u64>()? + seconds.parse::<u64>()?,
+        );
+
+        // The increment is given in (possibly fractional) seconds
+        let inc = Duration::try_from_secs_f64(next()?.parse()?)?;
+
+        Ok(Level { mps, base, inc })
+      }
+
+      "st" => Ok(St(Duration::try_from_secs_f64(remainder.parse()?)?)),
+      "sd" => Ok(Sd(remainder.parse()?)),
+
+      "time" => Ok(Time(parse_centiseconds(remainder)?)),
+      "otim" => Ok(Otim(parse_centiseconds(remainder)?)),
+
+      "undo" => Ok(Undo),
+      "remove" => Ok(Remove),
+      "result" => Ok(Result(remainder.to_string())),
+      "setboard" => Ok(SetBoard(remainder.parse()?)),
+      "post" => Ok(Post),
+      "nopost" => Ok(NoPost),
+      "ping" => Ok(Ping(remainder.parse()?)),
+      "?" => Ok(MoveNow),
+      "memory" => Ok(Memory(remainder.parse()?)),
+      "cores" => Ok(Cores(remainder.parse()?)),
+      "quit" => Ok(Quit),
+
+      "random" | "easy" | "hard" | "computer" | "name" | "rating" | "ics"
+      | "accepted" | "rejected" | "draw" => Ok(Ignored(s.to_string())),
+
+      // Older clients send bare moves, without the `usermove` prefix
+      _ => match cmd.parse() {
+        Ok(mv) => Ok(UserMove(mv)),
+        Err(_) => Err(anyhow!("Invalid XBoard command: {cmd}")),
+      },
+    }
+  }
+}
+
+/// Parse a clock time, which XBoard gives in centiseconds. A side that has
+/// overstepped its time gets a negative time, which we treat as zero.
+fn parse_centiseconds(s: &str) -> anyhow::Result<Duration> {
+  let centis = s.parse::<i64>()?.max(0) as u64;
+  Ok(Duration::from_millis(10 * centis))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_level() {
+    let cmd: XBoardCommand = "level 40 2:30 0.5".parse().unwrap();
+
+    let XBoardCommand::Level { mps, base, inc } = cmd else {
+      panic!("Expected a level command, got {cmd}");
+    };
+
+    assert_eq!(mps, 40);
+    assert_eq!(base, Duration::from_secs(150));
+    assert_eq!(inc, Duration::from_millis(500));
+  }
+
+  #[test]
+  fn negative_times_are_rejected() {
+    assert!("st -1".parse::<XBoardCommand>().is_err());
+    assert!("st nan".parse::<XBoardCommand>().is_err());
+    assert!("level 40 5 -1".parse::<XBoardCommand>().is_err());
+  }
+
+  #[test]
+  fn negative_clock_times_are_zero() {
+    assert!(matches!(
+      "time -150".parse(),
+      Ok(XBoardCommand::Time(time)) if time == Duration::ZERO
+    ));
+
+    assert!(matches!(
+      "otim -1".parse(),
+      Ok(XBoardCommand::Otim(time)) if time == Duration::ZERO
+    ));
+
+    assert!(matches!(
+      "time 150".parse(),
+      Ok(XBoardCommand::Time(time)) if time == Duration::from_millis(1500)
+    ));
+  }
+
+  #[test]
+  fn parse_moves() {
+    let mv: BareMove = "e7e8q".parse().unwrap();
+
+    assert!(matches!(
+      "usermove e7e8q".parse(),
+      Ok(XBoardCommand::UserMove(parsed)) if parsed == mv
+    ));
+
+    assert!(matches!(
+      "e7e8q".parse(),
+      Ok(XBoardCommand::UserMove(parsed)) if parsed == mv
+    ));
+
+    assert!("foo".parse::<XBoardCommand>().is_err());
+  }
+}